@@ -1,205 +1,27 @@
 use atomic_float::AtomicF32;
 use nih_plug::prelude::*;
-use nih_plug_egui::{create_egui_editor, egui, widgets, EguiState};
-use std::sync::Arc;
+use nih_plug_egui::{create_egui_editor, egui, widgets};
+use nih_widgets::editor_state::{self, ScalableEguiState};
+use nih_widgets::history::{self, EditHistory, HistoryEditor};
+use nih_widgets::param_menu::{ParamContextMenu, ParamMenuContext, ParamMenuState};
+use nih_widgets::presets::{self, PresetManager};
+use nih_widgets::theme::{self, ThemePickerState, ThemeSelection};
+use std::sync::{Arc, RwLock};
 
 mod dial;
 
 /// The time it takes for the peak meter to decay by 12 dB after switching to complete silence.
 const PEAK_METER_DECAY_MS: f64 = 150.0;
 
-// FIXME: Theme should be `Copy` since it isn't big enough to generate a call to `memcpy`,
-// do this when egui releases a minor version
-/// The colors for a theme variant.
-// #[derive(Debug, Clone, Hash, PartialEq, Eq)]
-// pub struct Theme {
-//     text: egui::Color32,
-
-//     // noninteractive widgets
-//     noninteractive_widget_bg_fill: egui::Color32,
-//     // weak_bg_fill: noninteractive_widget_weak_bg_fill: egui::Color32,
-//     noninteractive_widget_bg_stroke: egui::Color32,
-//     noninteractive_widget_rounding: egui::Color32,
-//     noninteractive_widget_fg_stroke: egui::Color32,
-//     noninteractive_widget_expansion: egui::Color32,
-
-//     // inactive widgets
-//     inactive_widget_bg_fill: egui::Color32,
-//     // weak_bg_fill: inactive_widget_weak_bg_fill: egui::Color32,
-//     inactive_widget_bg_stroke: egui::Color32,
-//      inactive_widget_rounding: egui::Color32,
-//      inactive_widget_fg_stroke: egui::Color32,
-//      inactive_widget_expansion: egui::Color32,
-
-//     // hovered widgets
-//      hovered_widget_bg_fill: egui::Color32,
-//     // weak_bg_fill: hovered_widget_weak_bg_fill: egui::Color32,
-//      hovered_widget_bg_stroke: egui::Color32,
-//      hovered_widget_rounding: egui::Color32,
-//      hovered_widget_fg_stroke: egui::Color32,
-//      hovered_widget_expansion: egui::Color32,
-
-//     // active widgets
-//      active_widget_bg_fill: egui::Color32,
-//     // weak_bg_fill: active_widget_weak_bg_fill: egui::Color32,
-//      active_widget_bg_stroke: egui::Color32,
-//      active_widget_rounding: egui::Color32,
-//      active_widget_fg_stroke: egui::Color32,
-//      active_widget_expansion: egui::Color32,
-
-//     // open widgets
-//      open_widget_bg_fill: egui::Color32,
-//     // weak_bg_fill: open_widget_weak_bg_fill: egui::Color32,
-//      open_widget_bg_stroke: egui::Color32,
-//      open_widget_rounding: egui::Color32,
-//      open_widget_fg_stroke: egui::Color32,
-//      open_widget_expansion: egui::Color32,
-
-// pub rosewater: egui::Color32,
-// pub flamingo: egui::Color32,
-// pub pink: egui::Color32,
-// pub mauve: egui::Color32,
-// pub red: egui::Color32,
-// pub maroon: egui::Color32,
-// pub peach: egui::Color32,
-// pub yellow: egui::Color32,
-// pub green: egui::Color32,
-// pub teal: egui::Color32,
-// pub sky: egui::Color32,
-// pub sapphire: egui::Color32,
-// pub blue: egui::Color32,
-// pub lavender: egui::Color32,
-// pub text: egui::Color32,
-// pub subtext1: egui::Color32,
-// pub subtext0: egui::Color32,
-// pub overlay2: egui::Color32,
-// pub overlay1: egui::Color32,
-// pub overlay0: egui::Color32,
-// pub surface2: egui::Color32,
-// pub surface1: egui::Color32,
-// pub surface0: egui::Color32,
-// pub base: egui::Color32,
-// pub mantle: egui::Color32,
-// pub crust: egui::Color32,
-// }
-
-// pub const DEFAULT_THEME: Theme = Theme {
-//     text: egui::Color32::from_rgb(76, 79, 105),
-
-// rosewater: egui::Color32::from_rgb(220, 138, 120),
-// flamingo: egui::Color32::from_rgb(221, 120, 120),
-// pink: egui::Color32::from_rgb(234, 118, 203),
-// mauve: egui::Color32::from_rgb(136, 57, 239),
-// red: egui::Color32::from_rgb(210, 15, 57),
-// maroon: egui::Color32::from_rgb(230, 69, 83),
-// peach: egui::Color32::from_rgb(254, 100, 11),
-// yellow: egui::Color32::from_rgb(223, 142, 29),
-// green: egui::Color32::from_rgb(64, 160, 43),
-// teal: egui::Color32::from_rgb(23, 146, 153),
-// sky: egui::Color32::from_rgb(4, 165, 229),
-// sapphire: egui::Color32::from_rgb(32, 159, 181),
-// blue: egui::Color32::from_rgb(30, 102, 245),
-// lavender: egui::Color32::from_rgb(114, 135, 253),
-// subtext1: egui::Color32::from_rgb(92, 95, 119),
-// subtext0: egui::Color32::from_rgb(108, 111, 133),
-// overlay2: egui::Color32::from_rgb(124, 127, 147),
-// overlay1: egui::Color32::from_rgb(140, 143, 161),
-// overlay0: egui::Color32::from_rgb(156, 160, 176),
-// surface2: egui::Color32::from_rgb(172, 176, 190),
-// surface1: egui::Color32::from_rgb(188, 192, 204),
-// surface0: egui::Color32::from_rgb(204, 208, 218),
-// base: egui::Color32::from_rgb(239, 241, 245),
-// mantle: egui::Color32::from_rgb(230, 233, 239),
-// crust: egui::Color32::from_rgb(220, 224, 232),
-// };
-
-// pub fn make_widget_visuals(old_widget: egui::style::WidgetVisuals, theme: &Theme) -> egui::style::WidgetVisuals {
-//     old_widget
-// }
-
-/// Apply the given theme to a [`Context`](egui::Context).
-pub fn set_theme(ctx: &egui::Context) {
-    let old = ctx.style().visuals.clone();
-    ctx.set_visuals(egui::Visuals {
-        // panel_fill: egui::Color32::from_rgb(0, 0, 0),
-        // override_text_color: old.override_text_color,
-        // widgets: egui::style::Widgets {
-        //     noninteractive: egui::style::WidgetVisuals {
-        //         bg_stroke: egui::Stroke {
-        //             color: egui::Color32::from_rgb(0, 255, 0),
-        //             width: 1.0,
-        //         },
-        //         ..old.widgets.noninteractive
-        //     },
-        //     inactive: egui::style::WidgetVisuals {
-        //         bg_stroke: egui::Stroke {
-        //             color: egui::Color32::from_rgb(0, 255, 0),
-        //             width: 1.0,
-        //         },
-        //         ..old.widgets.inactive
-        //     },
-        //     hovered: egui::style::WidgetVisuals {
-        //         bg_stroke: egui::Stroke {
-        //             color: egui::Color32::from_rgb(0, 255, 0),
-        //             width: 1.0,
-        //         },
-        //         ..old.widgets.hovered
-        //     },
-        //     active: egui::style::WidgetVisuals {
-        //         bg_stroke: egui::Stroke {
-        //             color: egui::Color32::from_rgb(0, 255, 0),
-        //             width: 1.0,
-        //         },
-        //         ..old.widgets.active
-        //     },
-        //     open: egui::style::WidgetVisuals {
-        //         bg_stroke: egui::Stroke {
-        //             color: egui::Color32::from_rgb(0, 255, 0),
-        //             width: 1.0,
-        //         },
-        //         ..old.widgets.open
-        //     },
-        // },
-        // selection: egui::style::Selection { ..old.selection },
-        // hyperlink_color: theme.hyperlink_color,
-        // faint_bg_color: theme.faint_bg_color,
-        // extreme_bg_color: theme.extreme_bg_color,
-        // code_bg_color: theme.code_bg_color,
-        // warn_fg_color: theme.warn_fg_color,
-        // error_fg_color: theme.error_fg_color,
-        // window_rounding
-        // window_shadow,
-        // window_fill: egui::Color32::from_rgb(0, 0, 0),
-        // window_stroke: egui::Stroke {
-        //     color: theme.overlay1,
-        //     ..old.window_stroke
-        // },
-        // menu_rounding
-        // panel_fill
-        // popup_shadow
-        // resize_corner_size
-        // text_cursor_width
-        // text_cursor_preview
-        // clip_rect_margin
-        // button_frame
-        // collapsing_header_frame
-        // indent_has_left_vline
-        // striped
-        // slider_trailing_fill
-
-        // panel_fill: theme.base,
-
-        // window_shadow: epaint::Shadow {
-        //     color: theme.base,
-        //     ..old.window_shadow
-        // },
-        // popup_shadow: epaint::Shadow {
-        //     color: theme.base,
-        //     ..old.popup_shadow
-        // },
-        ..old
-    });
-}
+/// The factory presets compiled into the plugin.
+const FACTORY_PRESETS: &[&str] = &[
+    include_str!("../presets/Unity.json"),
+    include_str!("../presets/Boost.json"),
+    include_str!("../presets/Cut.json"),
+];
+/// The persisted fields that belong to the editor instead of to the sound. These are not stored in
+/// presets.
+const PRESET_IGNORED_FIELDS: &[&str] = &["editor-state", "theme", "param-menu"];
 
 /// This is mostly identical to the gain example, minus some fluff, and with a GUI.
 pub struct Gain {
@@ -213,14 +35,30 @@ pub struct Gain {
     ///
     /// This is stored as voltage gain.
     peak_meter: Arc<AtomicF32>,
+    /// The editor's undo history and A/B snapshots. This lives here instead of in the editor so it
+    /// survives closing and reopening the editor.
+    edit_history: Arc<EditHistory>,
+}
+
+/// The editor's state that's kept between frames.
+struct EditorUserState {
+    theme_picker: ThemePickerState,
+    presets: PresetManager,
 }
 
 #[derive(Params)]
 pub struct GainParams {
-    /// The editor state, saved together with the parameter state so the custom scaling can be
+    /// The editor's size and scale, saved together with the parameter state so they can be
     /// restored.
     #[persist = "editor-state"]
-    editor_state: Arc<EguiState>,
+    editor_state: Arc<ScalableEguiState>,
+    /// The editor's color theme. This is stored together with the parameter state so the editor
+    /// looks the same when the plugin is reopened.
+    #[persist = "theme"]
+    theme: RwLock<ThemeSelection>,
+    /// MIDI learn mappings and parameter locks set through the parameter context menus.
+    #[persist = "param-menu"]
+    param_menu: ParamMenuState,
 
     #[id = "gain"]
     pub gain: FloatParam,
@@ -237,6 +75,7 @@ impl Default for Gain {
 
             peak_meter_decay_weight: 1.0,
             peak_meter: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
+            edit_history: Arc::new(EditHistory::default()),
         }
     }
 }
@@ -245,7 +84,9 @@ impl Default for GainParams {
     fn default() -> Self {
         Self {
             // set window size
-            editor_state: EguiState::from_size(600, 600),
+            editor_state: ScalableEguiState::from_size(600, 600),
+            theme: RwLock::new(ThemeSelection::Light),
+            param_menu: ParamMenuState::default(),
 
             // See the main gain example for more details
             gain: FloatParam::new(
@@ -287,6 +128,8 @@ impl Plugin for Gain {
         },
     ];
 
+    // MIDI CCs can be mapped to parameters through MIDI learn
+    const MIDI_INPUT: MidiConfig = MidiConfig::MidiCCs;
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
 
     type SysExMessage = ();
@@ -298,18 +141,31 @@ impl Plugin for Gain {
 
     fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
         let params = self.params.clone();
+        let build_params = self.params.clone();
         let peak_meter = self.peak_meter.clone();
+        let edit_history = self.edit_history.clone();
         create_egui_editor(
             // State
-            self.params.editor_state.clone(),
+            self.params.editor_state.egui_state(),
             // User state
-            (),
+            EditorUserState {
+                theme_picker: ThemePickerState::default(),
+                presets: PresetManager::new(Self::NAME, FACTORY_PRESETS, PRESET_IGNORED_FIELDS),
+            },
             // Build
-            |egui_ctx, _state| {
-                set_theme(egui_ctx);
+            move |egui_ctx, _state| {
+                theme::apply_selected_theme(egui_ctx, &build_params.theme);
+                editor_state::apply_scale(egui_ctx, build_params.editor_state.scale());
             },
             // Update
-            move |egui_ctx, setter, _state| {
+            move |egui_ctx, setter, state| {
+                params.param_menu.handle_midi_input(&*params, setter);
+                history::handle_shortcuts(egui_ctx, &edit_history, setter);
+                let menu_context = ParamMenuContext {
+                    params: &*params,
+                    state: &params.param_menu,
+                };
+
                 let my_frame = egui::containers::Frame {
                     inner_margin: egui::style::Margin {
                         left: 10.,
@@ -337,7 +193,7 @@ impl Plugin for Gain {
                     stroke: egui::Stroke::new(2.0, egui::Color32::GOLD),
                 };
 
-                let my_frame_2 = egui::Frame::none().fill(egui::Color32::WHITE);
+                let my_frame_2 = egui::Frame::none().fill(egui_ctx.style().visuals.panel_fill);
                 // egui::CentralPanel::default()
                 //     .frame(my_frame)
                 //     .show(ctx, |ui| {});
@@ -352,12 +208,45 @@ impl Plugin for Gain {
                         // display and modify the parameter from the parametr itself
                         // It's not yet fully implemented, as the text is missing.
                         ui.heading("DISTORTOooo");
+                        presets::preset_bar(
+                            ui,
+                            &mut state.presets,
+                            setter,
+                            edit_history.change_count(),
+                            Some(&params.param_menu),
+                        );
+                        history::history_bar(ui, &edit_history, setter);
+                        theme::theme_picker(ui, &params.theme, &mut state.theme_picker);
+                        editor_state::scale_picker(ui, &params.editor_state, setter);
 
+                        // Right clicking the slider and the dials opens a context menu for the
+                        // parameter
                         ui.label("Gain");
-                        ui.add(widgets::ParamSlider::for_param(&params.gain, setter));
-
-                        ui.label("Dial");
-                        ui.add(dial::Dial::for_param(&params.gain, setter));
+                        let gain_slider =
+                            ui.add(widgets::ParamSlider::for_param(&params.gain, setter));
+                        ParamContextMenu::for_param(&params.gain, setter)
+                            .with_context(menu_context)
+                            .show_with_value_field(&gain_slider);
+
+                        // The dials wrap onto the next row when the editor gets too narrow
+                        ui.horizontal_wrapped(|ui| {
+                            ui.vertical(|ui| {
+                                ui.label("Gain");
+                                ui.add(
+                                    nih_widgets::Dial::for_param(&params.gain, setter)
+                                        .with_skin(&dial::DistortoDialSkin)
+                                        .with_menu_context(menu_context),
+                                );
+                            });
+                            ui.vertical(|ui| {
+                                ui.label("Something");
+                                ui.add(
+                                    nih_widgets::Dial::for_param(&params.some_int, setter)
+                                        .with_skin(&dial::DistortoDialSkin)
+                                        .with_menu_context(menu_context),
+                                );
+                            });
+                        });
 
                         // TODO: Add a proper custom widget instead of reusing a progress bar
                         let peak_meter =
@@ -375,8 +264,12 @@ impl Plugin for Gain {
                                 .text(peak_meter_text),
                         );
                     });
+
+                editor_state::resize_corner(egui_ctx, &params.editor_state, setter);
             },
         )
+        // Every parameter change made through the editor is recorded for undo and redo
+        .map(|editor| HistoryEditor::wrap(editor, self.edit_history.clone()))
     }
 
     fn initialize(
@@ -398,8 +291,17 @@ impl Plugin for Gain {
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
+        while let Some(event) = context.next_event() {
+            // Parameters mapped with MIDI learn can only be changed through the editor
+            if let NoteEvent::MidiCC { cc, value, .. } = event {
+                if self.params.editor_state.is_open() {
+                    self.params.param_menu.process_midi_cc(cc, value);
+                }
+            }
+        }
+
         for channel_samples in buffer.iter_samples() {
             let mut amplitude = 0.0;
             let num_samples = channel_samples.len();
@@ -18,8 +18,8 @@ use nih_plug::debug::*;
 use std::f32::consts;
 use std::ops::{Add, Mul, Sub};
 
-/// A simple biquad filter with functions for generating coefficients for second order low-pass and
-/// high-pass filters.
+/// A simple biquad filter with functions for generating coefficients for second order band-pass and
+/// shelving filters.
 ///
 /// Based on <https://en.wikipedia.org/wiki/Digital_biquad_filter#Transposed_direct_forms>.
 ///
@@ -127,6 +127,63 @@ impl<T: SimdType> BiquadCoefficients<T> {
 
         Self::from_f32s(BiquadCoefficients { b0, b1, b2, a1, a2 })
     }
+
+    /// Compute the coefficients for a low-shelf filter with a shelf slope of 1. `gain_db` is the
+    /// gain below `frequency`, in decibels.
+    ///
+    /// Based on <http://shepazu.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>.
+    pub fn low_shelf(sample_rate: f32, frequency: f32, gain_db: f32) -> Self {
+        nih_debug_assert!(sample_rate > 0.0);
+        nih_debug_assert!(frequency > 0.0);
+        nih_debug_assert!(frequency < sample_rate / 2.0);
+
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let omega0 = consts::TAU * (frequency / sample_rate);
+        let cos_omega0 = omega0.cos();
+        // With a shelf slope of 1 the `(A + 1/A) * (1/S - 1)` term drops out
+        let two_sqrt_a_alpha = 2.0 * a.sqrt() * (omega0.sin() / 2.0 * consts::SQRT_2);
+
+        let a0 = (a + 1.0) + (a - 1.0) * cos_omega0 + two_sqrt_a_alpha;
+        let b0 = (a * ((a + 1.0) - (a - 1.0) * cos_omega0 + two_sqrt_a_alpha)) / a0;
+        let b1 = (2.0 * a * ((a - 1.0) - (a + 1.0) * cos_omega0)) / a0;
+        let b2 = (a * ((a + 1.0) - (a - 1.0) * cos_omega0 - two_sqrt_a_alpha)) / a0;
+        let a1 = (-2.0 * ((a - 1.0) + (a + 1.0) * cos_omega0)) / a0;
+        let a2 = ((a + 1.0) + (a - 1.0) * cos_omega0 - two_sqrt_a_alpha) / a0;
+
+        Self::from_f32s(BiquadCoefficients { b0, b1, b2, a1, a2 })
+    }
+
+    /// Compute the coefficients for a high-shelf filter with a shelf slope of 1. `gain_db` is the
+    /// gain above `frequency`, in decibels.
+    ///
+    /// Based on <http://shepazu.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>.
+    pub fn high_shelf(sample_rate: f32, frequency: f32, gain_db: f32) -> Self {
+        nih_debug_assert!(sample_rate > 0.0);
+        nih_debug_assert!(frequency > 0.0);
+        nih_debug_assert!(frequency < sample_rate / 2.0);
+
+        let a = 10.0f32.powf(gain_db / 40.0);
+        let omega0 = consts::TAU * (frequency / sample_rate);
+        let cos_omega0 = omega0.cos();
+        let two_sqrt_a_alpha = 2.0 * a.sqrt() * (omega0.sin() / 2.0 * consts::SQRT_2);
+
+        let a0 = (a + 1.0) - (a - 1.0) * cos_omega0 + two_sqrt_a_alpha;
+        let b0 = (a * ((a + 1.0) + (a - 1.0) * cos_omega0 + two_sqrt_a_alpha)) / a0;
+        let b1 = (-2.0 * a * ((a - 1.0) + (a + 1.0) * cos_omega0)) / a0;
+        let b2 = (a * ((a + 1.0) + (a - 1.0) * cos_omega0 - two_sqrt_a_alpha)) / a0;
+        let a1 = (2.0 * ((a - 1.0) - (a + 1.0) * cos_omega0)) / a0;
+        let a2 = ((a + 1.0) - (a - 1.0) * cos_omega0 - two_sqrt_a_alpha) / a0;
+
+        Self::from_f32s(BiquadCoefficients { b0, b1, b2, a1, a2 })
+    }
+}
+
+impl BiquadCoefficients<f32> {
+    /// Whether both poles of the filter lie strictly inside of the unit circle. This is the
+    /// stability triangle for second order sections: `|a2| < 1` and `|a1| < 1 + a2`.
+    pub fn is_stable(&self) -> bool {
+        self.a2.abs() < 1.0 && self.a1.abs() < 1.0 + self.a2
+    }
 }
 
 impl SimdType for f32 {
@@ -143,3 +200,74 @@ impl SimdType for f32 {
 //         f32x2::splat(value)
 //     }
 // }
+
+/// Run an impulse through `num_stages` cascaded copies of a filter and return the absolute peak of
+/// the entire impulse response and of its last `tail_length` samples.
+#[cfg(test)]
+fn cascaded_impulse_response_peaks(
+    coefficients: BiquadCoefficients<f32>,
+    num_stages: usize,
+    num_samples: usize,
+    tail_length: usize,
+) -> (f32, f32) {
+    let mut filters = vec![Biquad::<f32>::default(); num_stages];
+    for filter in &mut filters {
+        filter.coefficients = coefficients;
+    }
+
+    let mut peak = 0.0f32;
+    let mut tail_peak = 0.0f32;
+    for i in 0..num_samples {
+        let mut sample = if i == 0 { 1.0 } else { 0.0 };
+        for filter in &mut filters {
+            sample = filter.process(sample);
+        }
+
+        assert!(sample.is_finite(), "Non-finite output at sample {i}");
+        peak = peak.max(sample.abs());
+        if i >= num_samples - tail_length {
+            tail_peak = tail_peak.max(sample.abs());
+        }
+    }
+
+    (peak, tail_peak)
+}
+
+#[test]
+fn test_bandpass_stable_at_extreme_q() {
+    for q in [0.00001, 0.1, 30.00001] {
+        let coefficients = BiquadCoefficients::<f32>::bandpass(44100.0, 5500.0, q);
+        assert!(coefficients.is_stable(), "Unstable at Q = {q}");
+
+        let (peak, tail_peak) = cascaded_impulse_response_peaks(coefficients, 8, 44100 * 4, 1024);
+        assert!(tail_peak < peak * 0.01, "Q = {q}: {tail_peak} vs. {peak}");
+    }
+}
+
+#[test]
+fn test_bandpass_stable_near_nyquist() {
+    for frequency in [20000.0, 21000.0, 22000.0] {
+        for q in [0.00001, 30.00001] {
+            let coefficients = BiquadCoefficients::<f32>::bandpass(44100.0, frequency, q);
+            assert!(
+                coefficients.is_stable(),
+                "Unstable at {frequency} Hz, Q = {q}"
+            );
+
+            let (peak, tail_peak) =
+                cascaded_impulse_response_peaks(coefficients, 8, 44100 * 4, 1024);
+            assert!(
+                tail_peak < peak * 0.01,
+                "{frequency} Hz, Q = {q}: {tail_peak} vs. {peak}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_shelves_stable_near_nyquist() {
+    for gain_db in [-12.0, 0.0, 12.0] {
+        assert!(BiquadCoefficients::<f32>::low_shelf(44100.0, 22000.0, gain_db).is_stable());
+        assert!(BiquadCoefficients::<f32>::high_shelf(44100.0, 22000.0, gain_db).is_stable());
+    }
+}
@@ -18,34 +18,67 @@ use nih_plug::prelude::*;
 use std::sync::Arc;
 
 mod filter;
-
-/// The length of silence after which the signal should start fading out into silence. This is to
-/// avoid outputting a constant DC signal.
-const SILENCE_FADEOUT_START_MS: f32 = 1000.0;
-/// The time it takes after `SILENCE_FADEOUT_START_MS` to fade from a full scale DC signal to silence.
-const SILENCE_FADEOUT_END_MS: f32 = SILENCE_FADEOUT_START_MS + 1000.0;
-
-/// The center frequency for our optional bandpass filter, in Hertz.
-const BP_FREQUENCY: f32 = 5500.0;
+mod limiter;
+mod silence;
+
+/// The default center frequency for our optional bandpass filter, in Hertz.
+const DEFAULT_BP_FREQUENCY: f32 = 5500.0;
+/// The maximum number of cascaded band-pass filters. The filter state for all of these is allocated
+/// up front in `initialize()` so the `Filter Stages` parameter can be changed without allocating.
+const MAX_BP_STAGES: usize = 8;
+/// The filter frequencies are clamped to this fraction of the Nyquist frequency so the coefficients
+/// stay well-behaved at lower sample rates.
+const MAX_FREQUENCY_NYQUIST_FRACTION: f32 = 0.95;
+
+/// The maximum lookahead for the limiter used in [`Mode::Clean`], in milliseconds.
+const MAX_LIMITER_LOOKAHEAD_MS: f32 = 20.0;
+/// The maximum number of channels in any of the plugin's audio IO layouts. Used to copy a frame of
+/// samples to the stack for the limiter.
+const MAX_NUM_CHANNELS: usize = 2;
 
 struct LoudnessWarWinner {
     params: Arc<LoudnessWarWinnerParams>,
 
     sample_rate: f32,
-    /// To win even harder we'll band-pass the signal around 5.5 kHz when the `WIN HARDER` parameter
-    /// is enabled. And we'll cascade four of these filters while we're at it.
-    bp_filters: Vec<[filter::Biquad<f32>; 4]>,
-
-    /// The number of samples since the last non-zero sample. This is used to fade into silence when
-    /// the input has also been silent for a while instead of outputting a constant DC signal. All
-    /// channels need to be silent for a signal to be considered silent.
-    num_silent_samples: u32,
-    /// `SILENCE_FADEOUT_START_MS` converted to samples.
-    silence_fadeout_start_samples: u32,
-    /// `SILENCE_FADEOUT_END_MS` converted to samples.
-    silence_fadeout_end_samples: u32,
-    /// The length of the fadeout, in samples.
-    silence_fadeout_length_samples: u32,
+    /// To win even harder we'll band-pass the signal around 5.5 kHz (by default) when the `WIN
+    /// HARDER` parameter is enabled. And we'll cascade up to eight of these filters while we're at
+    /// it. Only the first `num_active_bp_stages` filters for each channel are used.
+    bp_filters: Vec<[filter::Biquad<f32>; MAX_BP_STAGES]>,
+    /// The number of band-pass filters that were used during the last sample. When the `Filter
+    /// Stages` parameter increases the newly engaged filters are reset first so they don't start
+    /// with stale state.
+    num_active_bp_stages: usize,
+    /// A low-shelf and a high-shelf filter per channel, pivoting around the band-pass filter's
+    /// center frequency. Together these form the tilt EQ.
+    tilt_filters: Vec<[filter::Biquad<f32>; 2]>,
+    /// Whether the tilt filters were used during the last sample. They're bypassed at 0 dB, and
+    /// they're reset when they get engaged again so they don't start with stale state.
+    tilt_filters_active: bool,
+
+    /// Used to fade into silence when the input has also been silent for a while instead of
+    /// outputting a constant DC signal. All channels need to be below the threshold for a signal to
+    /// be considered silent.
+    silence_gate: silence::SilenceGate,
+
+    /// The lookahead limiter used in [`Mode::Clean`].
+    limiter: limiter::Limiter,
+    /// The mode used during the last block. The limiter is reset when switching to
+    /// [`Mode::Clean`] so it doesn't play out the audio left over from the last time it was used.
+    mode: Mode,
+    /// The latency we last reported to the host, in samples. The limiter's latency is only
+    /// reported in [`Mode::Clean`].
+    reported_latency_samples: u32,
+}
+
+/// How the signal is made louder.
+#[derive(Enum, Debug, PartialEq, Eq, Clone, Copy)]
+enum Mode {
+    /// Turn everything into a full scale square wave. The original and most victorious mode.
+    #[id = "square"]
+    Square,
+    /// Drive the signal into a lookahead true peak limiter. This adds latency.
+    #[id = "clean"]
+    Clean,
 }
 
 #[derive(Params)]
@@ -53,12 +86,56 @@ struct LoudnessWarWinnerParams {
     /// The output gain, set to -24 dB by default because oof ouchie.
     #[id = "output"]
     output_gain: FloatParam,
+    /// Whether to square off the signal or to limit it.
+    #[id = "mode"]
+    mode: EnumParam<Mode>,
 
-    /// When non-zero, this engages a bandpass filter around 5.5 kHz to help with the LUFS
-    /// K-Weighting. This is a fraction in `[0, 1]`. [`LoudnessWarWinner::update_bp_filters()`]
+    /// When non-zero, this engages a bandpass filter around `bp_frequency` to help with the LUFS
+    /// K-Weighting. This is a fraction in `[0, 1]`. [`LoudnessWarWinner::update_filters()`]
     /// calculates the filter's Q value basedo n this.
     #[id = "powah"]
     win_harder_factor: FloatParam,
+    /// The center frequency for the `WIN HARDER` band-pass filter. This is also the pivot
+    /// frequency for the tilt EQ.
+    #[id = "bp_freq"]
+    bp_frequency: FloatParam,
+    /// The number of cascaded band-pass filters, in `[1, MAX_BP_STAGES]`.
+    #[id = "bp_stages"]
+    bp_stages: IntParam,
+    /// Tilts the spectrum around `bp_frequency` before the signal gets squared off, in decibels.
+    /// Positive values boost the highs and cut the lows. The tilt EQ is bypassed at 0 dB.
+    #[id = "tilt"]
+    tilt_db: FloatParam,
+
+    /// Input signals with a peak value below this threshold, in dBFS, are considered silent. After
+    /// `silence_hold_ms` of silence the output fades out. At the minimum of -120 dBFS only exact
+    /// digital silence counts as silent.
+    #[id = "sil_thresh"]
+    silence_threshold_db: FloatParam,
+    /// Once the output has started fading out, the input needs to exceed the threshold again to
+    /// count as signal. Until then it only needs to stay above `threshold - hysteresis`.
+    #[id = "sil_hyst"]
+    silence_hysteresis_db: FloatParam,
+    /// How long the input needs to be silent before the output starts fading out, in milliseconds.
+    #[id = "sil_hold"]
+    silence_hold_ms: FloatParam,
+
+    /// The gain applied before the limiter in [`Mode::Clean`].
+    #[id = "lim_drive"]
+    limiter_drive: FloatParam,
+    /// The maximum true peak level of the limiter's output, in dBTP.
+    #[id = "lim_ceil"]
+    limiter_ceiling_db: FloatParam,
+    /// The limiter's lookahead time, in milliseconds. This determines the latency in
+    /// [`Mode::Clean`], so it can't be automated.
+    #[id = "lim_look"]
+    limiter_lookahead_ms: FloatParam,
+    /// The limiter's release time, in milliseconds.
+    #[id = "lim_rel"]
+    limiter_release_ms: FloatParam,
+    /// Stretch the release time when the limiter is constantly reducing gain.
+    #[id = "lim_auto"]
+    limiter_auto_release: BoolParam,
 }
 
 impl Default for LoudnessWarWinner {
@@ -68,11 +145,15 @@ impl Default for LoudnessWarWinner {
 
             sample_rate: 1.0,
             bp_filters: Vec::new(),
+            num_active_bp_stages: 0,
+            tilt_filters: Vec::new(),
+            tilt_filters_active: false,
+
+            silence_gate: silence::SilenceGate::default(),
 
-            num_silent_samples: 0,
-            silence_fadeout_start_samples: 0,
-            silence_fadeout_end_samples: 0,
-            silence_fadeout_length_samples: 0,
+            limiter: limiter::Limiter::new(1.0, 0, 0.0),
+            mode: Mode::Square,
+            reported_latency_samples: 0,
         }
     }
 }
@@ -93,6 +174,7 @@ impl Default for LoudnessWarWinnerParams {
             .with_unit(" dB")
             .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
             .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+            mode: EnumParam::new("Mode", Mode::Square),
             win_harder_factor: FloatParam::new(
                 "WIN HARDER",
                 0.0,
@@ -108,6 +190,116 @@ impl Default for LoudnessWarWinnerParams {
             .with_unit("%")
             .with_value_to_string(formatters::v2s_f32_percentage(0))
             .with_string_to_value(formatters::s2v_f32_percentage()),
+            bp_frequency: FloatParam::new(
+                "Filter Frequency",
+                DEFAULT_BP_FREQUENCY,
+                FloatRange::Skewed {
+                    min: 500.0,
+                    max: 20_000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(30.0))
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            bp_stages: IntParam::new(
+                "Filter Stages",
+                4,
+                IntRange::Linear {
+                    min: 1,
+                    max: MAX_BP_STAGES as i32,
+                },
+            ),
+            tilt_db: FloatParam::new(
+                "Tilt",
+                0.0,
+                FloatRange::Linear {
+                    min: -12.0,
+                    max: 12.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(30.0))
+            .with_step_size(0.1)
+            .with_unit(" dB"),
+
+            silence_threshold_db: FloatParam::new(
+                "Silence Threshold",
+                -70.0,
+                FloatRange::Linear {
+                    min: -120.0,
+                    max: -24.0,
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" dBFS"),
+            silence_hysteresis_db: FloatParam::new(
+                "Silence Hysteresis",
+                6.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 24.0,
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" dB"),
+            silence_hold_ms: FloatParam::new(
+                "Silence Hold",
+                1000.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 5000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_step_size(1.0)
+            .with_unit(" ms"),
+
+            limiter_drive: FloatParam::new(
+                "Limiter Drive",
+                util::db_to_gain(12.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(0.0),
+                    max: util::db_to_gain(36.0),
+                    factor: FloatRange::gain_skew_factor(0.0, 36.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(10.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+            limiter_ceiling_db: FloatParam::new(
+                "Limiter Ceiling",
+                -1.0,
+                FloatRange::Linear {
+                    min: -12.0,
+                    max: 0.0,
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" dBTP"),
+            limiter_lookahead_ms: FloatParam::new(
+                "Limiter Lookahead",
+                5.0,
+                FloatRange::Linear {
+                    min: 0.1,
+                    max: MAX_LIMITER_LOOKAHEAD_MS,
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms")
+            .non_automatable(),
+            limiter_release_ms: FloatParam::new(
+                "Limiter Release",
+                100.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms"),
+            limiter_auto_release: BoolParam::new("Limiter Auto Release", true),
         }
     }
 }
@@ -144,7 +336,7 @@ impl Plugin for LoudnessWarWinner {
         &mut self,
         audio_io_layout: &AudioIOLayout,
         buffer_config: &BufferConfig,
-        _context: &mut impl InitContext<Self>,
+        context: &mut impl InitContext<Self>,
     ) -> bool {
         self.sample_rate = buffer_config.sample_rate;
 
@@ -152,16 +344,25 @@ impl Plugin for LoudnessWarWinner {
             .main_output_channels
             .expect("Plugin does not have a main output")
             .get() as usize;
-        self.bp_filters
-            .resize(num_output_channels, [filter::Biquad::default(); 4]);
-        self.update_bp_filters();
-
-        self.silence_fadeout_start_samples =
-            (SILENCE_FADEOUT_START_MS / 1000.0 * buffer_config.sample_rate).round() as u32;
-        self.silence_fadeout_end_samples =
-            (SILENCE_FADEOUT_END_MS / 1000.0 * buffer_config.sample_rate).round() as u32;
-        self.silence_fadeout_length_samples =
-            self.silence_fadeout_end_samples - self.silence_fadeout_start_samples;
+        self.bp_filters.resize(
+            num_output_channels,
+            [filter::Biquad::default(); MAX_BP_STAGES],
+        );
+        self.tilt_filters
+            .resize(num_output_channels, [filter::Biquad::default(); 2]);
+        self.num_active_bp_stages = self.params.bp_stages.value() as usize;
+        self.update_filters(true, true);
+
+        self.silence_gate = silence::SilenceGate::new(buffer_config.sample_rate);
+
+        self.limiter = limiter::Limiter::new(
+            buffer_config.sample_rate,
+            num_output_channels,
+            MAX_LIMITER_LOOKAHEAD_MS,
+        );
+        self.update_limiter();
+        self.reported_latency_samples = self.latency_samples();
+        context.set_latency_samples(self.reported_latency_samples);
 
         true
     }
@@ -172,62 +373,130 @@ impl Plugin for LoudnessWarWinner {
                 filter.reset();
             }
         }
+        for filters in &mut self.tilt_filters {
+            for filter in filters {
+                filter.reset();
+            }
+        }
 
         // Start with silence, so we don't immediately output a DC signal if the plugin is inserted
         // on a silent channel
-        self.num_silent_samples = self.silence_fadeout_end_samples;
+        self.silence_gate.reset();
+        self.limiter.reset();
     }
 
     fn process(
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
+        // These are cheap to update, so there's no need to check whether they have changed
+        self.silence_gate.set_thresholds(
+            self.params.silence_threshold_db.value(),
+            self.params.silence_hysteresis_db.value(),
+        );
+        self.silence_gate
+            .set_hold_time(self.params.silence_hold_ms.value());
+        self.update_limiter();
+
+        // Switching modes or changing the limiter's lookahead changes the latency
+        let mode = self.params.mode.value();
+        self.update_mode(mode);
+        let latency_samples = self.latency_samples();
+        if latency_samples != self.reported_latency_samples {
+            self.reported_latency_samples = latency_samples;
+            context.set_latency_samples(latency_samples);
+        }
+
         for mut channel_samples in buffer.iter_samples() {
             let output_gain = self.params.output_gain.smoothed.next();
-
-            // When the `WIN_HARDER` parameter is engaged, we'll band-pass the signal around 5 kHz
-            if self.params.win_harder_factor.smoothed.is_smoothing() {
-                self.update_bp_filters();
+            let limiter_drive = self.params.limiter_drive.smoothed.next();
+
+            // When the `WIN_HARDER` parameter is engaged, we'll band-pass the signal around the
+            // configured center frequency
+            let frequency_smoothing = self.params.bp_frequency.smoothed.is_smoothing();
+            let update_bp_filters =
+                frequency_smoothing || self.params.win_harder_factor.smoothed.is_smoothing();
+            let update_tilt_filters =
+                frequency_smoothing || self.params.tilt_db.smoothed.is_smoothing();
+            if update_bp_filters || update_tilt_filters {
+                self.update_filters(update_bp_filters, update_tilt_filters);
             }
             let apply_bp_filters = self.params.win_harder_factor.smoothed.previous_value() > 0.0;
+            let apply_tilt_filters = self.params.tilt_db.smoothed.previous_value() != 0.0;
+
+            if apply_tilt_filters && !self.tilt_filters_active {
+                for filters in &mut self.tilt_filters {
+                    for filter in filters {
+                        filter.reset();
+                    }
+                }
+            }
+            self.tilt_filters_active = apply_tilt_filters;
 
-            let mut is_silent = true;
-            for (sample, bp_filters) in channel_samples.iter_mut().zip(&mut self.bp_filters) {
-                is_silent &= *sample == 0.0;
+            let num_bp_stages = self.params.bp_stages.value() as usize;
+            if num_bp_stages > self.num_active_bp_stages {
+                for filters in &mut self.bp_filters {
+                    for filter in &mut filters[self.num_active_bp_stages..num_bp_stages] {
+                        filter.reset();
+                    }
+                }
+            }
+            self.num_active_bp_stages = num_bp_stages;
 
-                // For better performance we can move this conditional to an outer loop, but right
+            let mut peak = 0.0f32;
+            for ((sample, bp_filters), tilt_filters) in channel_samples
+                .iter_mut()
+                .zip(&mut self.bp_filters)
+                .zip(&mut self.tilt_filters)
+            {
+                peak = peak.max(sample.abs());
+
+                // For better performance we can move these conditionals to an outer loop, but right
                 // now it shouldn't be too bad
                 if apply_bp_filters {
-                    for filter in bp_filters {
+                    for filter in &mut bp_filters[..num_bp_stages] {
+                        *sample = filter.process(*sample);
+                    }
+                }
+                if apply_tilt_filters {
+                    for filter in tilt_filters {
                         *sample = filter.process(*sample);
                     }
                 }
 
-                *sample = if *sample >= 0.0 { 1.0 } else { -1.0 } * output_gain;
+                match mode {
+                    Mode::Square => *sample = if *sample >= 0.0 { 1.0 } else { -1.0 } * output_gain,
+                    Mode::Clean => *sample *= limiter_drive,
+                }
             }
 
             // To avoid outputting a constant DC signal even when there's no input we'll slowly fade
-            // into silence
-            if is_silent {
-                self.num_silent_samples += 1;
-
-                if self.num_silent_samples >= self.silence_fadeout_end_samples {
-                    for sample in channel_samples {
-                        *sample = 0.0;
+            // into silence, and fade back in when the signal returns. The limiter doesn't have
+            // this problem, but the gate still needs to follow the input when switching modes.
+            let silence_gain = self.silence_gate.next(peak);
+            match mode {
+                Mode::Square => {
+                    if silence_gain < 1.0 {
+                        for sample in channel_samples {
+                            *sample *= silence_gain;
+                        }
                     }
-                } else if self.num_silent_samples >= self.silence_fadeout_start_samples {
-                    let fadeout_gain = 1.0
-                        - ((self.num_silent_samples - self.silence_fadeout_start_samples) as f32
-                            / self.silence_fadeout_length_samples as f32);
+                }
+                Mode::Clean => {
+                    let num_channels = channel_samples.len();
+                    let mut frame = [0.0; MAX_NUM_CHANNELS];
+                    for (frame_sample, sample) in frame.iter_mut().zip(channel_samples.iter_mut()) {
+                        *frame_sample = *sample;
+                    }
+
+                    self.limiter.process(&mut frame[..num_channels]);
 
-                    for sample in channel_samples {
-                        *sample *= fadeout_gain;
+                    for (sample, frame_sample) in channel_samples.into_iter().zip(frame) {
+                        *sample = frame_sample * output_gain;
                     }
                 }
-            } else {
-                self.num_silent_samples = 0;
             }
         }
 
@@ -236,18 +505,75 @@ impl Plugin for LoudnessWarWinner {
 }
 
 impl LoudnessWarWinner {
-    /// Update the band-pass filters. This should only be called during processing if
-    /// `self.params.win_harder_factor.smoothed.is_smoothing()`.
-    fn update_bp_filters(&mut self) {
-        let q = 0.00001 + (self.params.win_harder_factor.smoothed.next() * 30.0);
+    /// The plugin's current latency. This is the limiter's latency in [`Mode::Clean`], and zero
+    /// otherwise.
+    fn latency_samples(&self) -> u32 {
+        match self.params.mode.value() {
+            Mode::Square => 0,
+            Mode::Clean => self.limiter.latency_samples(),
+        }
+    }
 
-        let biquad_coefficients =
-            filter::BiquadCoefficients::bandpass(self.sample_rate, BP_FREQUENCY, q);
-        for filters in &mut self.bp_filters {
-            for filter in filters {
-                filter.coefficients = biquad_coefficients;
+    /// Switch to `mode`, resetting the limiter when switching to [`Mode::Clean`].
+    fn update_mode(&mut self, mode: Mode) {
+        if mode == Mode::Clean && self.mode != Mode::Clean {
+            self.limiter.reset();
+        }
+        self.mode = mode;
+    }
+
+    /// Update the limiter's settings from the parameters. This may change the limiter's latency.
+    fn update_limiter(&mut self) {
+        self.limiter
+            .set_lookahead(self.params.limiter_lookahead_ms.value());
+        self.limiter
+            .set_ceiling(self.params.limiter_ceiling_db.value());
+        self.limiter.set_release(
+            self.params.limiter_release_ms.value(),
+            self.params.limiter_auto_release.value(),
+        );
+    }
+
+    /// Update the band-pass filters, the tilt filters, or both. Only the smoothers that are
+    /// smoothing are advanced, so during processing this should be called at most once per sample,
+    /// and only for the filters whose parameters are smoothing.
+    fn update_filters(&mut self, update_bp_filters: bool, update_tilt_filters: bool) {
+        let frequency = next_smoothed_value(&self.params.bp_frequency.smoothed)
+            .min(self.sample_rate / 2.0 * MAX_FREQUENCY_NYQUIST_FRACTION);
+
+        if update_bp_filters {
+            let q = 0.00001 + (next_smoothed_value(&self.params.win_harder_factor.smoothed) * 30.0);
+            let bp_coefficients =
+                filter::BiquadCoefficients::bandpass(self.sample_rate, frequency, q);
+            for filters in &mut self.bp_filters {
+                for filter in filters {
+                    filter.coefficients = bp_coefficients;
+                }
             }
         }
+
+        if !update_tilt_filters {
+            return;
+        }
+
+        let tilt_db = next_smoothed_value(&self.params.tilt_db.smoothed);
+        let low_shelf_coefficients =
+            filter::BiquadCoefficients::low_shelf(self.sample_rate, frequency, -tilt_db / 2.0);
+        let high_shelf_coefficients =
+            filter::BiquadCoefficients::high_shelf(self.sample_rate, frequency, tilt_db / 2.0);
+        for [low_shelf, high_shelf] in &mut self.tilt_filters {
+            low_shelf.coefficients = low_shelf_coefficients;
+            high_shelf.coefficients = high_shelf_coefficients;
+        }
+    }
+}
+
+/// The smoother's next value if it's smoothing, or its current value otherwise.
+fn next_smoothed_value(smoother: &Smoother<f32>) -> f32 {
+    if smoother.is_smoothing() {
+        smoother.next()
+    } else {
+        smoother.previous_value()
     }
 }
 
@@ -279,3 +605,38 @@ impl Vst3Plugin for LoudnessWarWinner {
 
 nih_export_clap!(LoudnessWarWinner);
 nih_export_vst3!(LoudnessWarWinner);
+
+#[test]
+fn test_switching_to_clean_mode_resets_limiter() {
+    let mut plugin = LoudnessWarWinner::default();
+    plugin.limiter = limiter::Limiter::new(44100.0, 1, MAX_LIMITER_LOOKAHEAD_MS);
+    plugin.update_limiter();
+    plugin.update_mode(Mode::Clean);
+    let latency = plugin.limiter.latency_samples() as usize;
+
+    // Leave some audio in the limiter's delay line, and then switch modes back and forth
+    for _ in 0..latency / 2 {
+        plugin.limiter.process(&mut [0.5]);
+    }
+    plugin.update_mode(Mode::Square);
+    plugin.update_mode(Mode::Clean);
+
+    for _ in 0..latency * 2 {
+        let mut frame = [0.0];
+        plugin.limiter.process(&mut frame);
+        assert_eq!(frame, [0.0]);
+    }
+
+    // Staying in the same mode should not reset the limiter
+    for _ in 0..latency / 2 {
+        plugin.limiter.process(&mut [0.5]);
+    }
+    plugin.update_mode(Mode::Clean);
+    let mut output_peak = 0.0f32;
+    for _ in 0..latency {
+        let mut frame = [0.0];
+        plugin.limiter.process(&mut frame);
+        output_peak = output_peak.max(frame[0]);
+    }
+    assert!(output_peak > 0.4, "{output_peak}");
+}
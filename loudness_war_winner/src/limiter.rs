@@ -0,0 +1,712 @@
+// Loudness War Winner: Because negative LUFS are boring
+// Copyright (C) 2022-2023 Robbert van der Helm
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use nih_plug::debug::*;
+use nih_plug::util;
+use std::f32::consts;
+
+/// The oversampling factor used for the true peak detection.
+const OVERSAMPLING_FACTOR: usize = 4;
+/// The number of base rate taps for each of the interpolation filter's polyphase branches.
+const TRUE_PEAK_TAPS_PER_PHASE: usize = 8;
+/// The interpolation filter is linear phase, so the detected peaks lag behind the input by this
+/// many samples. The audio is delayed by this amount on top of the lookahead.
+const TRUE_PEAK_DELAY_SAMPLES: usize = TRUE_PEAK_TAPS_PER_PHASE / 2;
+
+/// With auto-release enabled, the release time is stretched by up to this factor when the limiter
+/// has been reducing gain for a while.
+const AUTO_RELEASE_MAX_FACTOR: f32 = 8.0;
+/// The time constant for the auto-release's estimate of how sustained the gain reduction is.
+const AUTO_RELEASE_WINDOW_MS: f32 = 300.0;
+
+/// When the lookahead time changes, the output crossfades from the old delay to the new delay over
+/// this many milliseconds.
+const LOOKAHEAD_CROSSFADE_MS: f32 = 10.0;
+
+/// A stereo-linked lookahead brickwall limiter with true peak detection.
+///
+/// The limiter runs the input through a 4x oversampled peak detector, takes the maximum of that
+/// over the lookahead window, and computes the gain needed to keep that peak below the ceiling.
+/// The gain envelope then gets released exponentially and smoothed with a moving average over the
+/// lookahead window. Because the moving average window covers the entire lookahead, the gain has
+/// fully reached its target by the time a peak leaves the delay line. This means the sample peaks
+/// never exceed the ceiling, and the inter-sample peaks only by a negligible amount.
+///
+/// All buffers are allocated up front in [`Limiter::new()`] for the maximum lookahead time, so
+/// changing the lookahead during processing won't allocate. Lookahead changes crossfade between the
+/// old and the new delay instead of clearing the buffers. During that crossfade the output's peaks
+/// may briefly exceed the ceiling.
+#[derive(Debug, Clone)]
+pub struct Limiter {
+    sample_rate: f32,
+
+    /// The maximum lookahead, in samples. Used to size all buffers.
+    max_lookahead_samples: usize,
+    /// The current lookahead, in samples. This is always at least one sample.
+    lookahead_samples: usize,
+    /// The lookahead set with [`Limiter::set_lookahead()`], in samples. This becomes the current
+    /// lookahead once the previous lookahead change has finished crossfading.
+    target_lookahead_samples: usize,
+    /// The lookahead before the last lookahead change, in samples. The output is crossfaded from
+    /// this delay to the current delay.
+    previous_lookahead_samples: usize,
+    /// The length of a lookahead crossfade, in samples.
+    crossfade_length: usize,
+    /// The number of samples left in the current lookahead crossfade, or zero if the lookahead is
+    /// not changing.
+    crossfade_remaining: usize,
+    /// The linear gain the output's true peak values should stay under.
+    ceiling: f32,
+    /// The one-pole coefficient for the configured release time.
+    release_coefficient: f32,
+    /// The one-pole coefficient for the configured release time stretched by
+    /// `AUTO_RELEASE_MAX_FACTOR`, or the same as `release_coefficient` if auto-release is
+    /// disabled.
+    slow_release_coefficient: f32,
+    /// The one-pole coefficient for `AUTO_RELEASE_WINDOW_MS`.
+    sustain_coefficient: f32,
+
+    /// Delay lines for every channel, of length `max_lookahead_samples + TRUE_PEAK_DELAY_SAMPLES +
+    /// 1` so the maximum delay doesn't read the sample that was just written.
+    delay_lines: Vec<Vec<f32>>,
+    /// The next write position in the delay lines.
+    delay_line_pos: usize,
+    /// The true peak detector for every channel.
+    true_peak_detectors: Vec<TruePeakDetector>,
+    /// The maximum detected peak value over the lookahead window.
+    peak_window: SlidingMax,
+    /// The released gain envelope, before the moving average filter.
+    gain_envelope: f32,
+    /// Smooths `gain_envelope` over the lookahead window.
+    gain_average: MovingAverage,
+    /// How much of the time the limiter has been reducing gain recently, in `[0, 1]`. Used for the
+    /// program-dependent auto-release.
+    sustain: f32,
+    /// The gain that was applied to the last output sample.
+    current_gain: f32,
+}
+
+/// Estimates the inter-sample peaks of a signal by upsampling it with a polyphase windowed sinc
+/// filter.
+#[derive(Debug, Clone)]
+struct TruePeakDetector {
+    /// The polyphase interpolation filter coefficients, indexed by `[phase][tap]`.
+    coefficients: [[f32; TRUE_PEAK_TAPS_PER_PHASE]; OVERSAMPLING_FACTOR],
+    /// The last `TRUE_PEAK_TAPS_PER_PHASE` input samples, with the most recent sample at `pos`.
+    history: [f32; TRUE_PEAK_TAPS_PER_PHASE],
+    pos: usize,
+}
+
+/// Computes the maximum over a sliding window using a monotonic queue stored in a preallocated ring
+/// buffer.
+#[derive(Debug, Clone)]
+struct SlidingMax {
+    /// `(time, value)` pairs with strictly decreasing values. The front of the queue is the maximum.
+    queue: Vec<(u64, f32)>,
+    head: usize,
+    len: usize,
+    window_length: usize,
+    time: u64,
+}
+
+/// A moving average over a preallocated ring buffer.
+#[derive(Debug, Clone)]
+struct MovingAverage {
+    /// The last `buffer.len()` values, so the window can grow without losing the history.
+    buffer: Vec<f32>,
+    pos: usize,
+    window_length: usize,
+    /// The sum of the last `window_length` values. This is an `f64` so rounding errors don't
+    /// accumulate as quickly.
+    sum: f64,
+}
+
+impl Limiter {
+    /// Create a limiter for `num_channels` channels that can use up to `max_lookahead_ms` of
+    /// lookahead. The lookahead, ceiling and release should be configured using the setters
+    /// afterwards.
+    pub fn new(sample_rate: f32, num_channels: usize, max_lookahead_ms: f32) -> Self {
+        let max_lookahead_samples = ms_to_samples(max_lookahead_ms, sample_rate).max(1);
+        let delay_line_length = max_lookahead_samples + TRUE_PEAK_DELAY_SAMPLES + 1;
+
+        let mut limiter = Self {
+            sample_rate,
+
+            max_lookahead_samples,
+            lookahead_samples: max_lookahead_samples,
+            target_lookahead_samples: max_lookahead_samples,
+            previous_lookahead_samples: max_lookahead_samples,
+            crossfade_length: ms_to_samples(LOOKAHEAD_CROSSFADE_MS, sample_rate).max(1),
+            crossfade_remaining: 0,
+            ceiling: 1.0,
+            release_coefficient: 0.0,
+            slow_release_coefficient: 0.0,
+            sustain_coefficient: one_pole_coefficient(AUTO_RELEASE_WINDOW_MS, sample_rate),
+
+            delay_lines: vec![vec![0.0; delay_line_length]; num_channels],
+            delay_line_pos: 0,
+            true_peak_detectors: vec![TruePeakDetector::new(); num_channels],
+            peak_window: SlidingMax::new(max_lookahead_samples + 1),
+            gain_envelope: 1.0,
+            gain_average: MovingAverage::new(max_lookahead_samples + 1),
+            sustain: 0.0,
+            current_gain: 1.0,
+        };
+        limiter.set_release(100.0, false);
+        limiter.reset();
+
+        limiter
+    }
+
+    /// The limiter's latency, in samples. This changes when the lookahead time changes.
+    pub fn latency_samples(&self) -> u32 {
+        (self.lookahead_samples + TRUE_PEAK_DELAY_SAMPLES) as u32
+    }
+
+    /// The gain that was applied to the last output sample, in `[0, 1]`.
+    pub fn current_gain(&self) -> f32 {
+        self.current_gain
+    }
+
+    /// Change the lookahead time. This is clamped to the maximum lookahead time passed to
+    /// [`Limiter::new()`]. Changing the lookahead changes the latency. The output crossfades to the
+    /// new delay without clearing the audio in the delay lines, and if the lookahead is already
+    /// changing then the new lookahead is applied after that crossfade has finished.
+    pub fn set_lookahead(&mut self, lookahead_ms: f32) {
+        self.target_lookahead_samples =
+            ms_to_samples(lookahead_ms, self.sample_rate).clamp(1, self.max_lookahead_samples);
+        if self.crossfade_remaining == 0 {
+            self.start_lookahead_crossfade();
+        }
+    }
+
+    /// Set the ceiling the output's true peak level should stay below, in decibels.
+    pub fn set_ceiling(&mut self, ceiling_db: f32) {
+        self.ceiling = util::db_to_gain(ceiling_db);
+    }
+
+    /// Set the release time in milliseconds. When `auto_release` is enabled, the release time gets
+    /// stretched when the limiter has been reducing gain for a longer period so sustained material
+    /// doesn't start pumping, while short transients still recover quickly.
+    pub fn set_release(&mut self, release_ms: f32, auto_release: bool) {
+        nih_debug_assert!(release_ms > 0.0);
+
+        self.release_coefficient = one_pole_coefficient(release_ms, self.sample_rate);
+        self.slow_release_coefficient = if auto_release {
+            one_pole_coefficient(release_ms * AUTO_RELEASE_MAX_FACTOR, self.sample_rate)
+        } else {
+            self.release_coefficient
+        };
+    }
+
+    /// Clear all delay lines and reset the gain to unity. Any pending lookahead change is applied
+    /// immediately.
+    pub fn reset(&mut self) {
+        self.lookahead_samples = self.target_lookahead_samples;
+        self.previous_lookahead_samples = self.target_lookahead_samples;
+        self.crossfade_remaining = 0;
+
+        for delay_line in &mut self.delay_lines {
+            delay_line.fill(0.0);
+        }
+        self.delay_line_pos = 0;
+        for detector in &mut self.true_peak_detectors {
+            detector.reset();
+        }
+
+        let window_length = self.lookahead_samples + 1;
+        self.peak_window.reset(window_length);
+        self.gain_average.reset(window_length, 1.0);
+        self.gain_envelope = 1.0;
+        self.sustain = 0.0;
+        self.current_gain = 1.0;
+    }
+
+    /// Process a single frame containing one sample for every channel. The output is delayed by
+    /// [`Limiter::latency_samples()`].
+    pub fn process(&mut self, frame: &mut [f32]) {
+        nih_debug_assert_eq!(frame.len(), self.delay_lines.len());
+
+        // The detection uses the maximum over all channels so the stereo image stays intact
+        let mut peak = 0.0f32;
+        for (sample, detector) in frame.iter().zip(&mut self.true_peak_detectors) {
+            peak = peak.max(detector.process(*sample));
+        }
+
+        let window_peak = self.peak_window.process(peak);
+        let target_gain = if window_peak > self.ceiling {
+            self.ceiling / window_peak
+        } else {
+            1.0
+        };
+
+        let is_limiting = if target_gain < 1.0 { 1.0 } else { 0.0 };
+        self.sustain = is_limiting + (self.sustain - is_limiting) * self.sustain_coefficient;
+
+        // Gain reduction is applied instantly here, and the moving average below turns that into a
+        // smooth attack that exactly spans the lookahead window
+        self.gain_envelope = if target_gain <= self.gain_envelope {
+            target_gain
+        } else {
+            let release_coefficient = self.release_coefficient
+                + (self.slow_release_coefficient - self.release_coefficient) * self.sustain;
+            target_gain + (self.gain_envelope - target_gain) * release_coefficient
+        };
+        self.current_gain = self.gain_average.process(self.gain_envelope);
+
+        let delay_line_length = self.delay_lines[0].len();
+        let read_pos = |lookahead_samples: usize| {
+            (self.delay_line_pos + delay_line_length - lookahead_samples - TRUE_PEAK_DELAY_SAMPLES)
+                % delay_line_length
+        };
+        let current_read_pos = read_pos(self.lookahead_samples);
+        if self.crossfade_remaining == 0 {
+            for (sample, delay_line) in frame.iter_mut().zip(&mut self.delay_lines) {
+                delay_line[self.delay_line_pos] = *sample;
+                *sample = delay_line[current_read_pos] * self.current_gain;
+            }
+        } else {
+            let previous_read_pos = read_pos(self.previous_lookahead_samples);
+            let t = 1.0 - (self.crossfade_remaining as f32 / self.crossfade_length as f32);
+            for (sample, delay_line) in frame.iter_mut().zip(&mut self.delay_lines) {
+                delay_line[self.delay_line_pos] = *sample;
+                *sample = (delay_line[previous_read_pos] * (1.0 - t)
+                    + delay_line[current_read_pos] * t)
+                    * self.current_gain;
+            }
+
+            self.crossfade_remaining -= 1;
+            if self.crossfade_remaining == 0 {
+                self.start_lookahead_crossfade();
+            }
+        }
+        self.delay_line_pos = (self.delay_line_pos + 1) % delay_line_length;
+    }
+
+    /// Start crossfading to `target_lookahead_samples` if it differs from the current lookahead.
+    /// The detection windows are resized right away while keeping their history.
+    fn start_lookahead_crossfade(&mut self) {
+        if self.target_lookahead_samples == self.lookahead_samples {
+            return;
+        }
+
+        self.previous_lookahead_samples = self.lookahead_samples;
+        self.lookahead_samples = self.target_lookahead_samples;
+        self.crossfade_remaining = self.crossfade_length;
+
+        let window_length = self.lookahead_samples + 1;
+        self.peak_window.set_window_length(window_length);
+        self.gain_average.set_window_length(window_length);
+    }
+}
+
+impl TruePeakDetector {
+    fn new() -> Self {
+        // This is a Blackman windowed sinc filter with its center at `TRUE_PEAK_DELAY_SAMPLES`.
+        // Phase 0 thus passes through the input as is, and the other phases interpolate between
+        // that sample and the next one.
+        let num_taps = TRUE_PEAK_TAPS_PER_PHASE * OVERSAMPLING_FACTOR;
+        let center = (TRUE_PEAK_DELAY_SAMPLES * OVERSAMPLING_FACTOR) as f32;
+        let mut coefficients = [[0.0; TRUE_PEAK_TAPS_PER_PHASE]; OVERSAMPLING_FACTOR];
+        for (phase, phase_coefficients) in coefficients.iter_mut().enumerate() {
+            for (tap, coefficient) in phase_coefficients.iter_mut().enumerate() {
+                let n = (tap * OVERSAMPLING_FACTOR + phase) as f32;
+                let x = (n - center) / OVERSAMPLING_FACTOR as f32;
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (consts::PI * x).sin() / (consts::PI * x)
+                };
+                let window_pos = n / num_taps as f32;
+                let window = 0.42 - 0.5 * (consts::TAU * window_pos).cos()
+                    + 0.08 * (2.0 * consts::TAU * window_pos).cos();
+
+                *coefficient = sinc * window;
+            }
+
+            // Normalize every phase to unity DC gain
+            let sum: f32 = phase_coefficients.iter().sum();
+            for coefficient in phase_coefficients.iter_mut() {
+                *coefficient /= sum;
+            }
+        }
+
+        Self {
+            coefficients,
+            history: [0.0; TRUE_PEAK_TAPS_PER_PHASE],
+            pos: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.pos = 0;
+    }
+
+    /// Add a sample to the detector, and return the absolute peak value of the upsampled signal
+    /// between `TRUE_PEAK_DELAY_SAMPLES` and `TRUE_PEAK_DELAY_SAMPLES - 1` samples ago.
+    fn process(&mut self, sample: f32) -> f32 {
+        self.pos = (self.pos + 1) % TRUE_PEAK_TAPS_PER_PHASE;
+        self.history[self.pos] = sample;
+
+        let mut peak = 0.0f32;
+        for phase_coefficients in &self.coefficients {
+            let mut value = 0.0;
+            for (tap, coefficient) in phase_coefficients.iter().enumerate() {
+                let history_pos =
+                    (self.pos + TRUE_PEAK_TAPS_PER_PHASE - tap) % TRUE_PEAK_TAPS_PER_PHASE;
+                value += coefficient * self.history[history_pos];
+            }
+
+            peak = peak.max(value.abs());
+        }
+
+        peak
+    }
+}
+
+impl SlidingMax {
+    fn new(max_window_length: usize) -> Self {
+        Self {
+            queue: vec![(0, 0.0); max_window_length],
+            head: 0,
+            len: 0,
+            window_length: max_window_length,
+            time: 0,
+        }
+    }
+
+    fn reset(&mut self, window_length: usize) {
+        nih_debug_assert!(window_length <= self.queue.len());
+
+        self.head = 0;
+        self.len = 0;
+        self.window_length = window_length;
+        self.time = 0;
+    }
+
+    /// Change the window length. When the window shrinks the values that have left the window are
+    /// removed during the next [`process()`][Self::process()] call. When it grows the values that
+    /// were already removed from the window are not brought back.
+    fn set_window_length(&mut self, window_length: usize) {
+        nih_debug_assert!(window_length <= self.queue.len());
+
+        self.window_length = window_length;
+    }
+
+    /// Add a value to the window and return the maximum of the last `window_length` values.
+    fn process(&mut self, value: f32) -> f32 {
+        let capacity = self.queue.len();
+
+        // Values that are smaller than the new value can never become the maximum again
+        while self.len > 0 && self.queue[(self.head + self.len - 1) % capacity].1 <= value {
+            self.len -= 1;
+        }
+        // And values that have left the window should also be removed
+        while self.len > 0 && self.queue[self.head].0 + self.window_length as u64 <= self.time {
+            self.head = (self.head + 1) % capacity;
+            self.len -= 1;
+        }
+
+        self.queue[(self.head + self.len) % capacity] = (self.time, value);
+        self.len += 1;
+        self.time += 1;
+
+        self.queue[self.head].1
+    }
+}
+
+impl MovingAverage {
+    fn new(max_window_length: usize) -> Self {
+        Self {
+            buffer: vec![0.0; max_window_length],
+            pos: 0,
+            window_length: max_window_length,
+            sum: 0.0,
+        }
+    }
+
+    /// Reset the filter with a new window length, filling the history with `value`.
+    fn reset(&mut self, window_length: usize, value: f32) {
+        nih_debug_assert!(window_length <= self.buffer.len());
+
+        self.buffer.fill(value);
+        self.pos = 0;
+        self.window_length = window_length;
+        self.sum = value as f64 * window_length as f64;
+    }
+
+    /// Change the window length, recomputing the sum from the last `window_length` values.
+    fn set_window_length(&mut self, window_length: usize) {
+        nih_debug_assert!(window_length <= self.buffer.len());
+
+        let capacity = self.buffer.len();
+        self.window_length = window_length;
+        self.sum = (1..=window_length)
+            .map(|age| self.buffer[(self.pos + capacity - age) % capacity] as f64)
+            .sum();
+    }
+
+    /// Add a value to the window and return the average of the last `window_length` values.
+    fn process(&mut self, value: f32) -> f32 {
+        let capacity = self.buffer.len();
+        let oldest_pos = (self.pos + capacity - self.window_length) % capacity;
+        self.sum += value as f64 - self.buffer[oldest_pos] as f64;
+        self.buffer[self.pos] = value;
+        self.pos = (self.pos + 1) % capacity;
+
+        (self.sum / self.window_length as f64) as f32
+    }
+}
+
+fn ms_to_samples(ms: f32, sample_rate: f32) -> usize {
+    (ms / 1000.0 * sample_rate).round() as usize
+}
+
+/// The coefficient for a one-pole filter that reaches `1 - 1/e` of the way to its target after
+/// `time_ms` milliseconds.
+fn one_pole_coefficient(time_ms: f32, sample_rate: f32) -> f32 {
+    (-1.0 / (time_ms / 1000.0 * sample_rate)).exp()
+}
+
+/// Deterministic white noise in `[-amplitude, amplitude]`, produced by a simple LCG.
+#[cfg(test)]
+fn noise(num_samples: usize, amplitude: f32) -> impl Iterator<Item = f32> {
+    let mut state = 0x1234_5678u32;
+    (0..num_samples).map(move |_| {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (state as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude
+    })
+}
+
+#[cfg(test)]
+fn test_limiter(num_channels: usize) -> Limiter {
+    let mut limiter = Limiter::new(44100.0, num_channels, 10.0);
+    limiter.set_lookahead(5.0);
+    limiter.set_ceiling(-1.0);
+    limiter.set_release(50.0, true);
+    limiter.reset();
+
+    limiter
+}
+
+#[test]
+fn test_sliding_max() {
+    let mut window = SlidingMax::new(8);
+    window.reset(3);
+
+    let outputs: Vec<f32> = [1.0, 3.0, 2.0, 0.0, 0.0, 0.0, 5.0, 4.0]
+        .into_iter()
+        .map(|value| window.process(value))
+        .collect();
+    assert_eq!(outputs, [1.0, 3.0, 3.0, 3.0, 2.0, 0.0, 5.0, 5.0]);
+}
+
+#[test]
+fn test_true_peak_detector_passes_samples_through() {
+    let mut detector = TruePeakDetector::new();
+    let mut peaks = Vec::new();
+    for i in 0..16 {
+        peaks.push(detector.process(if i == 0 { 0.5 } else { 0.0 }));
+    }
+
+    // Phase 0 should reproduce the input sample after the detector's delay
+    assert!((peaks[TRUE_PEAK_DELAY_SAMPLES] - 0.5).abs() < 1e-6);
+}
+
+#[test]
+fn test_true_peak_detector_finds_inter_sample_peaks() {
+    // A sine wave at a quarter of the sample rate with a 45 degree phase offset never hits its
+    // peak value on a sample
+    let mut detector = TruePeakDetector::new();
+    let mut sample_peak = 0.0f32;
+    let mut true_peak = 0.0f32;
+    for i in 0..256 {
+        let sample = (consts::FRAC_PI_2 * i as f32 + consts::FRAC_PI_4).sin();
+        sample_peak = sample_peak.max(sample.abs());
+        true_peak = true_peak.max(detector.process(sample));
+    }
+
+    assert!(sample_peak < 0.71);
+    assert!(true_peak > 0.95, "{true_peak}");
+}
+
+#[test]
+fn test_latency() {
+    let mut limiter = test_limiter(2);
+    let latency = limiter.latency_samples() as usize;
+
+    // A signal below the ceiling should pass through unchanged, just delayed
+    let mut outputs = Vec::new();
+    for i in 0..latency * 2 {
+        let mut frame = if i == 0 { [0.5, -0.25] } else { [0.0, 0.0] };
+        limiter.process(&mut frame);
+        outputs.push(frame);
+    }
+
+    for (i, frame) in outputs.iter().enumerate() {
+        if i == latency {
+            assert_eq!(*frame, [0.5, -0.25]);
+        } else {
+            assert_eq!(*frame, [0.0, 0.0]);
+        }
+    }
+}
+
+#[test]
+fn test_brickwall() {
+    let mut limiter = test_limiter(1);
+    let ceiling = util::db_to_gain(-1.0);
+
+    let mut output_detector = TruePeakDetector::new();
+    let mut sample_peak = 0.0f32;
+    let mut true_peak = 0.0f32;
+    for (i, sample) in noise(44100, util::db_to_gain(18.0)).enumerate() {
+        // Throw in some isolated spikes as well
+        let mut frame = [if i % 5000 == 2500 { 20.0 } else { sample }];
+        limiter.process(&mut frame);
+
+        sample_peak = sample_peak.max(frame[0].abs());
+        true_peak = true_peak.max(output_detector.process(frame[0]));
+    }
+
+    assert!(sample_peak <= ceiling * 1.0001, "{sample_peak} > {ceiling}");
+    // The gain changes between samples, so the inter-sample peaks of the output can very slightly
+    // overshoot
+    assert!(
+        util::gain_to_db(true_peak) < -1.0 + 0.1,
+        "{} dBTP",
+        util::gain_to_db(true_peak)
+    );
+}
+
+#[test]
+fn test_release() {
+    let mut limiter = test_limiter(1);
+    limiter.set_release(50.0, false);
+
+    for _ in 0..4410 {
+        limiter.process(&mut [4.0]);
+    }
+    assert!(limiter.current_gain() < 0.3);
+
+    // After a couple of release time constants the gain should be close to unity again
+    for _ in 0..22050 {
+        limiter.process(&mut [0.1]);
+    }
+    assert!(limiter.current_gain() > 0.99);
+}
+
+#[test]
+fn test_auto_release_is_program_dependent() {
+    // The gain after releasing for 50 ms following a short transient...
+    let mut transient_limiter = test_limiter(1);
+    for _ in 0..441 {
+        transient_limiter.process(&mut [4.0]);
+    }
+    for _ in 0..2205 {
+        transient_limiter.process(&mut [0.1]);
+    }
+
+    // ...should be higher than after the same amount of release following sustained limiting
+    let mut sustained_limiter = test_limiter(1);
+    for _ in 0..44100 {
+        sustained_limiter.process(&mut [4.0]);
+    }
+    for _ in 0..2205 {
+        sustained_limiter.process(&mut [0.1]);
+    }
+
+    assert!(
+        transient_limiter.current_gain() > sustained_limiter.current_gain() + 0.1,
+        "{} vs. {}",
+        transient_limiter.current_gain(),
+        sustained_limiter.current_gain()
+    );
+}
+
+#[test]
+fn test_change_lookahead() {
+    let mut limiter = test_limiter(1);
+    assert_eq!(
+        limiter.latency_samples(),
+        221 + TRUE_PEAK_DELAY_SAMPLES as u32
+    );
+
+    limiter.set_lookahead(1.0);
+    assert_eq!(
+        limiter.latency_samples(),
+        44 + TRUE_PEAK_DELAY_SAMPLES as u32
+    );
+
+    // This is clamped to the maximum lookahead, and it's only applied once the previous change has
+    // finished crossfading
+    limiter.set_lookahead(100.0);
+    assert_eq!(
+        limiter.latency_samples(),
+        44 + TRUE_PEAK_DELAY_SAMPLES as u32
+    );
+    for _ in 0..limiter.crossfade_length {
+        limiter.process(&mut [0.0]);
+    }
+    assert_eq!(
+        limiter.latency_samples(),
+        441 + TRUE_PEAK_DELAY_SAMPLES as u32
+    );
+}
+
+#[test]
+fn test_change_lookahead_keeps_audio() {
+    let mut limiter = test_limiter(2);
+    for _ in 0..4410 {
+        limiter.process(&mut [0.5, -0.5]);
+    }
+
+    // Changing the lookahead should neither drop nor limit a signal below the ceiling
+    for lookahead_ms in [1.0, 10.0, 0.1, 5.0] {
+        limiter.set_lookahead(lookahead_ms);
+        for _ in 0..limiter.crossfade_length * 2 {
+            let mut frame = [0.5, -0.5];
+            limiter.process(&mut frame);
+            assert!((frame[0] - 0.5).abs() < 1e-6, "{frame:?}");
+            assert!((frame[1] + 0.5).abs() < 1e-6, "{frame:?}");
+        }
+    }
+
+    // And the brickwall behavior still holds after the windows have been resized
+    for _ in 0..4410 {
+        let mut frame = [4.0, -4.0];
+        limiter.process(&mut frame);
+        assert!(frame[0] <= util::db_to_gain(-1.0) * 1.0001, "{frame:?}");
+    }
+}
+
+#[test]
+fn test_max_lookahead_delays_output() {
+    let mut limiter = test_limiter(1);
+    limiter.set_lookahead(10.0);
+    limiter.reset();
+    let latency = limiter.latency_samples() as usize;
+
+    let mut outputs = Vec::new();
+    for i in 0..latency + 1 {
+        let mut frame = [if i == 0 { 0.5 } else { 0.0 }];
+        limiter.process(&mut frame);
+        outputs.push(frame[0]);
+    }
+    assert_eq!(outputs[0], 0.0);
+    assert_eq!(outputs[latency], 0.5);
+}
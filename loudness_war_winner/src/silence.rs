@@ -0,0 +1,308 @@
+// Loudness War Winner: Because negative LUFS are boring
+// Copyright (C) 2022-2023 Robbert van der Helm
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use nih_plug::debug::*;
+use nih_plug::util;
+
+/// The time it takes after the hold time has passed to fade from a full scale signal to silence.
+const SILENCE_FADEOUT_LENGTH_MS: f32 = 1000.0;
+/// The time it takes to fade back in from complete silence when the input signal returns.
+const SILENCE_FADEIN_LENGTH_MS: f32 = 5.0;
+/// Thresholds at or below this level are treated as exact digital silence.
+const MIN_THRESHOLD_DB: f32 = -120.0;
+
+/// Decides when the input signal should be considered silent, and computes a gain to fade the
+/// output into silence after it has been silent for a while. Without this the plugin would output a
+/// constant full scale signal on silent inputs, and any noise or dither on the input would do the
+/// same.
+///
+/// The detector has two thresholds. While the signal is considered present, it stays present for as
+/// long as the input's peak value exceeds `threshold - hysteresis`. After the input has stayed below
+/// that for the hold time, the output fades out and the signal only counts as being present again
+/// once it exceeds `threshold`. This prevents a noise floor sitting right at the threshold from
+/// repeatedly retriggering the output. Both thresholds are hard thresholds, the fades take care of
+/// the transitions.
+#[derive(Debug, Clone)]
+pub struct SilenceGate {
+    sample_rate: f32,
+
+    /// The linear gain the input needs to exceed to reopen the gate after it has closed.
+    open_threshold: f32,
+    /// The linear gain the input needs to exceed to keep the gate open.
+    close_threshold: f32,
+    /// The number of samples the input needs to stay below `close_threshold` before fading out.
+    hold_samples: u32,
+    /// `SILENCE_FADEOUT_LENGTH_MS` converted to samples.
+    fadeout_length_samples: u32,
+    /// How much the gain increases per sample when fading back in, based on
+    /// `SILENCE_FADEIN_LENGTH_MS`.
+    fadein_step: f32,
+
+    /// The number of samples since the last sample that kept the gate open.
+    num_silent_samples: u32,
+    /// The current gain. This is 1.0 while the gate is fully open and 0.0 when it's fully closed.
+    gain: f32,
+}
+
+impl Default for SilenceGate {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl SilenceGate {
+    /// Create a silence gate for the given sample rate. The gate starts out fully closed so the
+    /// plugin doesn't immediately output a full scale signal when it's inserted on a silent channel.
+    /// The thresholds and hold time need to be configured with [`Self::set_thresholds()`] and
+    /// [`Self::set_hold_time()`].
+    pub fn new(sample_rate: f32) -> Self {
+        let mut gate = Self {
+            sample_rate,
+
+            open_threshold: 0.0,
+            close_threshold: 0.0,
+            hold_samples: 0,
+            fadeout_length_samples: ms_to_samples(SILENCE_FADEOUT_LENGTH_MS, sample_rate).max(1),
+            fadein_step: 1.0 / ms_to_samples(SILENCE_FADEIN_LENGTH_MS, sample_rate).max(1) as f32,
+
+            num_silent_samples: 0,
+            gain: 0.0,
+        };
+        gate.reset();
+
+        gate
+    }
+
+    /// Set the detection threshold and the hysteresis, both in decibels. Thresholds at or below
+    /// -120 dBFS are set to exactly zero, so a threshold of -120 dBFS and no hysteresis only treats
+    /// exact digital silence as silent.
+    pub fn set_thresholds(&mut self, threshold_db: f32, hysteresis_db: f32) {
+        nih_debug_assert!(hysteresis_db >= 0.0);
+
+        self.open_threshold = threshold_to_gain(threshold_db);
+        self.close_threshold = threshold_to_gain(threshold_db - hysteresis_db);
+    }
+
+    /// Set the time the input needs to stay silent before the output starts fading out, in
+    /// milliseconds.
+    pub fn set_hold_time(&mut self, hold_ms: f32) {
+        let hold_samples = ms_to_samples(hold_ms, self.sample_rate);
+
+        // Keep a closed gate closed when the hold time changes
+        if self.num_silent_samples >= self.hold_samples + self.fadeout_length_samples {
+            self.num_silent_samples = hold_samples + self.fadeout_length_samples;
+        }
+        self.hold_samples = hold_samples;
+    }
+
+    /// Reset the gate to the fully closed state.
+    pub fn reset(&mut self) {
+        self.num_silent_samples = self.hold_samples + self.fadeout_length_samples;
+        self.gain = 0.0;
+    }
+
+    /// Whether the gate is fully closed. The output should be completely silent in that case.
+    pub fn is_closed(&self) -> bool {
+        self.gain == 0.0
+    }
+
+    /// Process the next sample's peak value (the maximum absolute sample value across all channels)
+    /// and return the gain that should be applied to the output for that sample.
+    pub fn next(&mut self, peak: f32) -> f32 {
+        // The input needs to be louder to reopen the gate once it has started fading out
+        let threshold = if self.num_silent_samples >= self.hold_samples {
+            self.open_threshold
+        } else {
+            self.close_threshold
+        };
+        if peak > threshold {
+            self.num_silent_samples = 0;
+        } else {
+            self.num_silent_samples = self.num_silent_samples.saturating_add(1);
+        }
+
+        let target_gain = if self.num_silent_samples < self.hold_samples {
+            1.0
+        } else if self.num_silent_samples < self.hold_samples + self.fadeout_length_samples {
+            1.0 - ((self.num_silent_samples - self.hold_samples) as f32
+                / self.fadeout_length_samples as f32)
+        } else {
+            0.0
+        };
+
+        // Fading out follows the fadeout curve directly, but fading back in is rate limited so the
+        // output doesn't jump back to full scale when the signal returns
+        self.gain = if target_gain > self.gain {
+            (self.gain + self.fadein_step).min(target_gain)
+        } else {
+            target_gain
+        };
+
+        self.gain
+    }
+}
+
+fn threshold_to_gain(threshold_db: f32) -> f32 {
+    if threshold_db <= MIN_THRESHOLD_DB {
+        0.0
+    } else {
+        util::db_to_gain_fast(threshold_db)
+    }
+}
+
+fn ms_to_samples(ms: f32, sample_rate: f32) -> u32 {
+    (ms / 1000.0 * sample_rate).round() as u32
+}
+
+/// A deterministic TPDF dither signal with the given peak amplitude, produced by a simple LCG.
+#[cfg(test)]
+fn dither(num_samples: usize, amplitude: f32) -> impl Iterator<Item = f32> {
+    let mut state = 0x1234_5678u32;
+    let mut next_uniform = move || {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        state as f32 / u32::MAX as f32
+    };
+
+    (0..num_samples).map(move |_| (next_uniform() - next_uniform()) * amplitude)
+}
+
+#[cfg(test)]
+fn test_gate() -> SilenceGate {
+    let mut gate = SilenceGate::new(44100.0);
+    gate.set_thresholds(-60.0, 6.0);
+    gate.set_hold_time(100.0);
+
+    gate
+}
+
+#[test]
+fn test_starts_closed() {
+    let mut gate = test_gate();
+
+    assert!(gate.is_closed());
+    assert_eq!(gate.next(0.0), 0.0);
+}
+
+#[test]
+fn test_dithered_silence_fades_out() {
+    let mut gate = test_gate();
+    for _ in 0..4410 {
+        gate.next(0.5);
+    }
+    assert_eq!(gate.next(0.5), 1.0);
+
+    // 16-bit TPDF dither is roughly -90 dBFS, which should be considered silent. After the 100 ms
+    // hold time and the one second fadeout the gate should be completely closed.
+    let mut previous_gain = 1.0;
+    for sample in dither(44100 * 2, util::db_to_gain(-90.0)) {
+        let gain = gate.next(sample.abs());
+        assert!(gain <= previous_gain);
+        previous_gain = gain;
+    }
+    assert!(gate.is_closed());
+}
+
+#[test]
+fn test_hold_time() {
+    let mut gate = test_gate();
+    for _ in 0..4410 {
+        gate.next(0.5);
+    }
+
+    // The fadeout starts at full scale right after the hold time has passed
+    for _ in 0..4410 {
+        assert_eq!(gate.next(0.0), 1.0);
+    }
+    assert!(gate.next(0.0) < 1.0);
+}
+
+#[test]
+fn test_signal_onset_fades_in() {
+    let mut gate = test_gate();
+    assert!(gate.is_closed());
+
+    // The gain should ramp up over the 5 ms fade-in instead of snapping back to full scale
+    let fadein_length_samples = ms_to_samples(SILENCE_FADEIN_LENGTH_MS, 44100.0) as usize;
+    let mut previous_gain = 0.0;
+    for _ in 0..fadein_length_samples - 1 {
+        let gain = gate.next(0.5);
+        assert!(gain > previous_gain);
+        assert!(gain < 1.0);
+        previous_gain = gain;
+    }
+    assert_eq!(gate.next(0.5), 1.0);
+}
+
+#[test]
+fn test_onset_during_fadeout_fades_in() {
+    let mut gate = test_gate();
+    for _ in 0..4410 {
+        gate.next(0.5);
+    }
+
+    // Halfway through the fadeout
+    for _ in 0..4410 + 22050 {
+        gate.next(0.0);
+    }
+    let fadeout_gain = gate.next(0.0);
+    assert!(fadeout_gain > 0.4 && fadeout_gain < 0.6);
+
+    let gain = gate.next(0.5);
+    assert!(gain > fadeout_gain);
+    assert!(gain < 1.0);
+}
+
+#[test]
+fn test_hysteresis() {
+    let mut gate = test_gate();
+    let between_thresholds = util::db_to_gain(-63.0);
+
+    // A closed gate should not be reopened by a signal between the two thresholds...
+    for _ in 0..44100 {
+        assert_eq!(gate.next(between_thresholds), 0.0);
+    }
+
+    // ...but once it's open, that same signal should keep it open
+    gate.next(util::db_to_gain(-50.0));
+    for _ in 0..44100 {
+        gate.next(between_thresholds);
+    }
+    assert_eq!(gate.next(between_thresholds), 1.0);
+
+    // Going below the lower threshold closes it again
+    for _ in 0..44100 * 2 {
+        gate.next(util::db_to_gain(-70.0));
+    }
+    assert!(gate.is_closed());
+}
+
+#[test]
+fn test_digital_silence_threshold() {
+    let mut gate = SilenceGate::new(44100.0);
+    gate.set_thresholds(MIN_THRESHOLD_DB, 0.0);
+    gate.set_hold_time(100.0);
+
+    // Anything above exact digital silence opens the gate and keeps it open
+    for _ in 0..44100 {
+        gate.next(1e-7);
+    }
+    assert_eq!(gate.next(1e-7), 1.0);
+
+    for _ in 0..44100 * 2 {
+        gate.next(0.0);
+    }
+    assert!(gate.is_closed());
+}
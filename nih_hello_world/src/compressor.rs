@@ -0,0 +1,363 @@
+use nih_plug::prelude::*;
+
+/// The time constant for the RMS detector's mean square estimate.
+const RMS_WINDOW_MS: f32 = 10.0;
+/// The maximum number of channels the compressor can process. This matches the plugin's audio IO
+/// layouts, and it lets us keep per-sample temporaries on the stack.
+pub const MAX_NUM_CHANNELS: usize = 2;
+
+/// How the compressor's detector measures the input level.
+#[derive(Enum, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DetectorMode {
+    /// React to the absolute sample values.
+    #[id = "peak"]
+    Peak,
+    /// React to the signal's RMS level over the last `RMS_WINDOW_MS` milliseconds.
+    #[id = "rms"]
+    #[name = "RMS"]
+    Rms,
+}
+
+/// The compressor's settings. These are converted to filter coefficients in
+/// [`Compressor::set_settings()`], so they should only be updated once per block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompressorSettings {
+    /// The level above which the gain is reduced, in dBFS.
+    pub threshold_db: f32,
+    /// The compression ratio, e.g. `4.0` for 4:1.
+    pub ratio: f32,
+    /// The width of the soft knee around the threshold, in decibels. Zero gives a hard knee.
+    pub knee_db: f32,
+    /// The time it takes for the gain reduction to reach `1 - 1/e` of its target, in milliseconds.
+    pub attack_ms: f32,
+    /// The time it takes for the gain reduction to release by `1 - 1/e`, in milliseconds.
+    pub release_ms: f32,
+    pub detector: DetectorMode,
+    /// How much the channels' detector levels are linked, in `[0, 1]`. At 1.0 all channels get the
+    /// same gain reduction based on the loudest channel.
+    pub stereo_link: f32,
+}
+
+/// A feed-forward compressor. The gain computer works in the decibel domain, and the gain
+/// reduction is smoothed with separate attack and release times after the static curve has been
+/// applied.
+#[derive(Debug, Clone)]
+pub struct Compressor {
+    sample_rate: f32,
+    settings: CompressorSettings,
+
+    attack_coefficient: f32,
+    release_coefficient: f32,
+    rms_coefficient: f32,
+
+    /// The mean square value for every channel's detector signal. Only used with
+    /// [`DetectorMode::Rms`].
+    mean_squares: Vec<f32>,
+    /// The current smoothed gain reduction for every channel, in decibels. This is zero or
+    /// negative.
+    gain_reduction_db: Vec<f32>,
+}
+
+impl Default for CompressorSettings {
+    fn default() -> Self {
+        Self {
+            threshold_db: -18.0,
+            ratio: 4.0,
+            knee_db: 6.0,
+            attack_ms: 10.0,
+            release_ms: 100.0,
+            detector: DetectorMode::Peak,
+            stereo_link: 1.0,
+        }
+    }
+}
+
+impl Compressor {
+    pub fn new(sample_rate: f32, num_channels: usize) -> Self {
+        nih_debug_assert!(num_channels <= MAX_NUM_CHANNELS);
+
+        let mut compressor = Self {
+            sample_rate,
+            settings: CompressorSettings::default(),
+
+            attack_coefficient: 0.0,
+            release_coefficient: 0.0,
+            rms_coefficient: one_pole_coefficient(RMS_WINDOW_MS, sample_rate),
+
+            mean_squares: vec![0.0; num_channels],
+            gain_reduction_db: vec![0.0; num_channels],
+        };
+        compressor.set_settings(CompressorSettings::default());
+
+        compressor
+    }
+
+    pub fn set_settings(&mut self, settings: CompressorSettings) {
+        nih_debug_assert!(settings.ratio >= 1.0);
+        nih_debug_assert!(settings.knee_db >= 0.0);
+
+        self.attack_coefficient = one_pole_coefficient(settings.attack_ms, self.sample_rate);
+        self.release_coefficient = one_pole_coefficient(settings.release_ms, self.sample_rate);
+        self.settings = settings;
+    }
+
+    pub fn reset(&mut self) {
+        self.mean_squares.fill(0.0);
+        self.gain_reduction_db.fill(0.0);
+    }
+
+    /// The largest gain reduction across all channels, in decibels. This is zero or negative.
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.gain_reduction_db
+            .iter()
+            .fold(0.0f32, |acc, gain_reduction| acc.min(*gain_reduction))
+    }
+
+    /// Compress a single frame containing one sample for every channel. The gain reduction is based
+    /// on `detector_frame`, which is either the same signal or the sidechain input.
+    pub fn process(&mut self, frame: &mut [f32], detector_frame: &[f32]) {
+        nih_debug_assert_eq!(frame.len(), self.gain_reduction_db.len());
+        nih_debug_assert_eq!(detector_frame.len(), self.gain_reduction_db.len());
+
+        let mut levels_db = [0.0; MAX_NUM_CHANNELS];
+        let levels_db = &mut levels_db[..frame.len()];
+        for ((level_db, sample), mean_square) in levels_db
+            .iter_mut()
+            .zip(detector_frame)
+            .zip(&mut self.mean_squares)
+        {
+            let level = match self.settings.detector {
+                DetectorMode::Peak => sample.abs(),
+                DetectorMode::Rms => {
+                    *mean_square =
+                        sample * sample + (*mean_square - sample * sample) * self.rms_coefficient;
+                    mean_square.sqrt()
+                }
+            };
+
+            *level_db = util::gain_to_db_fast(level);
+        }
+
+        let linked_level_db = levels_db
+            .iter()
+            .fold(util::MINUS_INFINITY_DB, |acc, level_db| acc.max(*level_db));
+        for ((sample, level_db), gain_reduction_db) in frame
+            .iter_mut()
+            .zip(levels_db.iter())
+            .zip(&mut self.gain_reduction_db)
+        {
+            let level_db = level_db + (linked_level_db - level_db) * self.settings.stereo_link;
+            let target_gain_reduction_db = static_curve_gain_reduction(
+                level_db,
+                self.settings.threshold_db,
+                self.settings.ratio,
+                self.settings.knee_db,
+            );
+
+            // More gain reduction means a more negative value
+            let coefficient = if target_gain_reduction_db < *gain_reduction_db {
+                self.attack_coefficient
+            } else {
+                self.release_coefficient
+            };
+            *gain_reduction_db = target_gain_reduction_db
+                + (*gain_reduction_db - target_gain_reduction_db) * coefficient;
+
+            *sample *= util::db_to_gain_fast(*gain_reduction_db);
+        }
+    }
+}
+
+/// Compute the gain reduction in decibels for an input level in decibels. This is the soft knee
+/// curve from Giannoulis, Massberg and Reiss' "Digital Dynamic Range Compressor Design - A
+/// Tutorial and Analysis". The result is zero or negative.
+pub fn static_curve_gain_reduction(
+    level_db: f32,
+    threshold_db: f32,
+    ratio: f32,
+    knee_db: f32,
+) -> f32 {
+    let overshoot_db = level_db - threshold_db;
+    let output_db = if 2.0 * overshoot_db < -knee_db {
+        level_db
+    } else if knee_db > 0.0 && 2.0 * overshoot_db.abs() <= knee_db {
+        let knee_overshoot_db = overshoot_db + knee_db / 2.0;
+        level_db + (ratio.recip() - 1.0) * knee_overshoot_db * knee_overshoot_db / (2.0 * knee_db)
+    } else {
+        threshold_db + overshoot_db / ratio
+    };
+
+    output_db - level_db
+}
+
+/// The coefficient for a one-pole filter that reaches `1 - 1/e` of the way to its target after
+/// `time_ms` milliseconds.
+fn one_pole_coefficient(time_ms: f32, sample_rate: f32) -> f32 {
+    if time_ms <= 0.0 {
+        0.0
+    } else {
+        (-1.0 / (time_ms / 1000.0 * sample_rate)).exp()
+    }
+}
+
+#[cfg(test)]
+fn assert_approx_eq(actual: f32, expected: f32, tolerance: f32) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{actual} is not within {tolerance} of {expected}"
+    );
+}
+
+#[test]
+fn test_static_curve_hard_knee() {
+    assert_eq!(static_curve_gain_reduction(-30.0, -20.0, 4.0, 0.0), 0.0);
+    assert_eq!(static_curve_gain_reduction(-20.0, -20.0, 4.0, 0.0), 0.0);
+    // 8 dB over the threshold at 4:1 leaves 2 dB over the threshold
+    assert_approx_eq(
+        static_curve_gain_reduction(-12.0, -20.0, 4.0, 0.0),
+        -6.0,
+        1e-5,
+    );
+    // And at 1:1 nothing happens
+    assert_approx_eq(static_curve_gain_reduction(0.0, -20.0, 1.0, 0.0), 0.0, 1e-5);
+}
+
+#[test]
+fn test_static_curve_soft_knee() {
+    // Outside of the knee the soft knee curve should match the hard knee curve
+    assert_eq!(static_curve_gain_reduction(-26.0, -20.0, 4.0, 10.0), 0.0);
+    assert_approx_eq(
+        static_curve_gain_reduction(-10.0, -20.0, 4.0, 10.0),
+        -7.5,
+        1e-5,
+    );
+
+    // At the threshold the soft knee already reduces the gain by `(1/R - 1) * W / 8`
+    assert_approx_eq(
+        static_curve_gain_reduction(-20.0, -20.0, 4.0, 10.0),
+        -0.75 * 10.0 / 8.0,
+        1e-5,
+    );
+
+    // And the curve should be continuous at the edges of the knee
+    for edge_db in [-25.0, -15.0] {
+        let below = static_curve_gain_reduction(edge_db - 0.001, -20.0, 4.0, 10.0);
+        let above = static_curve_gain_reduction(edge_db + 0.001, -20.0, 4.0, 10.0);
+        assert_approx_eq(below, above, 1e-2);
+    }
+}
+
+#[cfg(test)]
+fn test_compressor(settings: CompressorSettings, num_channels: usize) -> Compressor {
+    let mut compressor = Compressor::new(1000.0, num_channels);
+    compressor.set_settings(settings);
+
+    compressor
+}
+
+#[test]
+fn test_attack_and_release_timing() {
+    // At a 1 kHz sample rate every sample is one millisecond
+    let settings = CompressorSettings {
+        threshold_db: -20.0,
+        ratio: f32::INFINITY,
+        knee_db: 0.0,
+        attack_ms: 10.0,
+        release_ms: 100.0,
+        ..CompressorSettings::default()
+    };
+    let mut compressor = test_compressor(settings, 1);
+
+    // A step to 0 dBFS needs 20 dB of gain reduction. After the attack time 63.2% of that should
+    // have been reached.
+    for _ in 0..10 {
+        compressor.process(&mut [1.0], &[1.0]);
+    }
+    assert_approx_eq(compressor.gain_reduction_db(), -20.0 * 0.632, 0.1);
+
+    for _ in 0..990 {
+        compressor.process(&mut [1.0], &[1.0]);
+    }
+    assert_approx_eq(compressor.gain_reduction_db(), -20.0, 1e-3);
+
+    // The same goes for the release after dropping below the threshold again
+    for _ in 0..100 {
+        compressor.process(&mut [0.01], &[0.01]);
+    }
+    assert_approx_eq(compressor.gain_reduction_db(), -20.0 * 0.368, 0.1);
+}
+
+#[test]
+fn test_rms_detector() {
+    // A full scale sine wave has an RMS level of -3 dBFS, so a hard knee compressor at -3 dBFS with
+    // a peak detector should compress it but one with an RMS detector should not
+    let settings = CompressorSettings {
+        threshold_db: -2.5,
+        knee_db: 0.0,
+        attack_ms: 0.0,
+        release_ms: 1000.0,
+        ..CompressorSettings::default()
+    };
+    let mut peak_compressor = Compressor::new(48000.0, 1);
+    peak_compressor.set_settings(settings);
+    let mut rms_compressor = Compressor::new(48000.0, 1);
+    rms_compressor.set_settings(CompressorSettings {
+        detector: DetectorMode::Rms,
+        ..settings
+    });
+
+    // This is a 1 kHz sine wave
+    for i in 0..48000 {
+        let sample = (i as f32 * std::f32::consts::TAU / 48.0).sin();
+        peak_compressor.process(&mut [sample], &[sample]);
+        rms_compressor.process(&mut [sample], &[sample]);
+    }
+    assert!(peak_compressor.gain_reduction_db() < -0.1);
+    assert_eq!(rms_compressor.gain_reduction_db(), 0.0);
+}
+
+#[test]
+fn test_stereo_link() {
+    let settings = CompressorSettings {
+        threshold_db: -20.0,
+        knee_db: 0.0,
+        attack_ms: 0.0,
+        ..CompressorSettings::default()
+    };
+
+    // With the channels fully linked, a signal only on the left channel also ducks the right
+    // channel
+    let mut linked = test_compressor(settings, 2);
+    let mut frame = [1.0, 0.1];
+    linked.process(&mut frame, &[1.0, 0.1]);
+    assert_approx_eq(util::gain_to_db(frame[0]), -15.0, 1e-3);
+    assert_approx_eq(util::gain_to_db(frame[1]), -35.0, 1e-3);
+
+    let mut unlinked = test_compressor(
+        CompressorSettings {
+            stereo_link: 0.0,
+            ..settings
+        },
+        2,
+    );
+    let mut frame = [1.0, 0.1];
+    unlinked.process(&mut frame, &[1.0, 0.1]);
+    assert_approx_eq(util::gain_to_db(frame[0]), -15.0, 1e-3);
+    assert_approx_eq(frame[1], 0.1, 1e-6);
+}
+
+#[test]
+fn test_sidechain_detector() {
+    let settings = CompressorSettings {
+        threshold_db: -20.0,
+        knee_db: 0.0,
+        attack_ms: 0.0,
+        ..CompressorSettings::default()
+    };
+    let mut compressor = test_compressor(settings, 1);
+
+    // A quiet signal gets ducked by a loud detector signal
+    let mut frame = [0.01];
+    compressor.process(&mut frame, &[1.0]);
+    assert_approx_eq(util::gain_to_db(frame[0]), -40.0 - 15.0, 1e-3);
+}
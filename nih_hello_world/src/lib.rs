@@ -1,31 +1,75 @@
+use atomic_float::AtomicF32;
+use compressor::{Compressor, CompressorSettings, DetectorMode, MAX_NUM_CHANNELS};
 use nih_plug::prelude::*;
+use nih_plug_egui::{create_egui_editor, egui, widgets, EguiState};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
-// This is a shortened version of the gain example with most comments removed, check out
+mod compressor;
+mod sidechain;
+
+// This started out as a shortened version of the gain example, check out
 // https://github.com/robbert-vdh/nih-plug/blob/master/plugins/examples/gain/src/lib.rs to get
-// started
+// started. It's now a feed-forward compressor with a sidechain input.
 
 struct TestNihPlugin {
     params: Arc<TestNihPluginParams>,
+
+    compressor: Compressor,
+    /// The largest gain reduction during the last processed block, in decibels. This is shared
+    /// with the editor so it can draw a gain reduction meter.
+    gain_reduction_db: Arc<AtomicF32>,
 }
 
 #[derive(Params)]
 struct TestNihPluginParams {
+    /// The editor state, saved together with the parameter state so the custom scaling can be
+    /// restored.
+    #[persist = "editor-state"]
+    editor_state: Arc<EguiState>,
+
+    #[id = "threshold"]
+    pub threshold: FloatParam,
+    #[id = "ratio"]
+    pub ratio: FloatParam,
+    #[id = "knee"]
+    pub knee: FloatParam,
+    #[id = "attack"]
+    pub attack: FloatParam,
+    #[id = "release"]
+    pub release: FloatParam,
+
     /// The parameter's ID is used to identify the parameter in the wrappred plugin API. As long as
     /// these IDs remain constant, you can rename and reorder these fields as you wish. The
     /// parameters are exposed to the host in the same order they were defined. In this case, this
-    /// gain parameter is stored as linear gain while the values are displayed in decibels.
+    /// gain parameter is stored as linear gain while the values are displayed in decibels. This
+    /// used to be a plain gain parameter, so it keeps its old ID.
     #[id = "gain"]
-    pub gain: FloatParam,
-
+    pub makeup_gain: FloatParam,
+    /// A final gain stage after the compressor. This used to be an unused test parameter, and it
+    /// keeps that parameter's ID and range so existing sessions and automation still load.
     #[id = "test"]
-    pub test: FloatParam,
+    pub output_gain: FloatParam,
+
+    #[id = "detector"]
+    pub detector: EnumParam<DetectorMode>,
+    /// How much the channels' detector levels are linked. At 100% both channels are compressed by
+    /// the same amount.
+    #[id = "link"]
+    pub stereo_link: FloatParam,
+    /// Use the sidechain input instead of the main input for the detector. This does nothing when
+    /// the host picked one of the audio IO layouts without a sidechain input.
+    #[id = "sidechain"]
+    pub external_sidechain: BoolParam,
 }
 
 impl Default for TestNihPlugin {
     fn default() -> Self {
         Self {
             params: Arc::new(TestNihPluginParams::default()),
+
+            compressor: Compressor::new(1.0, 0),
+            gain_reduction_db: Arc::new(AtomicF32::new(0.0)),
         }
     }
 }
@@ -33,11 +77,67 @@ impl Default for TestNihPlugin {
 impl Default for TestNihPluginParams {
     fn default() -> Self {
         Self {
+            editor_state: EguiState::from_size(300, 460),
+
+            threshold: FloatParam::new(
+                "Threshold",
+                -18.0,
+                FloatRange::Linear {
+                    min: -60.0,
+                    max: 0.0,
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" dB"),
+            ratio: FloatParam::new(
+                "Ratio",
+                4.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(":1"),
+            knee: FloatParam::new(
+                "Knee",
+                6.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 24.0,
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" dB"),
+            attack: FloatParam::new(
+                "Attack",
+                10.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 200.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms"),
+            release: FloatParam::new(
+                "Release",
+                100.0,
+                FloatRange::Skewed {
+                    min: 5.0,
+                    max: 2000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_step_size(1.0)
+            .with_unit(" ms"),
+
             // This gain is stored as linear gain. NIH-plug comes with useful conversion functions
             // to treat these kinds of parameters as if we were dealing with decibels. Storing this
             // as decibels is easier to work with, but requires a conversion for every sample.
-            gain: FloatParam::new(
-                "Gain",
+            makeup_gain: FloatParam::new(
+                "Makeup Gain",
                 util::db_to_gain(0.0),
                 FloatRange::Skewed {
                     min: util::db_to_gain(-30.0),
@@ -56,27 +156,30 @@ impl Default for TestNihPluginParams {
             // `.with_step_size(0.1)` function to get internal rounding.
             .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
             .with_string_to_value(formatters::s2v_f32_gain_to_db()),
-
-            test: FloatParam::new(
-                "Test",
+            output_gain: FloatParam::new(
+                "Output Gain",
                 util::db_to_gain(0.0),
                 FloatRange::Skewed {
                     min: util::db_to_gain(-30.0),
                     max: util::db_to_gain(30.0),
-                    // This makes the range appear as if it was linear when displaying the values as
-                    // decibels
                     factor: FloatRange::gain_skew_factor(-30.0, 30.0),
                 },
             )
-            // Because the gain parameter is stored as linear gain instead of storing the value as
-            // decibels, we need logarithmic smoothing
             .with_smoother(SmoothingStyle::Logarithmic(50.0))
             .with_unit(" dB")
-            // There are many predefined formatters we can use here. If the gain was stored as
-            // decibels instead of as a linear gain value, we could have also used the
-            // `.with_step_size(0.1)` function to get internal rounding.
             .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
             .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+
+            detector: EnumParam::new("Detector", DetectorMode::Peak),
+            stereo_link: FloatParam::new(
+                "Stereo Link",
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            external_sidechain: BoolParam::new("External Sidechain", false),
         }
     }
 }
@@ -91,18 +194,49 @@ impl Plugin for TestNihPlugin {
 
     // The first audio IO layout is used as the default. The other layouts may be selected either
     // explicitly or automatically by the host or the user depending on the plugin API/backend.
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
-        main_input_channels: NonZeroU32::new(2),
-        main_output_channels: NonZeroU32::new(2),
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
 
-        aux_input_ports: &[],
-        aux_output_ports: &[],
+            // The compressor's detector can optionally listen to this input instead of the main
+            // input
+            aux_input_ports: &[new_nonzero_u32(2)],
+            aux_output_ports: &[],
 
-        // Individual ports and the layout as a whole can be named here. By default these names
-        // are generated as needed. This layout will be called 'Stereo', while a layout with
-        // only one input and output channel would be called 'Mono'.
-        names: PortNames::const_default(),
-    }];
+            // Individual ports and the layout as a whole can be named here. By default these names
+            // are generated as needed, so the two layouts without a sidechain at the bottom will be
+            // called 'Stereo' and 'Mono'.
+            names: PortNames {
+                layout: Some("Stereo with Sidechain"),
+                aux_inputs: &["Sidechain"],
+                ..PortNames::const_default()
+            },
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            aux_input_ports: &[new_nonzero_u32(1)],
+            names: PortNames {
+                layout: Some("Mono with Sidechain"),
+                aux_inputs: &["Sidechain"],
+                ..PortNames::const_default()
+            },
+            ..AudioIOLayout::const_default()
+        },
+        // Hosts that don't support sidechain inputs can use these. The detector then always uses
+        // the main input.
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            ..AudioIOLayout::const_default()
+        },
+    ];
 
     const MIDI_INPUT: MidiConfig = MidiConfig::None;
     const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
@@ -122,36 +256,142 @@ impl Plugin for TestNihPlugin {
         self.params.clone()
     }
 
+    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+        let params = self.params.clone();
+        let gain_reduction_db = self.gain_reduction_db.clone();
+        create_egui_editor(
+            self.params.editor_state.clone(),
+            (),
+            |_, _| {},
+            move |egui_ctx, setter, _state| {
+                egui::CentralPanel::default().show(egui_ctx, |ui| {
+                    ui.label("Threshold");
+                    ui.add(widgets::ParamSlider::for_param(&params.threshold, setter));
+                    ui.label("Ratio");
+                    ui.add(widgets::ParamSlider::for_param(&params.ratio, setter));
+                    ui.label("Knee");
+                    ui.add(widgets::ParamSlider::for_param(&params.knee, setter));
+                    ui.label("Attack");
+                    ui.add(widgets::ParamSlider::for_param(&params.attack, setter));
+                    ui.label("Release");
+                    ui.add(widgets::ParamSlider::for_param(&params.release, setter));
+                    ui.label("Makeup Gain");
+                    ui.add(widgets::ParamSlider::for_param(&params.makeup_gain, setter));
+                    ui.label("Output Gain");
+                    ui.add(widgets::ParamSlider::for_param(&params.output_gain, setter));
+                    ui.label("Detector");
+                    ui.add(widgets::ParamSlider::for_param(&params.detector, setter));
+                    ui.label("Stereo Link");
+                    ui.add(widgets::ParamSlider::for_param(&params.stereo_link, setter));
+                    ui.label("External Sidechain");
+                    ui.add(widgets::ParamSlider::for_param(
+                        &params.external_sidechain,
+                        setter,
+                    ));
+
+                    // The meter shows up to 30 dB of gain reduction
+                    let gain_reduction_db = gain_reduction_db.load(Ordering::Relaxed);
+                    ui.allocate_space(egui::Vec2::splat(2.0));
+                    ui.label("Gain Reduction");
+                    ui.add(
+                        egui::widgets::ProgressBar::new(-gain_reduction_db / 30.0)
+                            .text(format!("{gain_reduction_db:.1} dB")),
+                    );
+                });
+            },
+        )
+    }
+
     fn initialize(
         &mut self,
-        _audio_io_layout: &AudioIOLayout,
-        _buffer_config: &BufferConfig,
+        audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
         _context: &mut impl InitContext<Self>,
     ) -> bool {
         // Resize buffers and perform other potentially expensive initialization operations here.
-        // The `reset()` function is always called right after this function. You can remove this
-        // function if you do not need it.
+        // The `reset()` function is always called right after this function.
+        let num_channels = audio_io_layout
+            .main_output_channels
+            .expect("Plugin does not have a main output")
+            .get() as usize;
+        self.compressor = Compressor::new(buffer_config.sample_rate, num_channels);
+
         true
     }
 
     fn reset(&mut self) {
         // Reset buffers and envelopes here. This can be called from the audio thread and may not
-        // allocate. You can remove this function if you do not need it.
+        // allocate.
+        self.compressor.reset();
     }
 
     fn process(
         &mut self,
         buffer: &mut Buffer,
-        _aux: &mut AuxiliaryBuffers,
+        aux: &mut AuxiliaryBuffers,
         _context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        for channel_samples in buffer.iter_samples() {
-            // Smoothing is optionally built into the parameters themselves
-            let gain = self.params.gain.smoothed.next();
+        // None of these parameters are smoothed, so the coefficients only need to be computed once
+        // per block
+        self.compressor.set_settings(CompressorSettings {
+            threshold_db: self.params.threshold.value(),
+            ratio: self.params.ratio.value(),
+            knee_db: self.params.knee.value(),
+            attack_ms: self.params.attack.value(),
+            release_ms: self.params.release.value(),
+            detector: self.params.detector.value(),
+            stereo_link: self.params.stereo_link.value(),
+        });
+        let external_sidechain = self.params.external_sidechain.value();
+
+        let num_samples = buffer.samples();
+        let main = buffer.as_slice();
+        let num_channels = main.len();
+        // The sidechain input only exists if the host picked one of the layouts with a sidechain
+        // port. Otherwise the detector falls back to the main input.
+        let sidechain = match aux.inputs.first() {
+            Some(sidechain) if external_sidechain => Some(sidechain.as_slice_immutable()),
+            _ => None,
+        };
+        let num_sidechain_channels = sidechain.map_or(0, |sidechain| sidechain.len());
 
-            for sample in channel_samples {
-                *sample *= gain;
+        let mut max_gain_reduction_db = 0.0f32;
+        for sample_idx in 0..num_samples {
+            let mut frame = [0.0; MAX_NUM_CHANNELS];
+            for (sample, channel) in frame.iter_mut().zip(main.iter()) {
+                *sample = channel[sample_idx];
             }
+            let mut sidechain_frame = [0.0; MAX_NUM_CHANNELS];
+            if let Some(sidechain) = sidechain {
+                for (sample, channel) in sidechain_frame.iter_mut().zip(sidechain) {
+                    *sample = channel[sample_idx];
+                }
+            }
+
+            let mut detector_frame = [0.0; MAX_NUM_CHANNELS];
+            sidechain::fill_detector_frame(
+                &frame[..num_channels],
+                sidechain.map(|_| &sidechain_frame[..num_sidechain_channels]),
+                &mut detector_frame[..num_channels],
+            );
+
+            self.compressor
+                .process(&mut frame[..num_channels], &detector_frame[..num_channels]);
+            max_gain_reduction_db = max_gain_reduction_db.min(self.compressor.gain_reduction_db());
+
+            // Smoothing is optionally built into the parameters themselves
+            let gain =
+                self.params.makeup_gain.smoothed.next() * self.params.output_gain.smoothed.next();
+            for (channel, sample) in main.iter_mut().zip(frame) {
+                channel[sample_idx] = sample * gain;
+            }
+        }
+
+        // To save resources, a plugin can (and probably should!) only perform expensive
+        // calculations that are only displayed on the GUI while the GUI is open
+        if self.params.editor_state.is_open() {
+            self.gain_reduction_db
+                .store(max_gain_reduction_db, Ordering::Relaxed);
         }
 
         ProcessStatus::Normal
@@ -165,7 +405,11 @@ impl ClapPlugin for TestNihPlugin {
     const CLAP_SUPPORT_URL: Option<&'static str> = None;
 
     // Don't forget to change these features
-    const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::AudioEffect, ClapFeature::Stereo];
+    const CLAP_FEATURES: &'static [ClapFeature] = &[
+        ClapFeature::AudioEffect,
+        ClapFeature::Stereo,
+        ClapFeature::Compressor,
+    ];
 }
 
 impl Vst3Plugin for TestNihPlugin {
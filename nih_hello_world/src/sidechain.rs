@@ -0,0 +1,89 @@
+/// Fill `detector_frame` with the signal the compressor's detector should listen to. This is the
+/// sidechain input if the active audio IO layout has one and it's been enabled, and the main input
+/// otherwise. `detector_frame` has the same number of channels as `main_frame`.
+///
+/// The sidechain input does not need to have the same number of channels as the main input. A mono
+/// sidechain is used for every channel, and when the main input has fewer channels than the
+/// sidechain the sidechain's loudest channel is used so the detector doesn't miss peaks that only
+/// occur on one side.
+pub fn fill_detector_frame(
+    main_frame: &[f32],
+    sidechain_frame: Option<&[f32]>,
+    detector_frame: &mut [f32],
+) {
+    nih_plug::nih_debug_assert_eq!(main_frame.len(), detector_frame.len());
+
+    match sidechain_frame {
+        Some(sidechain_frame) if !sidechain_frame.is_empty() => {
+            if sidechain_frame.len() == detector_frame.len() {
+                detector_frame.copy_from_slice(sidechain_frame);
+            } else if detector_frame.len() == 1 {
+                detector_frame[0] = sidechain_frame.iter().fold(0.0f32, |acc, sample| {
+                    if sample.abs() > acc.abs() {
+                        *sample
+                    } else {
+                        acc
+                    }
+                });
+            } else {
+                for (channel_idx, sample) in detector_frame.iter_mut().enumerate() {
+                    *sample = sidechain_frame[channel_idx % sidechain_frame.len()];
+                }
+            }
+        }
+        _ => detector_frame.copy_from_slice(main_frame),
+    }
+}
+
+#[test]
+fn test_layouts() {
+    use crate::TestNihPlugin;
+    use nih_plug::prelude::Plugin;
+
+    let main_input = [0.1, 0.2];
+    let sidechain_input = [0.3, 0.4];
+    for layout in TestNihPlugin::AUDIO_IO_LAYOUTS {
+        let num_channels = layout.main_input_channels.unwrap().get() as usize;
+        let main_frame = &main_input[..num_channels];
+        let sidechain_frame = layout
+            .aux_input_ports
+            .first()
+            .map(|num_sidechain_channels| {
+                &sidechain_input[..num_sidechain_channels.get() as usize]
+            });
+
+        let mut detector_frame = [0.0; 2];
+        let detector_frame = &mut detector_frame[..num_channels];
+        fill_detector_frame(main_frame, sidechain_frame, detector_frame);
+
+        // Layouts with a sidechain input listen to it, and the others fall back to the main input
+        match sidechain_frame {
+            Some(_) => assert_eq!(detector_frame, &sidechain_input[..num_channels]),
+            None => assert_eq!(detector_frame, main_frame),
+        }
+    }
+}
+
+#[test]
+fn test_disabled_sidechain() {
+    let mut detector_frame = [0.0; 2];
+    fill_detector_frame(&[0.1, 0.2], None, &mut detector_frame);
+    assert_eq!(detector_frame, [0.1, 0.2]);
+
+    // An empty sidechain frame is treated the same as a missing one
+    fill_detector_frame(&[0.1, 0.2], Some(&[]), &mut detector_frame);
+    assert_eq!(detector_frame, [0.1, 0.2]);
+}
+
+#[test]
+fn test_mismatched_channel_count() {
+    // A mono sidechain is used for both channels of a stereo input
+    let mut detector_frame = [0.0; 2];
+    fill_detector_frame(&[0.1, 0.2], Some(&[0.5]), &mut detector_frame);
+    assert_eq!(detector_frame, [0.5, 0.5]);
+
+    // And a mono input uses the loudest channel of a stereo sidechain
+    let mut detector_frame = [0.0; 1];
+    fill_detector_frame(&[0.1], Some(&[0.3, -0.6]), &mut detector_frame);
+    assert_eq!(detector_frame, [-0.6]);
+}
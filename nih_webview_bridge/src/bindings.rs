@@ -0,0 +1,136 @@
+//! Generates the web UIs' bindings for the messages in [`protocol`][crate::protocol]. TypeScript
+//! UIs get the messages' types generated with ts-rs, and plain JavaScript UIs get
+//! [`PROTOCOL_VERSION`] and a constructor for every [`Action`]. The bindings are checked in so the
+//! UIs can be built without running the tests, and the plugins' tests use [`check_bindings()`] to
+//! fail when they no longer match the Rust types.
+
+use serde_json::Value;
+use std::env;
+use std::fs;
+use ts_rs::TS;
+
+use crate::protocol::{Action, ParamChange, ParamInfo, PluginMessage, PROTOCOL_VERSION};
+
+/// The first lines of every generated file.
+pub const HEADER: &str =
+    "// Generated from the plugin's Rust types, don't edit this by hand. Run\n\
+     // `UPDATE_BINDINGS=1 cargo test` after changing any of the messages.\n";
+
+/// A TypeScript module exporting [`PROTOCOL_VERSION`] and the types for all messages, followed by
+/// `extra_declarations`. Those are the declarations for the plugin's own messages, created with
+/// ts-rs' `TS::decl()`.
+pub fn typescript_bindings(extra_declarations: &[String]) -> String {
+    let declarations = [
+        Action::decl(),
+        PluginMessage::decl(),
+        ParamInfo::decl(),
+        ParamChange::decl(),
+        Value::decl(),
+    ];
+
+    let mut bindings = format!("{HEADER}\nexport const PROTOCOL_VERSION = {PROTOCOL_VERSION}\n");
+    for declaration in declarations.iter().chain(extra_declarations) {
+        bindings.push_str(&format!("\nexport {declaration}\n"));
+    }
+
+    bindings
+}
+
+/// JavaScript defining `PROTOCOL_VERSION` and an `actions` object with a constructor for every
+/// [`Action`], for web UIs without a build step. The constructors take the action's fields in the
+/// order they're declared in, so `actions.setParameter("gain", 0.5)` creates
+/// `{ type: "SetParameter", param: "gain", value: 0.5 }`.
+pub fn javascript_bindings() -> String {
+    let mut bindings =
+        format!("{HEADER}const PROTOCOL_VERSION = {PROTOCOL_VERSION};\nconst actions = {{\n");
+    for action in example_actions() {
+        let json = serde_json::to_string(&action).expect("Could not serialize an action");
+        let Value::Object(object) = serde_json::to_value(&action).unwrap() else {
+            unreachable!("Actions are serialized as objects");
+        };
+
+        // `serde_json` sorts the object's keys, so the fields are put back in the order the
+        // serializer wrote them in
+        let mut fields: Vec<&str> = object
+            .keys()
+            .map(String::as_str)
+            .filter(|&key| key != "type")
+            .collect();
+        fields.sort_by_key(|field| json.find(&format!("\"{field}\":")));
+
+        let fields = fields.join(", ");
+        let type_ = &object["type"];
+        bindings.push_str(&format!(
+            "  {}: ({fields}) => ({{ type: {type_}{}{fields} }}),\n",
+            constructor_name(&action),
+            if fields.is_empty() { "" } else { ", " }
+        ));
+    }
+    bindings.push_str("};\n");
+
+    bindings
+}
+
+/// Compare generated bindings to the checked in file at `path`, panicking if they differ. When the
+/// `UPDATE_BINDINGS` environment variable is set the file is overwritten instead.
+pub fn check_bindings(path: &str, bindings: &str) {
+    if env::var_os("UPDATE_BINDINGS").is_some() {
+        fs::write(path, bindings).expect("Could not write the bindings");
+        return;
+    }
+
+    let checked_in = fs::read_to_string(path).unwrap_or_default();
+    assert!(
+        checked_in == bindings,
+        "'{path}' is out of date, run the tests with UPDATE_BINDINGS=1 to regenerate it"
+    );
+}
+
+/// One of every action, used to find their fields.
+fn example_actions() -> [Action; 6] {
+    [
+        Action::Init { version: 0 },
+        Action::SetSize {
+            width: 0,
+            height: 0,
+        },
+        Action::BeginSetParameter {
+            param: String::new(),
+        },
+        Action::SetParameter {
+            param: String::new(),
+            value: 0.0,
+        },
+        Action::EndSetParameter {
+            param: String::new(),
+        },
+        Action::SetUiState { state: Value::Null },
+    ]
+}
+
+/// The name of the JavaScript constructor for an action. New actions also need to be added to
+/// [`example_actions()`].
+fn constructor_name(action: &Action) -> &'static str {
+    match action {
+        Action::Init { .. } => "init",
+        Action::SetSize { .. } => "setSize",
+        Action::BeginSetParameter { .. } => "beginSetParameter",
+        Action::SetParameter { .. } => "setParameter",
+        Action::EndSetParameter { .. } => "endSetParameter",
+        Action::SetUiState { .. } => "setUiState",
+    }
+}
+
+#[test]
+fn test_javascript_bindings() {
+    let bindings = javascript_bindings();
+    assert!(bindings.starts_with(HEADER));
+    assert!(bindings.contains(&format!("const PROTOCOL_VERSION = {PROTOCOL_VERSION};\n")));
+    assert!(bindings.contains("  init: (version) => ({ type: \"Init\", version }),\n"));
+    assert!(bindings
+        .contains("  setSize: (width, height) => ({ type: \"SetSize\", width, height }),\n"));
+    assert!(bindings.contains(
+        "  setParameter: (param, value) => ({ type: \"SetParameter\", param, value }),\n"
+    ));
+    assert_eq!(bindings.matches(": (").count(), example_actions().len());
+}
@@ -0,0 +1,95 @@
+//! Lock-free change tracking for parameters, so the editor only sends the parameters that actually
+//! changed to the web UI.
+
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A change flag for every parameter, keyed by the parameters' IDs. The parameters' value changed
+/// callbacks set their flags from any thread, including the audio thread, without locking. A
+/// [`ParamBridge`][crate::ParamBridge] looks up the flags for the parameters in
+/// `Params::param_map()` when it's created, and collects and clears them once per frame. Any number
+/// of changes to a parameter between two frames result in a single update.
+#[derive(Default)]
+pub struct DirtyParams {
+    /// The parameter IDs [`DirtyParams::callback()`] was called with, and their flags. This is only
+    /// locked when creating callbacks and bridges, never by the callbacks themselves.
+    flags: Mutex<Vec<(String, Arc<AtomicBool>)>>,
+}
+
+impl DirtyParams {
+    /// A value changed callback for the parameter with the given ID, for use with the parameters'
+    /// `with_callback()` methods. The ID needs to match the parameter's `#[id]` attribute.
+    pub fn callback<T>(&self, id: &str) -> Arc<dyn Fn(T) + Send + Sync> {
+        let flag = self.flag_or_insert(id);
+        Arc::new(move |_| flag.store(true, Ordering::Relaxed))
+    }
+
+    /// Mark the parameter with the given ID as changed. IDs without a callback are ignored.
+    pub fn mark(&self, id: &str) {
+        if let Some(flag) = self.flag(id) {
+            flag.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// The change flag for the parameter with the given ID, or `None` if no callback was created
+    /// for it.
+    pub(crate) fn flag(&self, id: &str) -> Option<Arc<AtomicBool>> {
+        self.flags
+            .lock()
+            .iter()
+            .find(|(flag_id, _)| flag_id == id)
+            .map(|(_, flag)| flag.clone())
+    }
+
+    /// The IDs of all parameters with a callback.
+    pub(crate) fn ids(&self) -> Vec<String> {
+        self.flags.lock().iter().map(|(id, _)| id.clone()).collect()
+    }
+
+    fn flag_or_insert(&self, id: &str) -> Arc<AtomicBool> {
+        if let Some(flag) = self.flag(id) {
+            return flag;
+        }
+
+        let flag = Arc::new(AtomicBool::new(false));
+        self.flags.lock().push((id.to_owned(), flag.clone()));
+
+        flag
+    }
+}
+
+#[test]
+fn test_callback() {
+    let dirty = DirtyParams::default();
+    let gain = dirty.callback::<f32>("gain");
+    let _mix = dirty.callback::<i32>("mix");
+    assert_eq!(dirty.ids(), ["gain", "mix"]);
+
+    let gain_flag = dirty.flag("gain").unwrap();
+    let mix_flag = dirty.flag("mix").unwrap();
+    assert!(!gain_flag.load(Ordering::Relaxed));
+
+    gain(0.5);
+    gain(0.75);
+    assert!(gain_flag.swap(false, Ordering::Relaxed));
+    assert!(!mix_flag.load(Ordering::Relaxed));
+
+    // A second callback for the same parameter shares its flag
+    dirty.callback::<f32>("gain")(0.25);
+    assert!(gain_flag.load(Ordering::Relaxed));
+    assert_eq!(dirty.ids().len(), 2);
+}
+
+#[test]
+fn test_mark() {
+    let dirty = DirtyParams::default();
+    let _gain = dirty.callback::<f32>("gain");
+
+    dirty.mark("gain");
+    assert!(dirty.flag("gain").unwrap().load(Ordering::Relaxed));
+
+    // Parameters without a callback are ignored
+    dirty.mark("foo");
+    assert!(dirty.flag("foo").is_none());
+}
@@ -0,0 +1,398 @@
+//! Handles the messages from the web UI. The editor's event loop hands every message to a
+//! [`Dispatcher`] together with an [`EditorContext`] and a [`ParamSink`]. Those are implemented for
+//! the webview's `WindowHandler` and for `ParamSetter`, and the tests use mock implementations to
+//! run the whole protocol without opening a window.
+
+use nih_plug::prelude::*;
+use nih_plug_webview::WindowHandler;
+use serde_json::Value;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::editor_state::WebViewEditorState;
+use crate::param_bridge::{ParamBridge, ParamSink};
+use crate::protocol::{parse_action, Action, PluginMessage, ProtocolError, PROTOCOL_VERSION};
+
+/// The parts of the webview editor the dispatcher talks to.
+pub trait EditorContext {
+    /// Send a message to the web UI.
+    fn send_json(&self, json: Value);
+    /// Resize the editor window, in logical pixels.
+    fn resize(&self, width: u32, height: u32);
+    /// The editor window's current size, in logical pixels.
+    fn size(&self) -> (u32, u32);
+}
+
+impl EditorContext for WindowHandler {
+    fn send_json(&self, json: Value) {
+        let _ = WindowHandler::send_json(self, json);
+    }
+
+    fn resize(&self, width: u32, height: u32) {
+        WindowHandler::resize(self, width, height);
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (
+            self.width.load(Ordering::Relaxed),
+            self.height.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Implements the protocol from [`protocol`][crate::protocol] on top of the parameters and the
+/// editor's state.
+pub struct Dispatcher {
+    bridge: ParamBridge,
+    editor_state: Arc<WebViewEditorState>,
+}
+
+impl Dispatcher {
+    pub fn new(bridge: ParamBridge, editor_state: Arc<WebViewEditorState>) -> Self {
+        Self {
+            bridge,
+            editor_state,
+        }
+    }
+
+    /// Handle a message from the web UI. Messages that can't be handled are logged and answered
+    /// with an `error` message.
+    pub fn handle_message(&self, ctx: &impl EditorContext, sink: &impl ParamSink, message: Value) {
+        let result = parse_action(message).and_then(|action| self.handle_action(ctx, sink, action));
+
+        if let Err(error) = result {
+            nih_log!("Invalid message received from web UI: {error}");
+            ctx.send_json(error.to_message().to_json());
+        }
+    }
+
+    /// Send the parameters that changed since the previous call in a single `params_changed`
+    /// message, if any did. This should be called once per frame after all messages have been
+    /// handled.
+    pub fn send_changes(&self, ctx: &impl EditorContext) {
+        let changes = self.bridge.take_changes();
+        if !changes.is_empty() {
+            ctx.send_json(PluginMessage::ParamsChanged { params: changes }.to_json());
+        }
+    }
+
+    fn handle_action(
+        &self,
+        ctx: &impl EditorContext,
+        sink: &impl ParamSink,
+        action: Action,
+    ) -> Result<(), ProtocolError> {
+        match action {
+            Action::Init { .. } => {
+                let param_list = PluginMessage::ParamList {
+                    version: PROTOCOL_VERSION,
+                    params: self.bridge.param_list(),
+                };
+                ctx.send_json(param_list.to_json());
+                let ui_state = PluginMessage::UiState {
+                    state: self.editor_state.ui_state(),
+                };
+                ctx.send_json(ui_state.to_json());
+
+                // nih-plug creates the editor before the host restores the plugin's state, so the
+                // window may not have the restored size yet
+                let (width, height) = self.editor_state.size();
+                if ctx.size() != (width, height) {
+                    ctx.resize(width, height);
+                }
+                ctx.send_json(PluginMessage::SetSize { width, height }.to_json());
+                Ok(())
+            }
+            Action::SetSize { width, height } => {
+                // The web UI is told about the size it actually got, since that may be clamped
+                let (width, height) = self.editor_state.set_size(width, height);
+                ctx.resize(width, height);
+                ctx.send_json(PluginMessage::SetSize { width, height }.to_json());
+                Ok(())
+            }
+            Action::BeginSetParameter { param } => self.bridge.begin_set_parameter(sink, &param),
+            Action::SetParameter { param, value } => {
+                self.bridge.set_parameter_normalized(sink, &param, value)
+            }
+            Action::EndSetParameter { param } => self.bridge.end_set_parameter(sink, &param),
+            Action::SetUiState { state } => {
+                self.editor_state.set_ui_state(state);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Records the messages sent to the web UI, and resizes instantly.
+#[cfg(test)]
+struct TestContext {
+    sent: std::cell::RefCell<Vec<Value>>,
+    size: std::cell::Cell<(u32, u32)>,
+}
+
+#[cfg(test)]
+impl EditorContext for TestContext {
+    fn send_json(&self, json: Value) {
+        self.sent.borrow_mut().push(json);
+    }
+
+    fn resize(&self, width: u32, height: u32) {
+        self.size.set((width, height));
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.size.get()
+    }
+}
+
+#[cfg(test)]
+impl TestContext {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            sent: Default::default(),
+            size: std::cell::Cell::new((width, height)),
+        }
+    }
+
+    /// The messages sent since the last call.
+    fn take_sent(&self) -> Vec<Value> {
+        self.sent.take()
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParamEvent {
+    Begin(ParamPtr),
+    Set(ParamPtr, f32),
+    End(ParamPtr),
+}
+
+/// Records the parameter changes instead of sending them to the host.
+#[cfg(test)]
+#[derive(Default)]
+struct TestSink {
+    events: std::cell::RefCell<Vec<ParamEvent>>,
+}
+
+#[cfg(test)]
+impl ParamSink for TestSink {
+    fn begin_set_parameter(&self, param: ParamPtr) {
+        self.events.borrow_mut().push(ParamEvent::Begin(param));
+    }
+
+    fn set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        self.events
+            .borrow_mut()
+            .push(ParamEvent::Set(param, normalized));
+    }
+
+    fn end_set_parameter(&self, param: ParamPtr) {
+        self.events.borrow_mut().push(ParamEvent::End(param));
+    }
+}
+
+#[cfg(test)]
+fn test_dispatcher() -> (Dispatcher, Arc<crate::param_bridge::TestParams>) {
+    let params = Arc::new(crate::param_bridge::TestParams::default());
+    let bridge = ParamBridge::new(params.clone(), params.dirty_params.clone());
+
+    (Dispatcher::new(bridge, params.editor_state.clone()), params)
+}
+
+#[cfg(test)]
+use serde_json::json;
+
+#[test]
+fn test_init() {
+    let (dispatcher, params) = test_dispatcher();
+    let ctx = TestContext::new(300, 200);
+    let sink = TestSink::default();
+
+    // Everything that changed before the web UI was initialized is included in the parameter list
+    params.dirty_params.mark("gain");
+    dispatcher.handle_message(
+        &ctx,
+        &sink,
+        json!({ "type": "Init", "version": PROTOCOL_VERSION }),
+    );
+    dispatcher.send_changes(&ctx);
+
+    let sent = ctx.take_sent();
+    let types: Vec<_> = sent.iter().map(|message| &message["type"]).collect();
+    assert_eq!(types, ["param_list", "ui_state", "set_size"]);
+    assert_eq!(sent[0]["version"], PROTOCOL_VERSION);
+    assert_eq!(sent[0]["params"][0]["id"], "gain");
+    assert_eq!(sent[1]["state"], Value::Null);
+    // The window is resized to the size stored in the editor state
+    assert_eq!(
+        sent[2],
+        json!({ "type": "set_size", "width": 200, "height": 200 })
+    );
+    assert_eq!(ctx.size(), (200, 200));
+
+    dispatcher.handle_message(&ctx, &sink, json!({ "type": "Init", "version": 0 }));
+    let sent = ctx.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0]["code"], "unsupported_version");
+}
+
+#[test]
+fn test_init_restored_size() {
+    use nih_plug::params::persist::PersistentField;
+
+    let (dispatcher, params) = test_dispatcher();
+    let ctx = TestContext::new(200, 200);
+    let sink = TestSink::default();
+
+    // The host restores the state after the editor has been created with the default size
+    params
+        .editor_state
+        .set(serde_json::from_value(json!({ "size": [640, 480] })).unwrap());
+    dispatcher.handle_message(
+        &ctx,
+        &sink,
+        json!({ "type": "Init", "version": PROTOCOL_VERSION }),
+    );
+
+    assert_eq!(ctx.size(), (640, 480));
+    assert_eq!(
+        ctx.take_sent()[2],
+        json!({ "type": "set_size", "width": 640, "height": 480 })
+    );
+}
+
+#[test]
+fn test_set_parameter() {
+    let (dispatcher, params) = test_dispatcher();
+    let ctx = TestContext::new(300, 200);
+    let sink = TestSink::default();
+
+    for message in [
+        json!({ "type": "BeginSetParameter", "param": "gain" }),
+        json!({ "type": "SetParameter", "param": "gain", "value": 0.25 }),
+        json!({ "type": "EndSetParameter", "param": "gain" }),
+    ] {
+        dispatcher.handle_message(&ctx, &sink, message);
+    }
+
+    let gain = params.gain.as_ptr();
+    assert_eq!(
+        sink.events.take(),
+        [
+            ParamEvent::Begin(gain),
+            ParamEvent::Set(gain, 0.25),
+            ParamEvent::End(gain)
+        ]
+    );
+    assert!(ctx.take_sent().is_empty());
+
+    // Invalid changes never reach the host
+    dispatcher.handle_message(
+        &ctx,
+        &sink,
+        json!({ "type": "SetParameter", "param": "foo", "value": 0.25 }),
+    );
+    dispatcher.handle_message(
+        &ctx,
+        &sink,
+        json!({ "type": "SetParameter", "param": "gain", "value": 1.5 }),
+    );
+    assert!(sink.events.take().is_empty());
+    let codes: Vec<_> = ctx
+        .take_sent()
+        .into_iter()
+        .map(|message| message["code"].clone())
+        .collect();
+    assert_eq!(codes, ["unknown_parameter", "invalid_value"]);
+}
+
+#[test]
+fn test_resize() {
+    let (dispatcher, params) = test_dispatcher();
+    let ctx = TestContext::new(300, 200);
+    let sink = TestSink::default();
+
+    dispatcher.handle_message(
+        &ctx,
+        &sink,
+        json!({ "type": "SetSize", "width": 400, "height": 500 }),
+    );
+    assert_eq!(ctx.size(), (400, 500));
+    assert_eq!(params.editor_state.size(), (400, 500));
+
+    dispatcher.handle_message(
+        &ctx,
+        &sink,
+        json!({ "type": "SetSize", "width": 1, "height": 100_000 }),
+    );
+    let clamped = (
+        crate::editor_state::MIN_SIZE.0,
+        crate::editor_state::MAX_SIZE.1,
+    );
+    assert_eq!(ctx.size(), clamped);
+    assert_eq!(params.editor_state.size(), clamped);
+    assert_eq!(
+        ctx.take_sent(),
+        [
+            json!({ "type": "set_size", "width": 400, "height": 500 }),
+            json!({ "type": "set_size", "width": clamped.0, "height": clamped.1 }),
+        ]
+    );
+}
+
+#[test]
+fn test_automation_echo() {
+    let (dispatcher, params) = test_dispatcher();
+    let ctx = TestContext::new(300, 200);
+    let sink = TestSink::default();
+
+    dispatcher.handle_message(
+        &ctx,
+        &sink,
+        json!({ "type": "Init", "version": PROTOCOL_VERSION }),
+    );
+    ctx.take_sent();
+    dispatcher.send_changes(&ctx);
+    assert!(ctx.take_sent().is_empty());
+
+    // The parameter's callback marks it as changed whenever the host automates it. Changes from
+    // the same frame are sent together, once.
+    params.dirty_params.mark("gain");
+    params.dirty_params.mark("gain");
+    dispatcher.send_changes(&ctx);
+    let sent = ctx.take_sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0]["type"], "params_changed");
+    assert_eq!(sent[0]["params"].as_array().unwrap().len(), 1);
+    assert_eq!(sent[0]["params"][0]["param"], "gain");
+    assert_eq!(
+        sent[0]["params"][0]["value"],
+        json!(params.gain.unmodulated_normalized_value())
+    );
+
+    dispatcher.send_changes(&ctx);
+    assert!(ctx.take_sent().is_empty());
+}
+
+#[test]
+fn test_ui_state() {
+    let (dispatcher, params) = test_dispatcher();
+    let ctx = TestContext::new(300, 200);
+    let sink = TestSink::default();
+
+    dispatcher.handle_message(
+        &ctx,
+        &sink,
+        json!({ "type": "SetUiState", "state": { "zoom": 1.5 } }),
+    );
+    assert!(ctx.take_sent().is_empty());
+    assert_eq!(params.editor_state.ui_state(), json!({ "zoom": 1.5 }));
+
+    dispatcher.handle_message(
+        &ctx,
+        &sink,
+        json!({ "type": "Init", "version": PROTOCOL_VERSION }),
+    );
+    assert_eq!(ctx.take_sent()[1]["state"], json!({ "zoom": 1.5 }));
+}
@@ -0,0 +1,120 @@
+//! The editor's size and the web UI's own state, like the selected tab or zoom level. This is
+//! stored in a `#[persist]` field so the editor reopens the way it was left, also after the plugin
+//! has been reloaded.
+
+use nih_plug::params::persist::PersistentField;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// The smallest size the editor can be resized to, in logical pixels.
+pub const MIN_SIZE: (u32, u32) = (200, 150);
+/// The largest size the editor can be resized to, in logical pixels.
+pub const MAX_SIZE: (u32, u32) = (2560, 1600);
+
+/// The editor's size and the web UI's state, shared between the plugin's parameters and the
+/// editor. Pass [`WebViewEditorState::size()`] to `WebViewEditor::new()` so the editor opens with
+/// the restored size. The host may restore the state after the editor has been created, so the
+/// [`Dispatcher`][crate::Dispatcher] also resizes the window to the stored size on `Init`.
+pub struct WebViewEditorState {
+    data: Mutex<WebViewEditorStateData>,
+}
+
+/// The serialized form of [`WebViewEditorState`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebViewEditorStateData {
+    size: (u32, u32),
+    /// Whatever the web UI wants to keep between sessions. The plugin doesn't look inside.
+    #[serde(default)]
+    ui_state: Value,
+}
+
+impl<'a> PersistentField<'a, WebViewEditorStateData> for Arc<WebViewEditorState> {
+    fn set(&self, new_value: WebViewEditorStateData) {
+        let (width, height) = new_value.size;
+        *self.data.lock() = WebViewEditorStateData {
+            // The state may come from a different version of the plugin, or it may have been
+            // edited by hand
+            size: clamp_size(width, height),
+            ui_state: new_value.ui_state,
+        };
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&WebViewEditorStateData) -> R,
+    {
+        f(&self.data.lock())
+    }
+}
+
+impl WebViewEditorState {
+    /// Initialize the state with the editor's default size in logical pixels, and no UI state.
+    pub fn from_size(width: u32, height: u32) -> Arc<Self> {
+        Arc::new(Self {
+            data: Mutex::new(WebViewEditorStateData {
+                size: clamp_size(width, height),
+                ui_state: Value::Null,
+            }),
+        })
+    }
+
+    /// The editor's current size in logical pixels.
+    pub fn size(&self) -> (u32, u32) {
+        self.data.lock().size
+    }
+
+    /// Store the editor's new size, clamped to [`MIN_SIZE`] and [`MAX_SIZE`]. Returns the clamped
+    /// size, which is what the editor should be resized to.
+    pub fn set_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let size = clamp_size(width, height);
+        self.data.lock().size = size;
+
+        size
+    }
+
+    /// The web UI's state, or `null` if the web UI never stored anything.
+    pub fn ui_state(&self) -> Value {
+        self.data.lock().ui_state.clone()
+    }
+
+    pub fn set_ui_state(&self, ui_state: Value) {
+        self.data.lock().ui_state = ui_state;
+    }
+}
+
+fn clamp_size(width: u32, height: u32) -> (u32, u32) {
+    (
+        width.clamp(MIN_SIZE.0, MAX_SIZE.0),
+        height.clamp(MIN_SIZE.1, MAX_SIZE.1),
+    )
+}
+
+#[test]
+fn test_set_size() {
+    let state = WebViewEditorState::from_size(600, 600);
+    assert_eq!(state.size(), (600, 600));
+
+    assert_eq!(state.set_size(800, 500), (800, 500));
+    assert_eq!(state.set_size(10, 100_000), (MIN_SIZE.0, MAX_SIZE.1));
+    assert_eq!(state.size(), (MIN_SIZE.0, MAX_SIZE.1));
+}
+
+#[test]
+fn test_persistence() {
+    let state = WebViewEditorState::from_size(600, 600);
+    state.set_size(800, 500);
+    state.set_ui_state(serde_json::json!({ "tab": "analyzer", "zoom": 1.25 }));
+
+    let json = state.map(serde_json::to_string).unwrap();
+    let restored = WebViewEditorState::from_size(600, 600);
+    restored.set(serde_json::from_str(&json).unwrap());
+    assert_eq!(restored.size(), (800, 500));
+    assert_eq!(restored.ui_state()["tab"], "analyzer");
+
+    // States without UI state, and with sizes that are out of bounds
+    restored.set(serde_json::from_str(r#"{ "size": [0, 9000] }"#).unwrap());
+    assert_eq!(restored.size(), (MIN_SIZE.0, MAX_SIZE.1));
+    assert_eq!(restored.ui_state(), Value::Null);
+}
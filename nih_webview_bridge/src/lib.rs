@@ -0,0 +1,16 @@
+//! The JSON protocol between NIH-plug plugins and their web UIs, and the parts of the editor that
+//! implement it on top of the plugin's parameters. See the readme for an overview.
+
+#[cfg(any(test, feature = "bindings"))]
+pub mod bindings;
+pub mod dirty_params;
+pub mod dispatcher;
+pub mod editor_state;
+pub mod param_bridge;
+pub mod protocol;
+
+pub use dirty_params::DirtyParams;
+pub use dispatcher::{Dispatcher, EditorContext};
+pub use editor_state::WebViewEditorState;
+pub use param_bridge::{ParamBridge, ParamSink};
+pub use protocol::{parse_action, Action, PluginMessage, ProtocolError, PROTOCOL_VERSION};
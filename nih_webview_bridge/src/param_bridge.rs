@@ -0,0 +1,254 @@
+//! A generic JSON bridge between the plugin's parameters and the web UI. The web UI receives the
+//! metadata and values for every parameter in `Params::param_map()` when it's initialized, and it
+//! can change any of them by their IDs.
+
+use nih_plug::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::dirty_params::DirtyParams;
+use crate::protocol::{ParamChange, ParamInfo, ProtocolError};
+
+/// Where parameter changes from the web UI end up. This is implemented for [`ParamSetter`], and the
+/// tests use a sink that records the changes instead of sending them to the host.
+pub trait ParamSink {
+    fn begin_set_parameter(&self, param: ParamPtr);
+    fn set_parameter_normalized(&self, param: ParamPtr, normalized: f32);
+    fn end_set_parameter(&self, param: ParamPtr);
+}
+
+// The pointers come from the parameters stored in a `ParamBridge`, so they're valid for as long as
+// the bridge exists
+impl ParamSink for ParamSetter<'_> {
+    fn begin_set_parameter(&self, param: ParamPtr) {
+        unsafe { self.raw_context.raw_begin_set_parameter(param) }
+    }
+
+    fn set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        unsafe {
+            self.raw_context
+                .raw_set_parameter_normalized(param, normalized)
+        }
+    }
+
+    fn end_set_parameter(&self, param: ParamPtr) {
+        unsafe { self.raw_context.raw_end_set_parameter(param) }
+    }
+}
+
+/// Looks up parameters by their IDs and collects the parameters that changed since the web UI last
+/// received them.
+pub struct ParamBridge {
+    /// Keeps the parameters alive for as long as the pointers in `param_map` are used.
+    _params: Arc<dyn Params>,
+    /// The parameters' IDs, pointers, and groups, in the order `Params::param_map()` returns them.
+    param_map: Vec<(String, ParamPtr, String)>,
+    /// The change flags from [`DirtyParams`] for the parameters in `param_map`, in the same order.
+    /// Parameters without a [`DirtyParams::callback()`] don't have one.
+    dirty_flags: Vec<Option<Arc<AtomicBool>>>,
+}
+
+impl ParamBridge {
+    /// Create a bridge for `params`. The parameters should mark themselves in `dirty_params` when
+    /// their values change, using [`DirtyParams::callback()`] with their IDs.
+    pub fn new(params: Arc<dyn Params>, dirty_params: Arc<DirtyParams>) -> Self {
+        let param_map = params.param_map();
+        let dirty_flags = param_map
+            .iter()
+            .map(|(id, _, _)| {
+                let flag = dirty_params.flag(id);
+                nih_debug_assert!(
+                    flag.is_some(),
+                    "Parameter '{id}' has no dirty parameter callback, its changes won't be sent to \
+                     the web UI"
+                );
+
+                flag
+            })
+            .collect();
+        for id in dirty_params.ids() {
+            nih_debug_assert!(
+                param_map.iter().any(|(param_id, _, _)| *param_id == id),
+                "There is a dirty parameter callback for the unknown parameter '{id}'"
+            );
+        }
+
+        Self {
+            _params: params,
+            param_map,
+            dirty_flags,
+        }
+    }
+
+    /// The metadata and current values for all parameters. Since this includes every parameter's
+    /// current value, pending changes are discarded.
+    pub fn param_list(&self) -> Vec<ParamInfo> {
+        for flag in self.dirty_flags.iter().flatten() {
+            flag.store(false, Ordering::Relaxed);
+        }
+
+        self.param_map
+            .iter()
+            .map(|(id, param_ptr, group)| {
+                // SAFETY: The pointers come from the parameters stored in the bridge
+                unsafe {
+                    let value = param_ptr.unmodulated_normalized_value();
+
+                    ParamInfo {
+                        id: id.clone(),
+                        name: param_ptr.name().to_owned(),
+                        group: group.clone(),
+                        unit: param_ptr.unit(),
+                        min: param_ptr.preview_plain(0.0),
+                        max: param_ptr.preview_plain(1.0),
+                        step_count: param_ptr.step_count(),
+                        default_value: param_ptr.default_normalized_value(),
+                        automatable: !param_ptr.flags().contains(ParamFlags::NON_AUTOMATABLE),
+                        value,
+                        text: param_ptr.normalized_value_to_string(value, true),
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// The parameters whose values or modulation have changed since they were last sent to the web
+    /// UI. Any number of changes to a parameter since the last call result in a single entry.
+    pub fn take_changes(&self) -> Vec<ParamChange> {
+        self.param_map
+            .iter()
+            .zip(&self.dirty_flags)
+            .filter(|(_, flag)| {
+                flag.as_ref()
+                    .is_some_and(|flag| flag.swap(false, Ordering::Relaxed))
+            })
+            .map(|((id, param_ptr, _), _)| {
+                // SAFETY: The pointers come from the parameters stored in the bridge
+                unsafe {
+                    let value = param_ptr.unmodulated_normalized_value();
+                    let modulated_value = param_ptr.modulated_normalized_value();
+
+                    ParamChange {
+                        param: id.clone(),
+                        value,
+                        text: param_ptr.normalized_value_to_string(value, true),
+                        modulated_value,
+                        modulated_text: param_ptr.normalized_value_to_string(modulated_value, true),
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Start an automation gesture for the parameter with the given ID. Returns an error if there
+    /// is no such parameter.
+    pub fn begin_set_parameter(
+        &self,
+        sink: &impl ParamSink,
+        id: &str,
+    ) -> Result<(), ProtocolError> {
+        self.with_param_ptr(id, |param_ptr| sink.begin_set_parameter(param_ptr))
+    }
+
+    /// Set the normalized value of the parameter with the given ID. This should be surrounded by
+    /// [`begin_set_parameter()`][Self::begin_set_parameter()] and
+    /// [`end_set_parameter()`][Self::end_set_parameter()] calls. Returns an error if there is no
+    /// such parameter.
+    pub fn set_parameter_normalized(
+        &self,
+        sink: &impl ParamSink,
+        id: &str,
+        value: f32,
+    ) -> Result<(), ProtocolError> {
+        self.with_param_ptr(id, |param_ptr| {
+            sink.set_parameter_normalized(param_ptr, value)
+        })
+    }
+
+    /// End the automation gesture for the parameter with the given ID. Returns an error if there
+    /// is no such parameter.
+    pub fn end_set_parameter(&self, sink: &impl ParamSink, id: &str) -> Result<(), ProtocolError> {
+        self.with_param_ptr(id, |param_ptr| sink.end_set_parameter(param_ptr))
+    }
+
+    /// Call `f` with the pointer for the parameter with the given ID. Returns an error if there is no
+    /// such parameter.
+    fn with_param_ptr(&self, id: &str, f: impl FnOnce(ParamPtr)) -> Result<(), ProtocolError> {
+        match self
+            .param_map
+            .iter()
+            .find(|(param_id, _, _)| param_id == id)
+        {
+            Some((_, param_ptr, _)) => {
+                f(*param_ptr);
+                Ok(())
+            }
+            None => Err(ProtocolError::UnknownParameter(id.to_owned())),
+        }
+    }
+}
+
+/// Parameters for the tests in this crate.
+#[cfg(test)]
+#[derive(Params)]
+pub(crate) struct TestParams {
+    #[persist = "editor-state"]
+    pub editor_state: Arc<crate::editor_state::WebViewEditorState>,
+
+    #[id = "gain"]
+    pub gain: FloatParam,
+    #[id = "mix"]
+    pub mix: FloatParam,
+
+    pub dirty_params: Arc<DirtyParams>,
+}
+
+#[cfg(test)]
+impl Default for TestParams {
+    fn default() -> Self {
+        let dirty_params = Arc::new(DirtyParams::default());
+
+        Self {
+            editor_state: crate::editor_state::WebViewEditorState::from_size(200, 200),
+
+            gain: FloatParam::new("Gain", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_callback(dirty_params.callback("gain")),
+            mix: FloatParam::new("Mix", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_callback(dirty_params.callback("mix")),
+
+            dirty_params,
+        }
+    }
+}
+
+#[cfg(test)]
+fn changed_ids(bridge: &ParamBridge) -> Vec<String> {
+    bridge
+        .take_changes()
+        .into_iter()
+        .map(|change| change.param)
+        .collect()
+}
+
+#[test]
+fn test_take_changes() {
+    let params = Arc::new(TestParams::default());
+    let bridge = ParamBridge::new(params.clone(), params.dirty_params.clone());
+    assert!(changed_ids(&bridge).is_empty());
+
+    // The callbacks share their flags with the callbacks the parameters were created with, so this
+    // is what happens when the host changes the parameter
+    for (id, _, _) in params.param_map() {
+        params.dirty_params.callback::<f32>(&id)(0.5);
+        assert_eq!(changed_ids(&bridge), [id]);
+        assert!(changed_ids(&bridge).is_empty());
+    }
+
+    // Changes are reported in `param_map()` order, and `param_list()` discards them
+    params.dirty_params.mark("mix");
+    params.dirty_params.mark("gain");
+    assert_eq!(changed_ids(&bridge), ["gain", "mix"]);
+    params.dirty_params.mark("mix");
+    assert_eq!(bridge.param_list().len(), 2);
+    assert!(changed_ids(&bridge).is_empty());
+}
@@ -0,0 +1,361 @@
+//! The messages exchanged with the web UI. Messages from the web UI are JSON objects with a `type`
+//! field naming the [`Action`], and the plugin's messages are [`PluginMessage`]s. The web UI sends
+//! its [`PROTOCOL_VERSION`] with the `Init` message, and messages that can't be handled are
+//! answered with an `error` message instead of being dropped silently.
+//!
+//! Plugins can send their own messages next to these, as long as their `type`s don't overlap. The
+//! web UIs' bindings for these messages are generated from these types, see the `bindings` module.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+#[cfg(any(test, feature = "bindings"))]
+use ts_rs::TS;
+
+/// The version of the message schema. This needs to be bumped whenever a message changes in a way
+/// that the other side can't handle, and the web UI needs to send the same version in its `Init`
+/// message.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A message sent by the web UI. `SetUiState` stores the web UI's own state, like the selected tab,
+/// with the plugin's state. It's sent back in a `ui_state` message after the next `Init`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(any(test, feature = "bindings"), derive(TS))]
+#[serde(tag = "type")]
+pub enum Action {
+    Init { version: u32 },
+    SetSize { width: u32, height: u32 },
+    BeginSetParameter { param: String },
+    SetParameter { param: String, value: f32 },
+    EndSetParameter { param: String },
+    SetUiState { state: Value },
+}
+
+/// A message sent to the web UI.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(any(test, feature = "bindings"), derive(TS))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginMessage {
+    /// The reply to `Init`, with the metadata and current values of all parameters.
+    ParamList {
+        version: u32,
+        params: Vec<ParamInfo>,
+    },
+    /// The parameters whose values changed since the previous update.
+    ParamsChanged { params: Vec<ParamChange> },
+    /// The web UI's state as stored with `SetUiState`, or `null`. Sent in reply to `Init`.
+    UiState { state: Value },
+    /// The editor's current size, sent in reply to `Init` and `SetSize`. On `Init` the editor is
+    /// first resized to the size stored with the plugin's state.
+    SetSize { width: u32, height: u32 },
+    /// A message from the web UI could not be handled.
+    Error {
+        version: u32,
+        code: String,
+        message: String,
+    },
+}
+
+impl PluginMessage {
+    pub fn to_json(&self) -> Value {
+        // None of the messages contain maps with non-string keys, which is the only way this can
+        // fail
+        serde_json::to_value(self).expect("Could not serialize a message for the web UI")
+    }
+}
+
+/// A parameter's metadata and current value, as sent to the web UI in the `param_list` message.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(any(test, feature = "bindings"), derive(TS))]
+pub struct ParamInfo {
+    pub id: String,
+    pub name: String,
+    /// The parameter's group, or an empty string for top-level parameters.
+    pub group: String,
+    pub unit: &'static str,
+    /// The plain value at normalized value 0.0.
+    pub min: f32,
+    /// The plain value at normalized value 1.0.
+    pub max: f32,
+    /// The number of steps for discrete parameters, or `None` for continuous parameters.
+    pub step_count: Option<usize>,
+    pub default_value: f32,
+    pub automatable: bool,
+    /// The current unmodulated normalized value.
+    pub value: f32,
+    /// The current value formatted with the parameter's unit.
+    pub text: String,
+}
+
+/// A parameter's new value, as sent to the web UI in a `params_changed` message.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(any(test, feature = "bindings"), derive(TS))]
+pub struct ParamChange {
+    pub param: String,
+    /// The new unmodulated normalized value.
+    pub value: f32,
+    /// The new value formatted with the parameter's unit.
+    pub text: String,
+    /// The normalized value with monophonic modulation applied.
+    pub modulated_value: f32,
+    /// The modulated value formatted with the parameter's unit.
+    pub modulated_text: String,
+}
+
+/// Why a message from the web UI could not be handled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolError {
+    /// The message is not an object, or it has an unknown type or missing or mistyped fields.
+    MalformedMessage(String),
+    /// The web UI uses a different version of the message schema.
+    UnsupportedVersion(u32),
+    /// There is no parameter with this ID.
+    UnknownParameter(String),
+    /// A normalized parameter value outside of `[0, 1]`.
+    InvalidValue { param: String, value: f32 },
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::MalformedMessage(reason) => write!(f, "malformed message: {reason}"),
+            ProtocolError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported protocol version {version}, expected version {PROTOCOL_VERSION}"
+            ),
+            ProtocolError::UnknownParameter(param) => write!(f, "unknown parameter '{param}'"),
+            ProtocolError::InvalidValue { param, value } => write!(
+                f,
+                "invalid normalized value {value} for parameter '{param}'"
+            ),
+        }
+    }
+}
+
+impl ProtocolError {
+    /// A short, stable identifier for the web UI to match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProtocolError::MalformedMessage(_) => "malformed_message",
+            ProtocolError::UnsupportedVersion(_) => "unsupported_version",
+            ProtocolError::UnknownParameter(_) => "unknown_parameter",
+            ProtocolError::InvalidValue { .. } => "invalid_value",
+        }
+    }
+
+    /// The `error` message sent back to the web UI.
+    pub fn to_message(&self) -> PluginMessage {
+        PluginMessage::Error {
+            version: PROTOCOL_VERSION,
+            code: self.code().to_owned(),
+            message: self.to_string(),
+        }
+    }
+}
+
+/// Parse and validate a message from the web UI. This never panics, no matter what the web UI
+/// sends.
+pub fn parse_action(value: Value) -> Result<Action, ProtocolError> {
+    let action: Action = serde_json::from_value(value)
+        .map_err(|err| ProtocolError::MalformedMessage(err.to_string()))?;
+
+    match action {
+        Action::Init { version } if version != PROTOCOL_VERSION => {
+            Err(ProtocolError::UnsupportedVersion(version))
+        }
+        Action::SetParameter { param, value } if !(0.0..=1.0).contains(&value) => {
+            Err(ProtocolError::InvalidValue { param, value })
+        }
+        action => Ok(action),
+    }
+}
+
+#[cfg(test)]
+use serde_json::json;
+
+/// A tiny xorshift generator so the fuzz tests are reproducible without extra dependencies.
+#[cfg(test)]
+struct Rng(u64);
+
+#[cfg(test)]
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// A random JSON value that's likely, but not guaranteed, to look a bit like a real message.
+#[cfg(test)]
+fn random_value(rng: &mut Rng, depth: usize) -> Value {
+    const KEYS: [&str; 9] = [
+        "type", "version", "param", "value", "width", "height", "state", "", "\u{0}",
+    ];
+    const STRINGS: [&str; 10] = [
+        "Init",
+        "SetSize",
+        "BeginSetParameter",
+        "SetParameter",
+        "EndSetParameter",
+        "SetUiState",
+        "gain",
+        "",
+        "init",
+        "🎛️",
+    ];
+
+    match rng.below(if depth == 0 { 5 } else { 7 }) {
+        0 => Value::Null,
+        1 => Value::Bool(rng.below(2) == 0),
+        2 => json!(rng.next() as i64),
+        // Non-finite floats turn into nulls
+        3 => json!(f64::from_bits(rng.next())),
+        4 => json!(STRINGS[rng.below(STRINGS.len())]),
+        5 => Value::Array(
+            (0..rng.below(4))
+                .map(|_| random_value(rng, depth - 1))
+                .collect(),
+        ),
+        _ => Value::Object(
+            (0..rng.below(5))
+                .map(|_| {
+                    (
+                        KEYS[rng.below(KEYS.len())].to_owned(),
+                        random_value(rng, depth - 1),
+                    )
+                })
+                .collect(),
+        ),
+    }
+}
+
+#[test]
+fn test_valid_actions() {
+    assert_eq!(
+        parse_action(json!({ "type": "Init", "version": PROTOCOL_VERSION })),
+        Ok(Action::Init {
+            version: PROTOCOL_VERSION
+        })
+    );
+    assert_eq!(
+        parse_action(json!({ "type": "SetSize", "width": 300, "height": 200 })),
+        Ok(Action::SetSize {
+            width: 300,
+            height: 200
+        })
+    );
+    assert_eq!(
+        parse_action(json!({ "type": "SetParameter", "param": "gain", "value": 0.5 })),
+        Ok(Action::SetParameter {
+            param: String::from("gain"),
+            value: 0.5
+        })
+    );
+    assert_eq!(
+        parse_action(json!({ "type": "SetUiState", "state": { "tab": "analyzer" } })),
+        Ok(Action::SetUiState {
+            state: json!({ "tab": "analyzer" })
+        })
+    );
+}
+
+#[test]
+fn test_invalid_actions() {
+    let errors = [
+        (json!(null), "malformed_message"),
+        (json!("Init"), "malformed_message"),
+        (json!([{ "type": "Init" }]), "malformed_message"),
+        (json!({}), "malformed_message"),
+        (
+            json!({ "type": "SetGain", "value": 0.5 }),
+            "malformed_message",
+        ),
+        (json!({ "type": "Init" }), "malformed_message"),
+        (json!({ "type": "SetUiState" }), "malformed_message"),
+        (
+            json!({ "type": "Init", "version": -1 }),
+            "malformed_message",
+        ),
+        (
+            json!({ "type": "Init", "version": 0 }),
+            "unsupported_version",
+        ),
+        (
+            json!({ "type": "SetSize", "width": "300" }),
+            "malformed_message",
+        ),
+        (
+            json!({ "type": "SetSize", "width": 1e12, "height": 200 }),
+            "malformed_message",
+        ),
+        (
+            json!({ "type": "SetParameter", "param": 1 }),
+            "malformed_message",
+        ),
+        (
+            json!({ "type": "SetParameter", "param": "gain", "value": 1.5 }),
+            "invalid_value",
+        ),
+        (
+            json!({ "type": "SetParameter", "param": "gain", "value": 1e300 }),
+            "invalid_value",
+        ),
+    ];
+
+    for (message, code) in errors {
+        let error = parse_action(message.clone()).expect_err(&message.to_string());
+        assert_eq!(error.code(), code, "{message}");
+
+        let reply = error.to_message().to_json();
+        assert_eq!(reply["type"], "error");
+        assert_eq!(reply["code"], code);
+    }
+}
+
+#[test]
+fn test_fuzzed_actions() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+    // Random JSON trees
+    for _ in 0..10_000 {
+        if let Err(error) = parse_action(random_value(&mut rng, 4)) {
+            let _ = error.to_message().to_json();
+        }
+    }
+
+    // Valid messages with random bytes changed, inserted, or removed. Whatever still parses as
+    // JSON is sent to the parser.
+    let messages = [
+        r#"{"type":"Init","version":1}"#,
+        r#"{"type":"SetSize","width":300,"height":200}"#,
+        r#"{"type":"BeginSetParameter","param":"gain"}"#,
+        r#"{"type":"SetParameter","param":"gain","value":0.5}"#,
+        r#"{"type":"EndSetParameter","param":"gain"}"#,
+    ];
+    for _ in 0..10_000 {
+        let mut bytes = messages[rng.below(messages.len())].as_bytes().to_vec();
+        for _ in 0..=rng.below(3) {
+            let index = rng.below(bytes.len());
+            match rng.below(3) {
+                0 => bytes[index] = rng.next() as u8,
+                1 => bytes.insert(index, rng.next() as u8),
+                _ if bytes.len() > 1 => {
+                    bytes.remove(index);
+                }
+                _ => (),
+            }
+        }
+
+        if let Ok(value) = serde_json::from_slice(&bytes) {
+            if let Err(error) = parse_action(value) {
+                let _ = error.to_message().to_json();
+            }
+        }
+    }
+}
@@ -0,0 +1,724 @@
+//! A rotary knob for any NIH-plug parameter.
+
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+use nih_plug::prelude::{Param, ParamSetter};
+use nih_plug_egui::egui::{
+    lerp, vec2, Id, Key, Painter, Pos2, Response, Sense, Shape, Stroke, TextEdit, TextStyle, Ui,
+    Vec2, Widget, WidgetInfo, WidgetText,
+};
+use parking_lot::Mutex;
+
+use crate::param_menu::{ParamContextMenu, ParamMenuContext};
+
+/// When shift+dragging a parameter, one pixel dragged corresponds to this much change in the
+/// noramlized parameter.
+const GRANULAR_DRAG_MULTIPLIER: f32 = 0.0015;
+/// When dragging a parameter normally, one pixel dragged corresponds to this much change in the
+/// normalized parameter. Dragging 200 pixels covers the entire range.
+const DEFAULT_DRAG_SENSITIVITY: f32 = 0.005;
+/// One step of the mouse wheel or one press of an arrow key changes a continuous parameter's
+/// normalized value by this much. Stepped parameters always move by a single step.
+const WHEEL_STEP: f32 = 0.02;
+/// PageUp and PageDown move the value by this many steps at once.
+const PAGE_STEPS: f32 = 10.0;
+/// The part of a full rotation the dial's pointer covers between the minimum and maximum values.
+pub const DIAL_SWEEP: f32 = 0.75;
+/// The angle in radians the dial's pointer points at for the minimum value. This is at the bottom
+/// left, with the maximum value at the bottom right.
+pub const DIAL_START_ANGLE: f32 = -0.625 * TAU;
+/// Stepped parameters with more steps than this don't get tick marks, since they would just blend
+/// together into a solid ring.
+pub const MAX_TICKS: usize = 32;
+/// Arcs are approximated with one line segment per this many radians.
+const ARC_SEGMENT_ANGLE: f32 = TAU / 128.0;
+
+lazy_static! {
+    static ref DRAG_NORMALIZED_VALUE_MEMORY_ID: Id = Id::new((file!(), 0));
+    static ref VALUE_ENTRY_MEMORY_ID: Id = Id::new((file!(), 2));
+}
+
+/// How dragging the dial changes its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DragMode {
+    /// Dragging up increases the value and dragging down decreases it, at a rate set with
+    /// [`Dial::with_sensitivity()`].
+    Vertical,
+    /// The value follows the pointer's rotation around the dial's center, relative to where the
+    /// drag started.
+    Circular,
+}
+
+/// Everything a [`DialSkin`] needs to know about the parameter to draw a dial.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DialState {
+    /// The parameter's current normalized value, including monophonic modulation.
+    pub normalized_value: f32,
+    /// The parameter's normalized value without any modulation applied.
+    pub unmodulated_normalized_value: f32,
+    /// The normalized value the parameter resets to.
+    pub default_normalized_value: f32,
+    /// The number of steps for discrete parameters, or `None` for continuous parameters.
+    pub step_count: Option<usize>,
+}
+
+impl DialState {
+    /// The angle in radians the dial's pointer should point at for a normalized value. This is
+    /// measured clockwise from the positive X axis, as is usual for egui's screen coordinates.
+    pub fn angle_for(normalized_value: f32) -> f32 {
+        DIAL_START_ANGLE + (normalized_value * DIAL_SWEEP * TAU)
+    }
+
+    /// The angle in radians for the current, modulated value.
+    pub fn value_angle(&self) -> f32 {
+        Self::angle_for(self.normalized_value)
+    }
+
+    /// Draw the dial's rings centered on a circle with the given radius, using the colors from the
+    /// active `egui::Visuals`:
+    ///
+    /// - A value arc `width` wide from the parameter's default value to its unmodulated value. For
+    ///   bipolar parameters like gain the default sits in the center, so the arc grows outwards
+    ///   from there in both directions.
+    /// - A thinner modulation arc on top of that from the unmodulated value to the value after
+    ///   the host's modulation has been applied.
+    /// - Tick marks just outside of the ring at every step of stepped parameters.
+    ///
+    /// The skin is responsible for drawing the track underneath the arcs, if it wants one.
+    pub fn paint_rings(&self, ui: &Ui, center: Pos2, radius: f32, width: f32) {
+        let visuals = ui.visuals();
+        let painter = ui.painter();
+
+        if let Some(step_count) = self
+            .step_count
+            .filter(|&count| count > 0 && count <= MAX_TICKS)
+        {
+            let tick_stroke = Stroke::new(
+                (width * 0.25).max(1.0),
+                visuals.widgets.noninteractive.fg_stroke.color,
+            );
+            for step in 0..=step_count {
+                let direction = Vec2::angled(Self::angle_for(step as f32 / step_count as f32));
+                painter.line_segment(
+                    [
+                        center + direction * (radius + width * 0.5),
+                        center + direction * (radius + width),
+                    ],
+                    tick_stroke,
+                );
+            }
+        }
+
+        paint_arc(
+            painter,
+            center,
+            radius,
+            Self::angle_for(self.default_normalized_value),
+            Self::angle_for(self.unmodulated_normalized_value),
+            Stroke::new(width, visuals.selection.bg_fill),
+        );
+        paint_arc(
+            painter,
+            center,
+            radius,
+            Self::angle_for(self.unmodulated_normalized_value),
+            self.value_angle(),
+            Stroke::new(width * 0.5, visuals.selection.stroke.color),
+        );
+    }
+}
+
+/// Draw an arc between two angles, in either direction. Angles use the same convention as
+/// [`DialState::angle_for()`]. Nothing is drawn if the angles are the same.
+pub fn paint_arc(
+    painter: &Painter,
+    center: Pos2,
+    radius: f32,
+    start_angle: f32,
+    end_angle: f32,
+    stroke: Stroke,
+) {
+    let sweep = end_angle - start_angle;
+    if sweep.abs() < f32::EPSILON {
+        return;
+    }
+
+    let num_segments = (sweep.abs() / ARC_SEGMENT_ANGLE).ceil() as usize;
+    let points = (0..=num_segments)
+        .map(|segment| {
+            let angle = lerp(
+                start_angle..=end_angle,
+                segment as f32 / num_segments as f32,
+            );
+            center + Vec2::angled(angle) * radius
+        })
+        .collect();
+
+    painter.add(Shape::line(points, stroke));
+}
+
+/// Draws a [`Dial`]. The dial itself only handles input and talking to the host, so plugins can
+/// implement this to give the dial their own look.
+pub trait DialSkin {
+    /// The dial's diameter when the widget doesn't override it with [`Dial::with_diameter()`].
+    fn default_diameter(&self, ui: &Ui) -> f32 {
+        ui.spacing().interact_size.y * 2.0
+    }
+
+    /// Draw the dial in `response.rect`. `response` can be used to check whether the dial is
+    /// being hovered or dragged.
+    fn paint(&self, ui: &Ui, response: &Response, state: &DialState);
+}
+
+/// A simple dial that follows the active `egui::Visuals`: a circle with a line pointing at the
+/// current value, surrounded by the rings drawn by [`DialState::paint_rings()`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultDialSkin;
+
+impl DialSkin for DefaultDialSkin {
+    fn paint(&self, ui: &Ui, response: &Response, state: &DialState) {
+        let visuals = ui.style().interact(response);
+        // All coordinates are in absolute screen coordinates so we use `rect` to place the elements.
+        let rect = response.rect.expand(visuals.expansion);
+        let radius = 0.5 * rect.height();
+        let center = rect.center();
+
+        // The rings are drawn on top of a track covering the dial's entire range
+        let ring_radius = 0.8 * radius;
+        let ring_width = 0.15 * radius;
+        paint_arc(
+            ui.painter(),
+            center,
+            ring_radius,
+            DialState::angle_for(0.0),
+            DialState::angle_for(1.0),
+            Stroke::new(ring_width, ui.visuals().extreme_bg_color),
+        );
+        state.paint_rings(ui, center, ring_radius, ring_width);
+
+        let inner_circle_radius = 0.6 * radius;
+        ui.painter().circle(
+            center,
+            inner_circle_radius,
+            visuals.bg_fill,
+            visuals.fg_stroke,
+        );
+
+        let pointer = center + Vec2::angled(state.value_angle()) * inner_circle_radius;
+        ui.painter()
+            .line_segment([center, pointer], visuals.fg_stroke);
+    }
+}
+
+/// A rotary knob widget that knows about NIH-plug parameters ranges and can get values for it. The
+/// dial supports double click and control click to reset, relative vertical or circular dragging,
+/// shift+drag for granular dragging, mouse wheel stepping, and text value entry by clicking on the
+/// value text or Alt+clicking the dial. Right clicking the dial opens a [`ParamContextMenu`].
+/// Drawing the dial is left to a [`DialSkin`].
+///
+/// The dial can also be used with just the keyboard. It can be focused with Tab like any other egui
+/// widget, after which the arrow keys step the value, PageUp and PageDown take larger steps, Home
+/// resets the parameter, and Enter starts text entry. Escape cancels text entry. The parameter's
+/// name and value are reported through `egui::WidgetInfo` for screen readers.
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct Dial<'a, P: Param> {
+    param: &'a P,
+    setter: &'a ParamSetter<'a>,
+    skin: &'a dyn DialSkin,
+
+    draw_value: bool,
+    diameter: Option<f32>,
+    drag_mode: DragMode,
+    drag_sensitivity: f32,
+    menu_context: Option<ParamMenuContext<'a>>,
+
+    /// Will be set in the `ui()` function so we can request keyboard input focus on Alt+click.
+    keyboard_focus_id: Option<Id>,
+    /// Will be set in the `ui()` function. Used to store whether this dial currently has an
+    /// automation gesture in progress.
+    gesture_id: Option<Id>,
+}
+
+impl<'a, P: Param> Dial<'a, P> {
+    /// Create a new dial for a parameter. Use the other methods to modify the dial before
+    /// passing it to [`Ui::add()`].
+    pub fn for_param(param: &'a P, setter: &'a ParamSetter<'a>) -> Self {
+        Self {
+            param,
+            setter,
+            skin: &DefaultDialSkin,
+
+            draw_value: true,
+            diameter: None,
+            drag_mode: DragMode::Vertical,
+            drag_sensitivity: DEFAULT_DRAG_SENSITIVITY,
+            menu_context: None,
+
+            keyboard_focus_id: None,
+            gesture_id: None,
+        }
+    }
+
+    /// Don't draw the dial's current value next to the dial.
+    pub fn without_value(mut self) -> Self {
+        self.draw_value = false;
+        self
+    }
+
+    /// Draw the dial with a custom skin instead of [`DefaultDialSkin`].
+    pub fn with_skin(mut self, skin: &'a dyn DialSkin) -> Self {
+        self.skin = skin;
+        self
+    }
+
+    /// Set a custom diameter for the dial.
+    pub fn with_diameter(mut self, diameter: f32) -> Self {
+        self.diameter = Some(diameter);
+        self
+    }
+
+    /// Change how dragging the dial changes its value. Defaults to [`DragMode::Vertical`].
+    pub fn with_drag_mode(mut self, drag_mode: DragMode) -> Self {
+        self.drag_mode = drag_mode;
+        self
+    }
+
+    /// Set how much the normalized value changes per pixel dragged in [`DragMode::Vertical`].
+    /// Defaults to 0.005, so dragging 200 pixels covers the entire range.
+    pub fn with_sensitivity(mut self, sensitivity: f32) -> Self {
+        self.drag_sensitivity = sensitivity;
+        self
+    }
+
+    /// Add MIDI learn and locking to the dial's context menu.
+    pub fn with_menu_context(mut self, context: ParamMenuContext<'a>) -> Self {
+        self.menu_context = Some(context);
+        self
+    }
+
+    fn plain_value(&self) -> P::Plain {
+        self.param.modulated_plain_value()
+    }
+
+    fn normalized_value(&self) -> f32 {
+        self.param.modulated_normalized_value()
+    }
+
+    fn string_value(&self) -> String {
+        self.param.to_string()
+    }
+
+    /// Enable the keyboard entry part of the widget.
+    fn begin_keyboard_entry(&self, ui: &Ui) {
+        ui.memory().request_focus(self.keyboard_focus_id.unwrap());
+
+        // Always initialize the field to the current value, that seems nicer than having to
+        // being typing from scratch
+        let value_entry_mutex = ui
+            .memory()
+            .data
+            .get_temp_mut_or_default::<Arc<Mutex<String>>>(*VALUE_ENTRY_MEMORY_ID)
+            .clone();
+        *value_entry_mutex.lock() = self.string_value();
+    }
+
+    fn keyboard_entry_active(&self, ui: &Ui) -> bool {
+        ui.memory().has_focus(self.keyboard_focus_id.unwrap())
+    }
+
+    /// Start the keyboard entry at the start of the next frame. When the text field gets focus in
+    /// the same frame the dial is clicked in, the text field sees that click as a click outside of
+    /// itself and immediately gives up focus again. Pressing Enter would likewise immediately
+    /// commit the value.
+    fn begin_keyboard_entry_next_frame(&self, ui: &Ui) {
+        ui.memory()
+            .data
+            .insert_temp(self.pending_keyboard_entry_id(), true);
+        ui.ctx().request_repaint();
+    }
+
+    /// Returns `true` once if [`Self::begin_keyboard_entry_next_frame()`] was called during an
+    /// earlier frame.
+    fn take_pending_keyboard_entry(&self, ui: &Ui) -> bool {
+        let id = self.pending_keyboard_entry_id();
+        let pending = ui.memory().data.get_temp(id).unwrap_or(false);
+        if pending {
+            ui.memory().data.remove::<bool>(id);
+        }
+
+        pending
+    }
+
+    fn pending_keyboard_entry_id(&self) -> Id {
+        self.keyboard_focus_id.unwrap().with("pending")
+    }
+
+    fn gesture_active(&self, ui: &Ui) -> bool {
+        ui.memory()
+            .data
+            .get_temp(self.gesture_id.unwrap())
+            .unwrap_or(false)
+    }
+
+    /// Start an automation gesture. This does nothing if a gesture is already in progress, so
+    /// every [`begin_set_parameter()`](ParamSetter::begin_set_parameter()) is paired with exactly
+    /// one [`end_set_parameter()`](ParamSetter::end_set_parameter()).
+    fn begin_drag(&self, ui: &Ui) {
+        if !self.gesture_active(ui) {
+            self.setter.begin_set_parameter(self.param);
+            ui.memory().data.insert_temp(self.gesture_id.unwrap(), true);
+        }
+    }
+
+    /// End the automation gesture started in [`Self::begin_drag()`], if there is one.
+    fn end_drag(&self, ui: &Ui) {
+        if self.gesture_active(ui) {
+            self.setter.end_set_parameter(self.param);
+            ui.memory()
+                .data
+                .insert_temp(self.gesture_id.unwrap(), false);
+        }
+    }
+
+    /// Perform a single change to the parameter as its own gesture, or as part of the current
+    /// gesture if the dial is being dragged.
+    fn one_shot_change(&self, ui: &Ui, change: impl FnOnce()) {
+        if self.gesture_active(ui) {
+            change();
+        } else {
+            self.begin_drag(ui);
+            change();
+            self.end_drag(ui);
+        }
+    }
+
+    fn set_normalized_value(&self, normalized: f32) {
+        // This snaps to the nearest plain value if the parameter is stepped in some way.
+        // TODO: As an optimization, we could add a `const CONTINUOUS: bool` to the parameter to
+        //       avoid this normalized->plain->normalized conversion for parameters that don't need
+        //       it
+        let value = self.param.preview_plain(normalized);
+        if value != self.plain_value() {
+            self.setter.set_parameter(self.param, value);
+        }
+    }
+
+    /// Begin and end drag still need to be called when using this. Returns `false` if the string
+    /// could no tbe parsed.
+    fn set_from_string(&self, string: &str) -> bool {
+        match self.param.string_to_normalized_value(string) {
+            Some(normalized_value) => {
+                self.set_normalized_value(normalized_value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Begin and end drag still need to be called when using this..
+    fn reset_param(&self) {
+        self.setter
+            .set_parameter(self.param, self.param.default_plain_value());
+    }
+
+    /// Change the value by a relative amount while dragging. The unsnapped normalized value is
+    /// kept in memory so small movements still add up for stepped parameters, and so dragging
+    /// past either end of the range and back immediately changes the value again.
+    fn drag(&self, ui: &Ui, response: &Response) {
+        let granular = ui.input().modifiers.shift;
+        let delta = match self.drag_mode {
+            DragMode::Vertical => {
+                let sensitivity = if granular {
+                    GRANULAR_DRAG_MULTIPLIER
+                } else {
+                    self.drag_sensitivity
+                };
+
+                // Screen coordinates grow downwards, but dragging up should increase the value
+                -response.drag_delta().y * sensitivity
+            }
+            DragMode::Circular => match response.interact_pointer_pos() {
+                Some(pointer_pos) => {
+                    let center = response.rect.center();
+                    let current_angle = (pointer_pos - center).angle();
+                    let previous_angle = (pointer_pos - response.drag_delta() - center).angle();
+                    // Wrap the difference to `[-pi, pi]` so crossing the bottom of the dial
+                    // doesn't cause a jump
+                    let delta_angle =
+                        (current_angle - previous_angle + TAU * 1.5) % TAU - TAU / 2.0;
+
+                    let delta = delta_angle / (DIAL_SWEEP * TAU);
+                    if granular {
+                        delta * (GRANULAR_DRAG_MULTIPLIER / DEFAULT_DRAG_SENSITIVITY)
+                    } else {
+                        delta
+                    }
+                }
+                None => 0.0,
+            },
+        };
+
+        let drag_value = (Self::get_drag_normalized_value_memory(ui) + delta).clamp(0.0, 1.0);
+        Self::set_drag_normalized_value_memory(ui, drag_value);
+        self.set_normalized_value(drag_value);
+    }
+
+    /// Move the value by `steps` steps. Stepped parameters move by whole steps, and continuous
+    /// parameters move by `WHEEL_STEP`, or by a tenth of that when `granular` is set.
+    fn step(&self, steps: f32, granular: bool) {
+        let normalized_value = self.param.unmodulated_normalized_value();
+        let new_value = match self.param.step_count() {
+            Some(step_count) => {
+                let step_count = step_count as f32;
+                ((normalized_value * step_count).round() + steps) / step_count
+            }
+            None if granular => normalized_value + (steps * WHEEL_STEP * 0.1),
+            None => normalized_value + (steps * WHEEL_STEP),
+        };
+
+        self.set_normalized_value(new_value.clamp(0.0, 1.0));
+    }
+
+    fn get_drag_normalized_value_memory(ui: &Ui) -> f32 {
+        ui.memory()
+            .data
+            .get_temp(*DRAG_NORMALIZED_VALUE_MEMORY_ID)
+            .unwrap_or(0.5)
+    }
+
+    fn set_drag_normalized_value_memory(ui: &Ui, value: f32) {
+        ui.memory()
+            .data
+            .insert_temp(*DRAG_NORMALIZED_VALUE_MEMORY_ID, value);
+    }
+
+    fn handle_input(&self, ui: &Ui, response: &mut Response) {
+        if self.take_pending_keyboard_entry(ui) {
+            self.begin_keyboard_entry(ui);
+        }
+
+        if response.drag_started() {
+            // Dragging is relative to the value at the start of the drag, so clicking on the dial
+            // doesn't make the value jump
+            self.begin_drag(ui);
+            Self::set_drag_normalized_value_memory(ui, self.param.unmodulated_normalized_value());
+        }
+        if response.dragged() && !ui.input().modifiers.command {
+            self.drag(ui, response);
+            response.mark_changed();
+        }
+
+        if response.double_clicked() || (response.clicked() && ui.input().modifiers.command) {
+            // Like double clicking, Ctrl+Click should reset the parameter
+            self.one_shot_change(ui, || self.reset_param());
+            response.mark_changed();
+        } else if response.clicked() && ui.input().modifiers.alt && self.draw_value {
+            // Allow typing in the value on an Alt+Click. This is shown in place of the value next
+            // to the dial.
+            self.begin_keyboard_entry_next_frame(ui);
+        }
+
+        // Every scroll event moves the value by one step
+        if response.hovered() {
+            let scroll_delta = ui.input().scroll_delta;
+            let scroll_amount = if scroll_delta.y != 0.0 {
+                scroll_delta.y
+            } else {
+                scroll_delta.x
+            };
+            if scroll_amount != 0.0 {
+                let granular = ui.input().modifiers.shift;
+                self.one_shot_change(ui, || self.step(scroll_amount.signum(), granular));
+                response.mark_changed();
+            }
+        }
+
+        if response.drag_released() {
+            self.end_drag(ui);
+        }
+
+        if response.has_focus() {
+            self.handle_keyboard_input(ui, response);
+        }
+    }
+
+    /// Arrow keys step the value, PageUp and PageDown take [`PAGE_STEPS`] steps at once, Home
+    /// resets the parameter, and Enter starts typing in a value. Holding shift makes the steps for
+    /// continuous parameters smaller, just like with the mouse wheel.
+    fn handle_keyboard_input(&self, ui: &Ui, response: &mut Response) {
+        // The input needs to be read up front since it can't be locked while changing the memory
+        let (steps, granular, reset, begin_entry) = {
+            let input = ui.input();
+            let key_steps = [
+                (Key::ArrowUp, 1.0),
+                (Key::ArrowRight, 1.0),
+                (Key::ArrowDown, -1.0),
+                (Key::ArrowLeft, -1.0),
+                (Key::PageUp, PAGE_STEPS),
+                (Key::PageDown, -PAGE_STEPS),
+            ];
+            let steps: f32 = key_steps
+                .into_iter()
+                .filter(|(key, _)| input.key_pressed(*key))
+                .map(|(_, steps)| steps)
+                .sum();
+
+            (
+                steps,
+                input.modifiers.shift,
+                input.key_pressed(Key::Home),
+                input.key_pressed(Key::Enter),
+            )
+        };
+
+        if reset {
+            self.one_shot_change(ui, || self.reset_param());
+            response.mark_changed();
+        } else if steps != 0.0 {
+            self.one_shot_change(ui, || self.step(steps, granular));
+            response.mark_changed();
+        }
+
+        if begin_entry && self.draw_value {
+            self.begin_keyboard_entry_next_frame(ui);
+        }
+    }
+
+    fn dial_ui(&self, ui: &mut Ui, response: &mut Response) {
+        self.handle_input(ui, response);
+
+        if ui.is_rect_visible(response.rect) {
+            let state = DialState {
+                normalized_value: self.normalized_value(),
+                unmodulated_normalized_value: self.param.unmodulated_normalized_value(),
+                default_normalized_value: self.param.default_normalized_value(),
+                step_count: self.param.step_count(),
+            };
+
+            self.skin.paint(ui, response, &state);
+
+            // Skins don't need to know about keyboard focus, so the focus ring is drawn here
+            if response.has_focus() {
+                let rect = response.rect;
+                ui.painter().circle_stroke(
+                    rect.center(),
+                    rect.width().min(rect.height()) * 0.5,
+                    ui.visuals().selection.stroke,
+                );
+            }
+        }
+
+        response.widget_info(|| {
+            let mut info = WidgetInfo::slider(self.normalized_value() as f64, self.param.name());
+            info.current_text_value = Some(self.string_value());
+            info
+        });
+    }
+
+    /// `dial_id` is the ID of the dial itself. Keyboard focus is returned to the dial after
+    /// entering a value so it can still be controlled with the keyboard.
+    fn value_ui(&self, ui: &mut Ui, dial_id: Id) {
+        let visuals = ui.visuals().widgets.inactive;
+        let should_draw_frame = ui.visuals().button_frame;
+        let padding = ui.spacing().button_padding;
+
+        // Either show the parameter's label, or show a text entry field if the parameter's label
+        // has been clicked on
+        let keyboard_focus_id = self.keyboard_focus_id.unwrap();
+        if self.keyboard_entry_active(ui) {
+            let value_entry_mutex = ui
+                .memory()
+                .data
+                .get_temp_mut_or_default::<Arc<Mutex<String>>>(*VALUE_ENTRY_MEMORY_ID)
+                .clone();
+            let mut value_entry = value_entry_mutex.lock();
+
+            ui.add(
+                TextEdit::singleline(&mut *value_entry)
+                    .id(keyboard_focus_id)
+                    .font(TextStyle::Monospace),
+            );
+            if ui.input().key_pressed(Key::Escape) {
+                // Cancel when pressing escape
+                ui.memory().request_focus(dial_id);
+            } else if ui.input().key_pressed(Key::Enter) {
+                // And try to set the value by string when pressing enter
+                self.one_shot_change(ui, || {
+                    self.set_from_string(&value_entry);
+                });
+
+                ui.memory().request_focus(dial_id);
+            }
+        } else {
+            let text = WidgetText::from(self.string_value()).into_galley(
+                ui,
+                None,
+                ui.available_width() - (padding.x * 2.0),
+                TextStyle::Button,
+            );
+
+            let response = ui.allocate_response(text.size() + (padding * 2.0), Sense::click());
+            if response.clicked() {
+                self.begin_keyboard_entry(ui);
+            }
+
+            if ui.is_rect_visible(response.rect) {
+                if should_draw_frame {
+                    let fill = visuals.bg_fill;
+                    let stroke = visuals.bg_stroke;
+                    ui.painter().rect(
+                        response.rect.expand(visuals.expansion),
+                        visuals.rounding,
+                        fill,
+                        stroke,
+                    );
+                }
+
+                let text_pos = ui
+                    .layout()
+                    .align_size_within_rect(text.size(), response.rect.shrink2(padding))
+                    .min;
+                text.paint_with_visuals(ui.painter(), text_pos, &visuals);
+            }
+        }
+    }
+}
+
+impl<P: Param> Widget for Dial<'_, P> {
+    fn ui(mut self, ui: &mut Ui) -> Response {
+        let diameter = self
+            .diameter
+            .unwrap_or_else(|| self.skin.default_diameter(ui));
+
+        ui.horizontal(|ui| {
+            let mut response =
+                ui.allocate_response(vec2(diameter, diameter), Sense::click_and_drag());
+            self.keyboard_focus_id = Some(response.id.with("keyboard_entry"));
+            self.gesture_id = Some(response.id.with("gesture"));
+
+            self.dial_ui(ui, &mut response);
+
+            // Typing in a value from the menu uses the dial's own text entry when it has one
+            let mut menu = ParamContextMenu::for_param(self.param, self.setter);
+            if let Some(menu_context) = self.menu_context {
+                menu = menu.with_context(menu_context);
+            }
+            if self.draw_value {
+                if menu.show(&response) {
+                    self.begin_keyboard_entry_next_frame(ui);
+                }
+            } else {
+                menu.show_with_value_field(&response);
+            }
+
+            if self.draw_value {
+                self.value_ui(ui, response.id);
+            }
+
+            response
+        })
+        .inner
+    }
+}
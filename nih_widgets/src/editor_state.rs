@@ -0,0 +1,269 @@
+//! A resizable and scalable wrapper around `EguiState`.
+
+use atomic_float::AtomicF32;
+use nih_plug::params::persist::PersistentField;
+use nih_plug::prelude::ParamSetter;
+use nih_plug_egui::egui::{self, Align2, ComboBox, Order, Rect, Sense, Stroke, Ui, Vec2};
+use nih_plug_egui::EguiState;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// The UI scales the user can pick from.
+pub const SCALES: [f32; 6] = [0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
+/// The smallest size the editor can be resized to at 100% scale, in logical pixels.
+pub const MIN_SIZE: (u32, u32) = (250, 200);
+/// The largest size the editor can be resized to at 100% scale, in logical pixels.
+pub const MAX_SIZE: (u32, u32) = (2560, 1600);
+
+/// The editor's window size and UI scale. This is meant to be stored in a `#[persist]` field
+/// instead of a plain `Arc<EguiState>`, and the wrapped state is passed to `create_egui_editor()`
+/// through [`ScalableEguiState::egui_state()`]. The size is stored in the wrapped `EguiState` so
+/// the editor opens with the restored size.
+pub struct ScalableEguiState {
+    egui_state: Arc<EguiState>,
+    /// The UI scale, where 1.0 means 100%.
+    scale: AtomicF32,
+}
+
+/// The serialized form of [`ScalableEguiState`]. Without a scale field this is the same as the
+/// serialized `EguiState`, so states saved before the editor became scalable can still be loaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScalableEguiStateData {
+    size: (u32, u32),
+    #[serde(default = "default_scale")]
+    scale: f32,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+impl<'a> PersistentField<'a, ScalableEguiStateData> for Arc<ScalableEguiState> {
+    fn set(&self, new_value: ScalableEguiStateData) {
+        let (width, height) = new_value.size;
+        self.set_egui_state_size(width, height);
+        self.scale
+            .store(clamp_scale(new_value.scale), Ordering::Relaxed);
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&ScalableEguiStateData) -> R,
+    {
+        f(&ScalableEguiStateData {
+            size: self.size(),
+            scale: self.scale(),
+        })
+    }
+}
+
+impl ScalableEguiState {
+    /// Initialize the state with the editor's default size at 100% scale, in logical pixels.
+    pub fn from_size(width: u32, height: u32) -> Arc<Self> {
+        Arc::new(Self {
+            egui_state: EguiState::from_size(width, height),
+            scale: AtomicF32::new(1.0),
+        })
+    }
+
+    /// The state to pass to `create_egui_editor()`.
+    pub fn egui_state(&self) -> Arc<EguiState> {
+        self.egui_state.clone()
+    }
+
+    /// Whether the editor is currently open.
+    pub fn is_open(&self) -> bool {
+        self.egui_state.is_open()
+    }
+
+    /// The editor's current size in logical pixels.
+    pub fn size(&self) -> (u32, u32) {
+        self.egui_state.size()
+    }
+
+    /// The current UI scale, where 1.0 means 100%.
+    pub fn scale(&self) -> f32 {
+        self.scale.load(Ordering::Relaxed)
+    }
+
+    /// Resize the editor, and ask the host to resize the editor window to match. The size is
+    /// clamped to [`MIN_SIZE`] and [`MAX_SIZE`] multiplied by the current scale. The old size is
+    /// restored if the host refuses the new size.
+    pub fn resize(&self, setter: &ParamSetter, width: u32, height: u32) {
+        let scale = self.scale();
+        let clamp = |size: u32, min: u32, max: u32| {
+            size.clamp(
+                (min as f32 * scale).round() as u32,
+                (max as f32 * scale).round() as u32,
+            )
+        };
+        let new_size = (
+            clamp(width, MIN_SIZE.0, MAX_SIZE.0),
+            clamp(height, MIN_SIZE.1, MAX_SIZE.1),
+        );
+
+        let old_size = self.size();
+        if new_size == old_size {
+            return;
+        }
+
+        self.set_egui_state_size(new_size.0, new_size.1);
+        if !setter.raw_context.request_resize() {
+            self.set_egui_state_size(old_size.0, old_size.1);
+        }
+    }
+
+    /// Change the UI scale. The editor is resized by the same factor so the layout stays the same,
+    /// and the new scale is applied to the context's style.
+    pub fn set_scale(&self, ctx: &egui::Context, setter: &ParamSetter, scale: f32) {
+        let old_scale = self.scale();
+        let scale = clamp_scale(scale);
+        if scale == old_scale {
+            return;
+        }
+
+        self.scale.store(scale, Ordering::Relaxed);
+        let (width, height) = self.size();
+        let factor = scale / old_scale;
+        self.resize(
+            setter,
+            (width as f32 * factor).round() as u32,
+            (height as f32 * factor).round() as u32,
+        );
+
+        apply_scale(ctx, scale);
+    }
+
+    fn set_egui_state_size(&self, width: u32, height: u32) {
+        // `EguiState` only allows changing its size through its persistent field implementation
+        match Arc::try_unwrap(EguiState::from_size(width, height)) {
+            Ok(new_state) => self.egui_state.set(new_state),
+            Err(_) => unreachable!("The new state is not shared with anything"),
+        }
+    }
+}
+
+fn clamp_scale(scale: f32) -> f32 {
+    scale.clamp(SCALES[0], SCALES[SCALES.len() - 1])
+}
+
+/// Scale egui's default text sizes and spacing by `scale` and apply them to the context. The
+/// visuals, and thus the theme, are left alone. This should be called from the editor's build
+/// function with [`ScalableEguiState::scale()`].
+pub fn apply_scale(ctx: &egui::Context, scale: f32) {
+    let default_style = egui::Style::default();
+    let mut style = (*ctx.style()).clone();
+
+    style.text_styles = default_style
+        .text_styles
+        .into_iter()
+        .map(|(text_style, mut font_id)| {
+            font_id.size *= scale;
+            (text_style, font_id)
+        })
+        .collect();
+
+    let default_spacing = default_style.spacing;
+    let spacing = &mut style.spacing;
+    spacing.item_spacing = default_spacing.item_spacing * scale;
+    spacing.button_padding = default_spacing.button_padding * scale;
+    spacing.indent = default_spacing.indent * scale;
+    spacing.interact_size = default_spacing.interact_size * scale;
+    spacing.slider_width = default_spacing.slider_width * scale;
+    spacing.text_edit_width = default_spacing.text_edit_width * scale;
+    spacing.icon_width = default_spacing.icon_width * scale;
+    spacing.icon_width_inner = default_spacing.icon_width_inner * scale;
+    spacing.icon_spacing = default_spacing.icon_spacing * scale;
+    spacing.tooltip_width = default_spacing.tooltip_width * scale;
+    spacing.scroll_bar_width = default_spacing.scroll_bar_width * scale;
+
+    ctx.set_style(style);
+}
+
+/// A drop-down for picking one of the [`SCALES`].
+pub fn scale_picker(ui: &mut Ui, state: &ScalableEguiState, setter: &ParamSetter) {
+    let current_scale = state.scale();
+    let mut new_scale = current_scale;
+    ComboBox::from_label("Scale")
+        .selected_text(format!("{:.0}%", current_scale * 100.0))
+        .show_ui(ui, |ui| {
+            for scale in SCALES {
+                ui.selectable_value(&mut new_scale, scale, format!("{:.0}%", scale * 100.0));
+            }
+        });
+
+    if new_scale != current_scale {
+        state.set_scale(ui.ctx(), setter, new_scale);
+    }
+}
+
+/// Draw a resize handle in the editor's bottom right corner. Dragging it shows an outline of the
+/// new size, and the editor is resized when the handle is released.
+pub fn resize_corner(ctx: &egui::Context, state: &ScalableEguiState, setter: &ParamSetter) {
+    let corner_size = Vec2::splat(ctx.style().visuals.resize_corner_size * state.scale());
+    let screen_rect = ctx.input().screen_rect();
+
+    egui::Area::new("resize_corner")
+        .order(Order::Foreground)
+        .fixed_pos(screen_rect.max - corner_size)
+        .show(ctx, |ui| {
+            let (rect, response) = ui.allocate_exact_size(corner_size, Sense::drag());
+            let drag_id = response.id.with("drag_size");
+
+            if response.drag_started() {
+                let (width, height) = state.size();
+                ui.memory()
+                    .data
+                    .insert_temp(drag_id, Vec2::new(width as f32, height as f32));
+            }
+            let drag_size = if response.dragged() {
+                let drag_size: Vec2 = ui
+                    .memory()
+                    .data
+                    .get_temp(drag_id)
+                    .unwrap_or(screen_rect.size())
+                    + response.drag_delta();
+                ui.memory().data.insert_temp(drag_id, drag_size);
+
+                Some(drag_size)
+            } else {
+                None
+            };
+
+            if let Some(drag_size) = drag_size {
+                ui.painter().rect_stroke(
+                    Rect::from_min_size(screen_rect.min, drag_size),
+                    0.0,
+                    ui.visuals().selection.stroke,
+                );
+            }
+            if response.drag_released() {
+                if let Some(drag_size) = ui.memory().data.get_temp::<Vec2>(drag_id) {
+                    state.resize(
+                        setter,
+                        drag_size.x.max(0.0).round() as u32,
+                        drag_size.y.max(0.0).round() as u32,
+                    );
+                }
+            }
+
+            // Three diagonal lines, like the corner of egui's own resizable windows
+            let stroke = if response.hovered() || response.dragged() {
+                ui.visuals().widgets.hovered.fg_stroke
+            } else {
+                Stroke::new(1.0, ui.visuals().widgets.noninteractive.fg_stroke.color)
+            };
+            let corner = Align2::RIGHT_BOTTOM.pos_in_rect(&rect);
+            let size = rect.width();
+            for offset in [size, size * 0.66, size * 0.33] {
+                ui.painter().line_segment(
+                    [
+                        corner - Vec2::new(offset, 0.0),
+                        corner - Vec2::new(0.0, offset),
+                    ],
+                    stroke,
+                );
+            }
+        });
+}
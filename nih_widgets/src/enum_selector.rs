@@ -0,0 +1,66 @@
+//! A row of buttons for picking the value of a NIH-plug `EnumParam`.
+
+use nih_plug::prelude::{Enum, EnumParam, Param, ParamSetter};
+use nih_plug_egui::egui::{Response, Ui, Widget};
+
+/// Shows every variant of an [`EnumParam`] as a selectable button, with the parameter's current
+/// value highlighted. Clicking a button selects that variant. The buttons are regular egui widgets,
+/// so they follow the active `egui::Visuals`.
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct EnumSelector<'a, T: Enum + PartialEq + 'static> {
+    param: &'a EnumParam<T>,
+    setter: &'a ParamSetter<'a>,
+
+    vertical: bool,
+}
+
+impl<'a, T: Enum + PartialEq + 'static> EnumSelector<'a, T> {
+    /// Create a new selector for a parameter. Use the other methods to modify the selector before
+    /// passing it to [`Ui::add()`].
+    pub fn for_param(param: &'a EnumParam<T>, setter: &'a ParamSetter<'a>) -> Self {
+        Self {
+            param,
+            setter,
+
+            vertical: false,
+        }
+    }
+
+    /// Stack the buttons vertically instead of putting them in a row.
+    pub fn vertical(mut self) -> Self {
+        self.vertical = true;
+        self
+    }
+
+    fn buttons_ui(&self, ui: &mut Ui) -> Response {
+        let selected_index = self.param.modulated_plain_value().to_index();
+
+        let mut response: Option<Response> = None;
+        for (index, name) in T::variants().iter().enumerate() {
+            let mut button_response = ui.selectable_label(index == selected_index, *name);
+            if button_response.clicked() && index != selected_index {
+                self.setter.begin_set_parameter(self.param);
+                self.setter.set_parameter(self.param, T::from_index(index));
+                self.setter.end_set_parameter(self.param);
+                button_response.mark_changed();
+            }
+
+            response = Some(match response {
+                Some(response) => response.union(button_response),
+                None => button_response,
+            });
+        }
+
+        response.expect("An enum needs at least one variant")
+    }
+}
+
+impl<T: Enum + PartialEq + 'static> Widget for EnumSelector<'_, T> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        if self.vertical {
+            ui.vertical(|ui| self.buttons_ui(ui)).inner
+        } else {
+            ui.horizontal(|ui| self.buttons_ui(ui)).inner
+        }
+    }
+}
@@ -0,0 +1,563 @@
+//! Undo and redo for parameter edits, and A/B comparison between two snapshots of the parameters.
+//!
+//! Edits are recorded by wrapping the plugin's editor in a [`HistoryEditor`]. That gives the
+//! editor a `GuiContext` that watches every `begin_set_parameter()`/`end_set_parameter()` pair, so
+//! every committed automation gesture becomes one undo step, no matter which widget made it.
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use nih_plug::prelude::{Editor, GuiContext, ParamPtr, ParamSetter, ParentWindowHandle, PluginApi};
+use nih_plug::wrapper::state::PluginState;
+use nih_plug_egui::egui::{self, Button, Key, Ui};
+use parking_lot::Mutex;
+
+/// The maximum number of undo steps that are kept around. The oldest edits are dropped first.
+pub const MAX_UNDO_STEPS: usize = 100;
+
+/// A committed change to a single parameter, as normalized values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Edit<K> {
+    pub param: K,
+    pub before: f32,
+    pub after: f32,
+}
+
+/// A gesture that has been started but not yet ended.
+#[derive(Debug, Clone, Copy)]
+struct OpenGesture<K> {
+    param: K,
+    before: f32,
+    /// The last value set during the gesture, if any.
+    after: Option<f32>,
+}
+
+/// The undo and redo stacks. `K` identifies a parameter. This is a `ParamPtr` in
+/// [`EditHistory`], but the model itself doesn't need to know about parameters.
+#[derive(Debug)]
+pub struct History<K> {
+    undo_stack: VecDeque<Edit<K>>,
+    redo_stack: Vec<Edit<K>>,
+    open_gestures: Vec<OpenGesture<K>>,
+}
+
+impl<K> Default for History<K> {
+    fn default() -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            open_gestures: Vec::new(),
+        }
+    }
+}
+
+impl<K: Copy + PartialEq> History<K> {
+    /// Start a gesture for a parameter that currently has the normalized value `value`. Nested
+    /// gestures for the same parameter are ignored.
+    pub fn begin_gesture(&mut self, param: K, value: f32) {
+        if !self
+            .open_gestures
+            .iter()
+            .any(|gesture| gesture.param == param)
+        {
+            self.open_gestures.push(OpenGesture {
+                param,
+                before: value,
+                after: None,
+            });
+        }
+    }
+
+    /// Record a new value for a parameter. This is ignored if the parameter doesn't have an open
+    /// gesture, since a change outside of a gesture can't be committed.
+    pub fn set_value(&mut self, param: K, value: f32) {
+        if let Some(gesture) = self
+            .open_gestures
+            .iter_mut()
+            .find(|gesture| gesture.param == param)
+        {
+            gesture.after = Some(value);
+        }
+    }
+
+    /// End a parameter's gesture and commit it as an undo step if the value changed. `value` is the
+    /// parameter's current normalized value, which is used if no new value was set during the
+    /// gesture. Committing an edit clears the redo stack.
+    pub fn end_gesture(&mut self, param: K, value: f32) {
+        let Some(index) = self
+            .open_gestures
+            .iter()
+            .position(|gesture| gesture.param == param)
+        else {
+            return;
+        };
+
+        let gesture = self.open_gestures.remove(index);
+        let after = gesture.after.unwrap_or(value);
+        if after != gesture.before {
+            if self.undo_stack.len() == MAX_UNDO_STEPS {
+                self.undo_stack.pop_front();
+            }
+            self.undo_stack.push_back(Edit {
+                param,
+                before: gesture.before,
+                after,
+            });
+            self.redo_stack.clear();
+        }
+    }
+
+    /// Take the last committed edit off the undo stack. The caller should set the parameter back
+    /// to the edit's `before` value.
+    pub fn undo(&mut self) -> Option<Edit<K>> {
+        let edit = self.undo_stack.pop_back()?;
+        self.redo_stack.push(edit);
+
+        Some(edit)
+    }
+
+    /// Take the last undone edit off the redo stack. The caller should set the parameter to the
+    /// edit's `after` value.
+    pub fn redo(&mut self) -> Option<Edit<K>> {
+        let edit = self.redo_stack.pop()?;
+        self.undo_stack.push_back(edit);
+
+        Some(edit)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+}
+
+/// One of the two snapshots of an A/B comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbSlot {
+    A,
+    B,
+}
+
+/// Two snapshots to flip between. The active snapshot is the plugin's current state, so only the
+/// inactive snapshot is stored.
+#[derive(Debug)]
+pub struct AbComparison<S> {
+    active: AbSlot,
+    inactive: Option<S>,
+}
+
+impl<S> Default for AbComparison<S> {
+    fn default() -> Self {
+        Self {
+            active: AbSlot::A,
+            inactive: None,
+        }
+    }
+}
+
+impl<S> AbComparison<S> {
+    pub fn active(&self) -> AbSlot {
+        self.active
+    }
+
+    /// Switch to the other snapshot. `current` is the plugin's current state, which becomes the
+    /// inactive snapshot. Returns the state that should be loaded, or `None` if the other snapshot
+    /// was still empty. In that case the other snapshot starts out as a copy of the current state.
+    pub fn switch(&mut self, current: S) -> Option<S> {
+        self.active = match self.active {
+            AbSlot::A => AbSlot::B,
+            AbSlot::B => AbSlot::A,
+        };
+
+        self.inactive.replace(current)
+    }
+
+    /// Overwrite the inactive snapshot with the current state.
+    pub fn copy_to_inactive(&mut self, current: S) {
+        self.inactive = Some(current);
+    }
+}
+
+/// The undo history and A/B snapshots for a plugin. This is stored in the plugin so it survives
+/// closing and reopening the editor, and shared with the [`HistoryEditor`] that records the edits.
+#[derive(Default)]
+pub struct EditHistory {
+    history: Mutex<History<ParamPtr>>,
+    ab: Mutex<AbComparison<PluginState>>,
+    /// Set while an undo or redo is being applied, so those changes are not recorded as new edits.
+    replaying: AtomicBool,
+    /// Incremented whenever a parameter changes, see [`EditHistory::change_count()`].
+    change_count: AtomicU64,
+}
+
+impl EditHistory {
+    /// Undo the last committed edit.
+    pub fn undo(&self, setter: &ParamSetter) {
+        // The lock is released before applying the edit, since that's recorded through this object
+        let edit = self.history.lock().undo();
+        if let Some(edit) = edit {
+            self.replay(setter, edit.param, edit.before);
+        }
+    }
+
+    /// Redo the last undone edit.
+    pub fn redo(&self, setter: &ParamSetter) {
+        let edit = self.history.lock().redo();
+        if let Some(edit) = edit {
+            self.replay(setter, edit.param, edit.after);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.lock().can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.lock().can_redo()
+    }
+
+    /// The active A/B snapshot.
+    pub fn active_slot(&self) -> AbSlot {
+        self.ab.lock().active()
+    }
+
+    /// Flip between the A and B snapshots. Only the parameters are part of the snapshots, so the
+    /// persisted fields, like the editor's size, stay the same.
+    pub fn switch_ab(&self, setter: &ParamSetter) {
+        let other_state = self.ab.lock().switch(params_state(setter));
+        if let Some(state) = other_state {
+            setter.raw_context.set_state(state);
+        }
+    }
+
+    /// Copy the active snapshot to the inactive one.
+    pub fn copy_to_inactive(&self, setter: &ParamSetter) {
+        self.ab.lock().copy_to_inactive(params_state(setter));
+    }
+
+    fn replay(&self, setter: &ParamSetter, param: ParamPtr, normalized_value: f32) {
+        self.replaying.store(true, Ordering::Relaxed);
+        // SAFETY: The pointer was recorded from the plugin's own parameters, which live as long
+        //         as the plugin and thus this history
+        unsafe {
+            setter.raw_context.raw_begin_set_parameter(param);
+            setter
+                .raw_context
+                .raw_set_parameter_normalized(param, normalized_value);
+            setter.raw_context.raw_end_set_parameter(param);
+        }
+        self.replaying.store(false, Ordering::Relaxed);
+    }
+
+    fn is_replaying(&self) -> bool {
+        self.replaying.load(Ordering::Relaxed)
+    }
+
+    /// A counter that changes whenever a gesture ends, a state is loaded through the editor, or the
+    /// host reports a parameter change to the [`HistoryEditor`]. Anything that's expensive to
+    /// derive from the plugin's state, like the preset bar's dirty marker, only needs to be
+    /// recomputed when this changes.
+    pub fn change_count(&self) -> u64 {
+        self.change_count.load(Ordering::Acquire)
+    }
+
+    fn note_change(&self) {
+        self.change_count.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// The plugin's current state without the persisted fields.
+fn params_state(setter: &ParamSetter) -> PluginState {
+    let mut state = setter.raw_context.get_state();
+    state.fields.clear();
+
+    state
+}
+
+/// Wraps a plugin's editor so the edits made through it are recorded in an [`EditHistory`].
+pub struct HistoryEditor {
+    inner: Box<dyn Editor>,
+    history: Arc<EditHistory>,
+}
+
+impl HistoryEditor {
+    /// Wrap an editor, usually the one returned by `create_egui_editor()`.
+    pub fn wrap(editor: Box<dyn Editor>, history: Arc<EditHistory>) -> Box<dyn Editor> {
+        Box::new(Self {
+            inner: editor,
+            history,
+        })
+    }
+}
+
+impl Editor for HistoryEditor {
+    fn spawn(
+        &self,
+        parent: ParentWindowHandle,
+        context: Arc<dyn GuiContext>,
+    ) -> Box<dyn Any + Send> {
+        self.inner.spawn(
+            parent,
+            Arc::new(RecordingContext {
+                inner: context,
+                history: self.history.clone(),
+            }),
+        )
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.inner.size()
+    }
+
+    fn set_scale_factor(&self, factor: f32) -> bool {
+        self.inner.set_scale_factor(factor)
+    }
+
+    fn param_value_changed(&self, id: &str, normalized_value: f32) {
+        self.history.note_change();
+        self.inner.param_value_changed(id, normalized_value)
+    }
+
+    fn param_modulation_changed(&self, id: &str, modulation_offset: f32) {
+        self.inner.param_modulation_changed(id, modulation_offset)
+    }
+
+    fn param_values_changed(&self) {
+        self.history.note_change();
+        self.inner.param_values_changed()
+    }
+}
+
+/// Forwards everything to the wrapper's `GuiContext`, while recording parameter gestures.
+struct RecordingContext {
+    inner: Arc<dyn GuiContext>,
+    history: Arc<EditHistory>,
+}
+
+impl GuiContext for RecordingContext {
+    fn plugin_api(&self) -> PluginApi {
+        self.inner.plugin_api()
+    }
+
+    fn request_resize(&self) -> bool {
+        self.inner.request_resize()
+    }
+
+    unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
+        if !self.history.is_replaying() {
+            self.history
+                .history
+                .lock()
+                .begin_gesture(param, param.unmodulated_normalized_value());
+        }
+        self.inner.raw_begin_set_parameter(param)
+    }
+
+    unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        if !self.history.is_replaying() {
+            self.history.history.lock().set_value(param, normalized);
+        }
+        self.inner.raw_set_parameter_normalized(param, normalized)
+    }
+
+    unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
+        self.inner.raw_end_set_parameter(param);
+        if !self.history.is_replaying() {
+            self.history
+                .history
+                .lock()
+                .end_gesture(param, param.unmodulated_normalized_value());
+        }
+        self.history.note_change();
+    }
+
+    fn get_state(&self) -> PluginState {
+        self.inner.get_state()
+    }
+
+    fn set_state(&self, state: PluginState) {
+        self.inner.set_state(state);
+        self.history.note_change();
+    }
+}
+
+/// Undo with Ctrl+Z and redo with Ctrl+Shift+Z, or Cmd instead of Ctrl on macOS. Nothing happens
+/// while a text field has keyboard focus, since those have their own undo. Call this once per
+/// frame from the editor's update function.
+pub fn handle_shortcuts(ctx: &egui::Context, history: &EditHistory, setter: &ParamSetter) {
+    if ctx.wants_keyboard_input() {
+        return;
+    }
+
+    let (undo, redo) = {
+        let input = ctx.input();
+        let z_pressed = input.modifiers.command && input.key_pressed(Key::Z);
+        (
+            z_pressed && !input.modifiers.shift,
+            z_pressed && input.modifiers.shift,
+        )
+    };
+
+    if undo {
+        history.undo(setter);
+    } else if redo {
+        history.redo(setter);
+    }
+}
+
+/// Undo and redo buttons, and buttons for switching between and copying the A/B snapshots.
+pub fn history_bar(ui: &mut Ui, history: &EditHistory, setter: &ParamSetter) {
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(history.can_undo(), Button::new("Undo"))
+            .on_hover_text("Ctrl+Z")
+            .clicked()
+        {
+            history.undo(setter);
+        }
+        if ui
+            .add_enabled(history.can_redo(), Button::new("Redo"))
+            .on_hover_text("Ctrl+Shift+Z")
+            .clicked()
+        {
+            history.redo(setter);
+        }
+
+        ui.separator();
+
+        let active_slot = history.active_slot();
+        for slot in [AbSlot::A, AbSlot::B] {
+            let label = match slot {
+                AbSlot::A => "A",
+                AbSlot::B => "B",
+            };
+            if ui.selectable_label(active_slot == slot, label).clicked() && active_slot != slot {
+                history.switch_ab(setter);
+            }
+        }
+
+        let copy_label = match active_slot {
+            AbSlot::A => "Copy A to B",
+            AbSlot::B => "Copy B to A",
+        };
+        if ui.button(copy_label).clicked() {
+            history.copy_to_inactive(setter);
+        }
+    });
+}
+
+#[test]
+fn test_gestures_become_edits() {
+    let mut history = History::default();
+    assert!(!history.can_undo());
+
+    history.begin_gesture(1, 0.5);
+    history.set_value(1, 0.6);
+    history.set_value(1, 0.7);
+    history.end_gesture(1, 0.0);
+    assert_eq!(
+        history.undo(),
+        Some(Edit {
+            param: 1,
+            before: 0.5,
+            after: 0.7
+        })
+    );
+    assert_eq!(history.undo(), None);
+
+    // Gestures that don't change anything, and changes outside of gestures, are not recorded
+    history.begin_gesture(1, 0.5);
+    history.end_gesture(1, 0.5);
+    history.set_value(1, 0.9);
+    history.end_gesture(1, 0.9);
+    assert!(!history.can_undo());
+
+    // Without a set value the current value at the end of the gesture is used
+    history.begin_gesture(2, 0.1);
+    history.end_gesture(2, 0.2);
+    assert_eq!(history.undo().map(|edit| edit.after), Some(0.2));
+}
+
+#[test]
+fn test_overlapping_gestures() {
+    let mut history = History::default();
+    history.begin_gesture(1, 0.0);
+    history.begin_gesture(2, 0.0);
+    // Nested gestures for the same parameter keep the original value
+    history.begin_gesture(1, 0.3);
+    history.set_value(1, 0.5);
+    history.set_value(2, 1.0);
+    history.end_gesture(2, 1.0);
+    history.end_gesture(1, 0.5);
+
+    assert_eq!(
+        history.undo().map(|edit| (edit.param, edit.before)),
+        Some((1, 0.0))
+    );
+    assert_eq!(history.undo().map(|edit| edit.param), Some(2));
+}
+
+#[test]
+fn test_undo_redo() {
+    let mut history = History::default();
+    for (before, after) in [(0.0, 0.1), (0.1, 0.2), (0.2, 0.3)] {
+        history.begin_gesture(1, before);
+        history.set_value(1, after);
+        history.end_gesture(1, after);
+    }
+
+    assert_eq!(history.undo().map(|edit| edit.before), Some(0.2));
+    assert_eq!(history.undo().map(|edit| edit.before), Some(0.1));
+    assert!(history.can_redo());
+    assert_eq!(history.redo().map(|edit| edit.after), Some(0.2));
+
+    // A new edit after undoing discards the redo stack
+    history.begin_gesture(1, 0.2);
+    history.set_value(1, 0.8);
+    history.end_gesture(1, 0.8);
+    assert!(!history.can_redo());
+    assert_eq!(history.redo(), None);
+    assert_eq!(history.undo().map(|edit| edit.after), Some(0.8));
+    assert_eq!(history.undo().map(|edit| edit.after), Some(0.2));
+    assert_eq!(history.undo().map(|edit| edit.after), Some(0.1));
+    assert_eq!(history.undo(), None);
+}
+
+#[test]
+fn test_history_limit() {
+    let mut history = History::default();
+    for step in 0..MAX_UNDO_STEPS + 10 {
+        history.begin_gesture(0, step as f32);
+        history.end_gesture(0, step as f32 + 1.0);
+    }
+
+    let mut num_undos = 0;
+    let mut oldest_edit = None;
+    while let Some(edit) = history.undo() {
+        num_undos += 1;
+        oldest_edit = Some(edit);
+    }
+    assert_eq!(num_undos, MAX_UNDO_STEPS);
+    assert_eq!(oldest_edit.map(|edit| edit.before), Some(10.0));
+}
+
+#[test]
+fn test_ab_comparison() {
+    let mut ab = AbComparison::default();
+    assert_eq!(ab.active(), AbSlot::A);
+
+    // B starts out as a copy of A
+    assert_eq!(ab.switch("a"), None);
+    assert_eq!(ab.active(), AbSlot::B);
+    assert_eq!(ab.switch("b"), Some("a"));
+    assert_eq!(ab.active(), AbSlot::A);
+    assert_eq!(ab.switch("a2"), Some("b"));
+
+    ab.copy_to_inactive("b2");
+    assert_eq!(ab.switch("b3"), Some("b2"));
+}
@@ -0,0 +1,57 @@
+//! Increment and decrement buttons for a NIH-plug `IntParam`.
+
+use nih_plug::prelude::{IntParam, Param, ParamSetter};
+use nih_plug_egui::egui::{Button, Response, Ui, Widget};
+
+/// Shows an [`IntParam`]'s value between a decrement and an increment button. The buttons are
+/// disabled at the ends of the parameter's range. The buttons are regular egui widgets, so they
+/// follow the active `egui::Visuals`.
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct IntStepper<'a> {
+    param: &'a IntParam,
+    setter: &'a ParamSetter<'a>,
+}
+
+impl<'a> IntStepper<'a> {
+    /// Create a new stepper for a parameter.
+    pub fn for_param(param: &'a IntParam, setter: &'a ParamSetter<'a>) -> Self {
+        Self { param, setter }
+    }
+
+    fn set_value(&self, value: i32) {
+        self.setter.begin_set_parameter(self.param);
+        self.setter.set_parameter(self.param, value);
+        self.setter.end_set_parameter(self.param);
+    }
+}
+
+impl Widget for IntStepper<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        // Reversed ranges map the normalized 0.0 to the largest value
+        let range_start = self.param.preview_plain(0.0);
+        let range_end = self.param.preview_plain(1.0);
+        let (min, max) = (range_start.min(range_end), range_start.max(range_end));
+        let value = self.param.unmodulated_plain_value();
+
+        ui.horizontal(|ui| {
+            let mut decrement_response = ui.add_enabled(value > min, Button::new("-"));
+            if decrement_response.clicked() {
+                self.set_value(value - 1);
+                decrement_response.mark_changed();
+            }
+
+            let label_response = ui.label(self.param.to_string());
+
+            let mut increment_response = ui.add_enabled(value < max, Button::new("+"));
+            if increment_response.clicked() {
+                self.set_value(value + 1);
+                increment_response.mark_changed();
+            }
+
+            decrement_response
+                .union(label_response)
+                .union(increment_response)
+        })
+        .inner
+    }
+}
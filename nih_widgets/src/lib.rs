@@ -0,0 +1,21 @@
+//! Parameter aware egui widgets for NIH-plug plugins. See the readme for an overview.
+
+pub mod dial;
+pub mod editor_state;
+pub mod enum_selector;
+pub mod history;
+pub mod int_stepper;
+pub mod param_menu;
+pub mod presets;
+pub mod theme;
+pub mod toggle_switch;
+
+pub use dial::{DefaultDialSkin, Dial, DialSkin, DialState, DragMode};
+pub use editor_state::ScalableEguiState;
+pub use enum_selector::EnumSelector;
+pub use history::{EditHistory, HistoryEditor};
+pub use int_stepper::IntStepper;
+pub use param_menu::{ParamContextMenu, ParamMenuContext, ParamMenuState};
+pub use presets::{Preset, PresetManager};
+pub use theme::{Theme, ThemeSelection};
+pub use toggle_switch::{DefaultToggleSkin, ToggleSkin, ToggleSwitch};
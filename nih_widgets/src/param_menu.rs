@@ -0,0 +1,671 @@
+//! A right-click context menu for parameter widgets, together with the MIDI learn mappings and
+//! parameter locks it manages.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use atomic_float::AtomicF32;
+use lazy_static::lazy_static;
+use nih_plug::params::persist::PersistentField;
+#[cfg(test)]
+use nih_plug::prelude::{FloatParam, FloatRange, GuiContext, PluginApi, SmoothingStyle};
+use nih_plug::prelude::{Param, ParamFlags, ParamPtr, ParamSetter, Params};
+#[cfg(test)]
+use nih_plug::wrapper::state::PluginState;
+use nih_plug_egui::egui::{Button, Id, Key, Response, TextEdit, Ui};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+
+/// The number of MIDI CC numbers.
+const NUM_MIDI_CCS: usize = 128;
+/// Stored in [`ParamMenuState::learned_cc`] when no MIDI CC has been learned.
+const NO_CC: u32 = u32::MAX;
+
+lazy_static! {
+    /// The value copied with the menu's copy entry, stored as the parameter's display string so it
+    /// can be pasted into any parameter that can parse it.
+    static ref CLIPBOARD_MEMORY_ID: Id = Id::new((file!(), 0));
+}
+
+/// The MIDI learn mappings and locked parameters for all of a plugin's parameters. This is meant to
+/// be stored in a `#[persist]` field of the plugin's parameters struct so the mappings and locks are
+/// saved together with the rest of the plugin's state.
+///
+/// MIDI CCs are received on the audio thread with [`ParamMenuState::process_midi_cc()`], which
+/// learns new mappings and stores the values of mapped CCs. Plugins can only change their
+/// parameters through the GUI context, so the editor applies those values with
+/// [`ParamMenuState::handle_midi_input()`]. Mapped CCs thus only change the parameters while the
+/// editor is open.
+pub struct ParamMenuState {
+    /// Maps MIDI CC numbers to the IDs of the parameters they control.
+    midi_mappings: RwLock<BTreeMap<u8, String>>,
+    /// The IDs of the parameters that should keep their current value when a preset is loaded.
+    locked_params: RwLock<BTreeSet<String>>,
+    /// The ID of the parameter that will be mapped to the next MIDI CC that's received, if any.
+    learning: Mutex<Option<String>>,
+
+    /// A lock-free snapshot of which MIDI CCs are mapped in `midi_mappings`, for the audio thread.
+    /// This is rebuilt whenever the mappings change.
+    cc_mapped: [AtomicBool; NUM_MIDI_CCS],
+    /// Set while `learning` contains a parameter ID. The audio thread maps the next MIDI CC it
+    /// receives to that parameter.
+    learning_cc: AtomicBool,
+    /// The MIDI CC the audio thread learned for `learning`, or [`NO_CC`]. The editor then stores the
+    /// mapping in `midi_mappings`.
+    learned_cc: AtomicU32,
+
+    /// The last received value for every MIDI CC, written from the audio thread.
+    cc_values: [AtomicF32; NUM_MIDI_CCS],
+    /// Set when a new value for a MIDI CC has been received that hasn't been handled yet.
+    cc_changed: [AtomicBool; NUM_MIDI_CCS],
+}
+
+/// The serialized form of [`ParamMenuState`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParamMenuStateData {
+    #[serde(default)]
+    midi_mappings: BTreeMap<u8, String>,
+    #[serde(default)]
+    locked_params: BTreeSet<String>,
+}
+
+impl Default for ParamMenuState {
+    fn default() -> Self {
+        Self {
+            midi_mappings: RwLock::new(BTreeMap::new()),
+            locked_params: RwLock::new(BTreeSet::new()),
+            learning: Mutex::new(None),
+
+            cc_mapped: std::array::from_fn(|_| AtomicBool::new(false)),
+            learning_cc: AtomicBool::new(false),
+            learned_cc: AtomicU32::new(NO_CC),
+
+            cc_values: std::array::from_fn(|_| AtomicF32::new(0.0)),
+            cc_changed: std::array::from_fn(|_| AtomicBool::new(false)),
+        }
+    }
+}
+
+impl<'a> PersistentField<'a, ParamMenuStateData> for ParamMenuState {
+    fn set(&self, new_value: ParamMenuStateData) {
+        *self.midi_mappings.write() = new_value.midi_mappings;
+        *self.locked_params.write() = new_value.locked_params;
+        self.update_cc_mapped();
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&ParamMenuStateData) -> R,
+    {
+        // The editor may have been closed before it stored the mapping the audio thread learned
+        self.commit_midi_learn();
+
+        f(&ParamMenuStateData {
+            midi_mappings: self.midi_mappings.read().clone(),
+            locked_params: self.locked_params.read().clone(),
+        })
+    }
+}
+
+impl ParamMenuState {
+    /// Handle a MIDI CC's new normalized value on the audio thread. If a parameter is waiting for
+    /// MIDI learn, the CC is mapped to that parameter. Otherwise the value is stored for the editor
+    /// if the CC is mapped to a parameter. This doesn't lock or allocate.
+    ///
+    /// Only call this while the editor is open. The parameters can only be changed through the
+    /// editor, so CCs received while it's closed would otherwise be applied when it's reopened.
+    pub fn process_midi_cc(&self, cc: u8, value: f32) {
+        let (Some(cc_mapped), Some(cc_value), Some(cc_changed)) = (
+            self.cc_mapped.get(cc as usize),
+            self.cc_values.get(cc as usize),
+            self.cc_changed.get(cc as usize),
+        ) else {
+            return;
+        };
+
+        if self.learning_cc.swap(false, Ordering::AcqRel) {
+            cc_mapped.store(true, Ordering::Relaxed);
+            self.learned_cc.store(cc as u32, Ordering::Release);
+            return;
+        }
+
+        if cc_mapped.load(Ordering::Relaxed) {
+            cc_value.store(value, Ordering::Relaxed);
+            cc_changed.store(true, Ordering::Release);
+        }
+    }
+
+    /// Set the parameters mapped to the MIDI CCs the audio thread received since the last call
+    /// through the GUI context, and store the mapping the audio thread learned, if any. This should
+    /// be called once per frame from the editor's update function.
+    pub fn handle_midi_input(&self, params: &dyn Params, setter: &ParamSetter) {
+        self.commit_midi_learn();
+
+        let changes = self.take_midi_changes();
+        if changes.is_empty() {
+            return;
+        }
+
+        let param_map = params.param_map();
+        for (param_id, normalized_value) in changes {
+            let param_ptr = param_map
+                .iter()
+                .find(|(id, _, _)| *id == param_id)
+                .map(|(_, param_ptr, _)| *param_ptr);
+            if let Some(param_ptr) = param_ptr {
+                // SAFETY: The pointer comes from the plugin's own parameters, which outlive the
+                //         editor
+                unsafe {
+                    setter.raw_context.raw_begin_set_parameter(param_ptr);
+                    setter
+                        .raw_context
+                        .raw_set_parameter_normalized(param_ptr, normalized_value);
+                    setter.raw_context.raw_end_set_parameter(param_ptr);
+                }
+            }
+        }
+    }
+
+    /// Consume the changed MIDI CCs and turn them into new normalized values for the parameters
+    /// mapped to them.
+    fn take_midi_changes(&self) -> Vec<(String, f32)> {
+        let mut changes = Vec::new();
+        for (cc, (cc_value, cc_changed)) in self.cc_values.iter().zip(&self.cc_changed).enumerate()
+        {
+            if !cc_changed.swap(false, Ordering::Acquire) {
+                continue;
+            }
+
+            if let Some(param_id) = self.midi_mappings.read().get(&(cc as u8)) {
+                changes.push((param_id.clone(), cc_value.load(Ordering::Relaxed)));
+            }
+        }
+
+        changes
+    }
+
+    /// Store the mapping the audio thread learned in `midi_mappings`, if it learned one.
+    fn commit_midi_learn(&self) {
+        let cc = self.learned_cc.swap(NO_CC, Ordering::Acquire);
+        if cc == NO_CC {
+            return;
+        }
+
+        if let Some(param_id) = self.learning.lock().take() {
+            self.map_midi_cc(cc as u8, param_id);
+        }
+    }
+
+    /// Map a MIDI CC to a parameter. Any other mapping for that parameter is removed.
+    fn map_midi_cc(&self, cc: u8, param_id: String) {
+        {
+            let mut midi_mappings = self.midi_mappings.write();
+            midi_mappings.retain(|_, mapped_id| *mapped_id != param_id);
+            midi_mappings.insert(cc, param_id);
+        }
+
+        self.update_cc_mapped();
+    }
+
+    /// Rebuild the audio thread's snapshot of `midi_mappings`.
+    fn update_cc_mapped(&self) {
+        let midi_mappings = self.midi_mappings.read();
+        for (cc, cc_mapped) in self.cc_mapped.iter().enumerate() {
+            cc_mapped.store(midi_mappings.contains_key(&(cc as u8)), Ordering::Relaxed);
+        }
+    }
+
+    /// The MIDI CC mapped to a parameter, if any.
+    pub fn midi_cc_for(&self, param_id: &str) -> Option<u8> {
+        self.commit_midi_learn();
+        self.midi_mappings
+            .read()
+            .iter()
+            .find(|(_, mapped_id)| *mapped_id == param_id)
+            .map(|(cc, _)| *cc)
+    }
+
+    /// Map the next MIDI CC that's received to a parameter.
+    pub fn start_midi_learn(&self, param_id: &str) {
+        self.cancel_midi_learn();
+
+        *self.learning.lock() = Some(param_id.to_owned());
+        self.learning_cc.store(true, Ordering::Release);
+    }
+
+    /// Stop waiting for a MIDI CC to map.
+    pub fn cancel_midi_learn(&self) {
+        self.learning_cc.store(false, Ordering::Release);
+        *self.learning.lock() = None;
+
+        // The audio thread may already have learned a CC that hasn't been stored yet
+        if self.learned_cc.swap(NO_CC, Ordering::Acquire) != NO_CC {
+            self.update_cc_mapped();
+        }
+    }
+
+    /// Whether the next MIDI CC will be mapped to this parameter.
+    pub fn is_learning(&self, param_id: &str) -> bool {
+        self.learning.lock().as_deref() == Some(param_id)
+    }
+
+    /// Remove a parameter's MIDI mapping, if it has one.
+    pub fn forget_midi_mapping(&self, param_id: &str) {
+        self.midi_mappings
+            .write()
+            .retain(|_, mapped_id| mapped_id != param_id);
+        self.update_cc_mapped();
+    }
+
+    /// Whether a parameter should keep its value when a preset is loaded.
+    pub fn is_locked(&self, param_id: &str) -> bool {
+        self.locked_params.read().contains(param_id)
+    }
+
+    /// Lock or unlock a parameter. See [`ParamMenuState::is_locked()`].
+    pub fn set_locked(&self, param_id: &str, locked: bool) {
+        let mut locked_params = self.locked_params.write();
+        if locked {
+            locked_params.insert(param_id.to_owned());
+        } else {
+            locked_params.remove(param_id);
+        }
+    }
+}
+
+/// What the context menu needs to offer MIDI learn and locking. Without this the menu only shows
+/// the entries that work on the parameter itself.
+#[derive(Clone, Copy)]
+pub struct ParamMenuContext<'a> {
+    /// The plugin's parameters, used to look up the parameter's ID.
+    pub params: &'a dyn Params,
+    /// Where the MIDI mappings and locks are stored.
+    pub state: &'a ParamMenuState,
+}
+
+/// A right-click menu for a parameter widget. The menu can reset the parameter, set it to a typed
+/// in value, copy and paste values between parameters, show how the parameter is exposed to the
+/// host, and, when given a [`ParamMenuContext`], MIDI learn and lock the parameter.
+pub struct ParamContextMenu<'a, P: Param> {
+    param: &'a P,
+    setter: &'a ParamSetter<'a>,
+    context: Option<ParamMenuContext<'a>>,
+}
+
+impl<'a, P: Param> ParamContextMenu<'a, P> {
+    /// Create a context menu for a parameter. Use [`ParamContextMenu::show()`] or
+    /// [`ParamContextMenu::show_with_value_field()`] to attach it to a widget's response.
+    pub fn for_param(param: &'a P, setter: &'a ParamSetter<'a>) -> Self {
+        Self {
+            param,
+            setter,
+            context: None,
+        }
+    }
+
+    /// Also show the MIDI learn and lock entries.
+    pub fn with_context(mut self, context: ParamMenuContext<'a>) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// Show the menu when `response` is right clicked. This is meant for widgets that have their
+    /// own text entry. Returns `true` when the menu's "Type value" entry was clicked, in which case
+    /// the widget should start its text entry.
+    pub fn show(self, response: &Response) -> bool {
+        self.show_impl(response, false)
+    }
+
+    /// Show the menu when `response` is right clicked, with a text field for typing in a value
+    /// directly in the menu. This is meant for widgets without their own text entry, like
+    /// `nih_plug_egui`'s `ParamSlider`.
+    pub fn show_with_value_field(self, response: &Response) {
+        self.show_impl(response, true);
+    }
+
+    fn show_impl(&self, response: &Response, value_field: bool) -> bool {
+        let mut type_value = false;
+        response.clone().context_menu(|ui| {
+            if ui
+                .button(format!(
+                    "Reset to {}",
+                    self.param
+                        .normalized_value_to_string(self.param.default_normalized_value(), true)
+                ))
+                .clicked()
+            {
+                self.set_normalized_value(self.param.default_normalized_value());
+                ui.close_menu();
+            }
+
+            if value_field {
+                self.value_field_ui(ui, response.id.with("menu_value_entry"));
+            } else if ui.button("Type value...").clicked() {
+                type_value = true;
+                ui.close_menu();
+            }
+
+            ui.separator();
+            self.clipboard_ui(ui);
+
+            ui.separator();
+            let param_id = self
+                .context
+                .and_then(|context| self.param_id(context.params));
+            ui.menu_button("Automation info", |ui| {
+                self.automation_info_ui(ui, &param_id)
+            });
+
+            if let (Some(context), Some(param_id)) = (self.context, &param_id) {
+                ui.separator();
+                midi_learn_ui(ui, context.state, param_id);
+
+                let mut locked = context.state.is_locked(param_id);
+                if ui
+                    .checkbox(&mut locked, "Lock across preset loads")
+                    .changed()
+                {
+                    context.state.set_locked(param_id, locked);
+                }
+            }
+        });
+
+        type_value
+    }
+
+    /// A text field that follows the parameter's value until it's clicked on. Pressing enter sets
+    /// the parameter to the typed in value.
+    fn value_field_ui(&self, ui: &mut Ui, field_id: Id) {
+        let mut value_entry = if ui.memory().has_focus(field_id) {
+            ui.memory()
+                .data
+                .get_temp::<String>(field_id)
+                .unwrap_or_default()
+        } else {
+            self.param.to_string()
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Value");
+            let response = ui.add(TextEdit::singleline(&mut value_entry).id(field_id));
+            if response.lost_focus() && ui.input().key_pressed(Key::Enter) {
+                if let Some(normalized_value) = self.param.string_to_normalized_value(&value_entry)
+                {
+                    self.set_normalized_value(normalized_value);
+                }
+                ui.close_menu();
+            }
+        });
+
+        ui.memory().data.insert_temp(field_id, value_entry);
+    }
+
+    fn clipboard_ui(&self, ui: &mut Ui) {
+        if ui.button("Copy value").clicked() {
+            let value = self.param.to_string();
+            ui.output().copied_text = value.clone();
+            ui.memory().data.insert_temp(*CLIPBOARD_MEMORY_ID, value);
+            ui.close_menu();
+        }
+
+        let pasted_value = ui
+            .memory()
+            .data
+            .get_temp::<String>(*CLIPBOARD_MEMORY_ID)
+            .and_then(|value| self.param.string_to_normalized_value(&value));
+        if ui
+            .add_enabled(pasted_value.is_some(), Button::new("Paste value"))
+            .clicked()
+        {
+            if let Some(normalized_value) = pasted_value {
+                self.set_normalized_value(normalized_value);
+            }
+            ui.close_menu();
+        }
+    }
+
+    /// Shows how the host sees the parameter.
+    fn automation_info_ui(&self, ui: &mut Ui, param_id: &Option<String>) {
+        if let Some(param_id) = param_id {
+            ui.label(format!("ID: {param_id}"));
+        }
+        ui.label(format!("Name: {}", self.param.name()));
+        ui.label(format!(
+            "Value: {}",
+            self.param
+                .normalized_value_to_string(self.param.unmodulated_normalized_value(), true)
+        ));
+        if self.param.modulated_normalized_value() != self.param.unmodulated_normalized_value() {
+            ui.label(format!("Modulated value: {}", self.param));
+        }
+        ui.label(match self.param.step_count() {
+            Some(step_count) => format!("Steps: {}", step_count + 1),
+            None => String::from("Continuous"),
+        });
+        ui.label(
+            if self.param.flags().contains(ParamFlags::NON_AUTOMATABLE) {
+                "Not automatable"
+            } else {
+                "Automatable"
+            },
+        );
+    }
+
+    /// Changes the parameter as a single automation gesture.
+    fn set_normalized_value(&self, normalized_value: f32) {
+        self.setter.begin_set_parameter(self.param);
+        self.setter
+            .set_parameter(self.param, self.param.preview_plain(normalized_value));
+        self.setter.end_set_parameter(self.param);
+    }
+
+    /// The ID the parameter has in the plugin's parameters struct.
+    fn param_id(&self, params: &dyn Params) -> Option<String> {
+        let param_ptr: ParamPtr = self.param.as_ptr();
+        params
+            .param_map()
+            .into_iter()
+            .find(|(_, ptr, _)| *ptr == param_ptr)
+            .map(|(id, _, _)| id)
+    }
+}
+
+fn midi_learn_ui(ui: &mut Ui, state: &ParamMenuState, param_id: &str) {
+    if state.is_learning(param_id) {
+        if ui.button("Waiting for MIDI CC... (cancel)").clicked() {
+            state.cancel_midi_learn();
+            ui.close_menu();
+        }
+    } else if let Some(cc) = state.midi_cc_for(param_id) {
+        if ui.button(format!("Forget MIDI CC {cc}")).clicked() {
+            state.forget_midi_mapping(param_id);
+            ui.close_menu();
+        }
+    } else if ui.button("MIDI learn").clicked() {
+        state.start_midi_learn(param_id);
+        ui.close_menu();
+    }
+}
+
+#[cfg(test)]
+#[derive(Params)]
+struct TestParams {
+    #[id = "gain"]
+    gain: FloatParam,
+}
+
+#[cfg(test)]
+impl Default for TestParams {
+    fn default() -> Self {
+        Self {
+            gain: FloatParam::new(
+                "Gain",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 10.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(10.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParamEvent {
+    Begin(ParamPtr),
+    Set(ParamPtr, f32),
+    End(ParamPtr),
+}
+
+/// Records the parameter changes instead of sending them to the host.
+#[cfg(test)]
+#[derive(Default)]
+struct RecordingContext {
+    events: Mutex<Vec<ParamEvent>>,
+}
+
+#[cfg(test)]
+impl GuiContext for RecordingContext {
+    fn plugin_api(&self) -> PluginApi {
+        PluginApi::Standalone
+    }
+
+    fn request_resize(&self) -> bool {
+        false
+    }
+
+    unsafe fn raw_begin_set_parameter(&self, param: ParamPtr) {
+        self.events.lock().push(ParamEvent::Begin(param));
+    }
+
+    unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+        self.events.lock().push(ParamEvent::Set(param, normalized));
+    }
+
+    unsafe fn raw_end_set_parameter(&self, param: ParamPtr) {
+        self.events.lock().push(ParamEvent::End(param));
+    }
+
+    fn get_state(&self) -> PluginState {
+        unimplemented!()
+    }
+
+    fn set_state(&self, _state: PluginState) {
+        unimplemented!()
+    }
+}
+
+#[test]
+fn test_midi_learn() {
+    let params = TestParams::default();
+    let context = RecordingContext::default();
+    let setter = ParamSetter::new(&context);
+    let state = ParamMenuState::default();
+    state.process_midi_cc(1, 0.5);
+    assert!(state.take_midi_changes().is_empty());
+
+    // The first CC after starting MIDI learn is mapped on the audio thread, but doesn't change the
+    // parameter. The mapping is saved even if the editor never stores it.
+    state.start_midi_learn("gain");
+    state.process_midi_cc(7, 0.25);
+    assert!(state.take_midi_changes().is_empty());
+    assert_eq!(
+        state.map(|data| data.midi_mappings.get(&7).cloned()),
+        Some(String::from("gain"))
+    );
+    assert!(!state.is_learning("gain"));
+    assert_eq!(state.midi_cc_for("gain"), Some(7));
+
+    // The audio thread leaves the parameters alone, and the editor sets the last value through the
+    // GUI context so the host knows about it
+    state.process_midi_cc(7, 0.5);
+    state.process_midi_cc(7, 0.75);
+    state.process_midi_cc(8, 1.0);
+    assert_eq!(params.gain.value(), 0.0);
+    assert_eq!(params.gain.smoothed.next(), 0.0);
+
+    state.handle_midi_input(&params, &setter);
+    let gain = params.gain.as_ptr();
+    assert_eq!(
+        *context.events.lock(),
+        [
+            ParamEvent::Begin(gain),
+            ParamEvent::Set(gain, 0.75),
+            ParamEvent::End(gain)
+        ]
+    );
+
+    context.events.lock().clear();
+    state.handle_midi_input(&params, &setter);
+    assert!(context.events.lock().is_empty());
+}
+
+#[test]
+fn test_cancel_midi_learn() {
+    let state = ParamMenuState::default();
+
+    state.start_midi_learn("gain");
+    state.cancel_midi_learn();
+    state.process_midi_cc(7, 0.25);
+    assert_eq!(state.midi_cc_for("gain"), None);
+
+    // Cancelling after the audio thread learned a CC, but before the editor stored it, also
+    // removes the mapping from the audio thread's snapshot
+    state.start_midi_learn("gain");
+    state.process_midi_cc(7, 0.25);
+    state.cancel_midi_learn();
+    assert_eq!(state.midi_cc_for("gain"), None);
+    state.process_midi_cc(7, 0.75);
+    assert!(state.take_midi_changes().is_empty());
+}
+
+#[test]
+fn test_midi_relearn() {
+    let state = ParamMenuState::default();
+    state.map_midi_cc(7, String::from("gain"));
+    state.map_midi_cc(8, String::from("gain"));
+    assert_eq!(state.midi_cc_for("gain"), Some(8));
+
+    // The audio thread only follows the new mapping
+    state.process_midi_cc(7, 0.25);
+    assert!(state.take_midi_changes().is_empty());
+    state.process_midi_cc(8, 0.25);
+    assert_eq!(
+        state.take_midi_changes(),
+        vec![(String::from("gain"), 0.25)]
+    );
+
+    // Learning another parameter on the same CC replaces the old mapping
+    state.map_midi_cc(8, String::from("foobar"));
+    assert_eq!(state.midi_cc_for("gain"), None);
+    assert_eq!(state.midi_cc_for("foobar"), Some(8));
+
+    state.forget_midi_mapping("foobar");
+    assert_eq!(state.midi_cc_for("foobar"), None);
+}
+
+#[test]
+fn test_persist_param_menu_state() {
+    let state = ParamMenuState::default();
+    state.map_midi_cc(7, String::from("gain"));
+    state.set_locked("foobar", true);
+
+    let json = state.map(serde_json::to_string).unwrap();
+    let restored = ParamMenuState::default();
+    restored.set(serde_json::from_str(&json).unwrap());
+    assert_eq!(restored.midi_cc_for("gain"), Some(7));
+
+    // The restored mappings are followed on the audio thread right away
+    restored.process_midi_cc(7, 1.0);
+    assert_eq!(
+        restored.take_midi_changes(),
+        vec![(String::from("gain"), 1.0)]
+    );
+    assert!(restored.is_locked("foobar"));
+    assert!(!restored.is_locked("gain"));
+
+    restored.set_locked("foobar", false);
+    assert!(!restored.is_locked("foobar"));
+}
@@ -0,0 +1,843 @@
+//! Presets for any `Params` struct. A [`Preset`] is a snapshot of the plugin's state as returned by
+//! NIH-plug's `GuiContext::get_state()`, stored as a versioned JSON file. The [`PresetManager`]
+//! combines a bank of factory presets compiled into the plugin with the user's own presets from the
+//! user's data directory.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use nih_plug::prelude::{nih_log, ParamSetter};
+#[cfg(test)]
+use nih_plug::prelude::{
+    BoolParam, Enum, EnumParam, FloatParam, FloatRange, IntParam, IntRange, Param, ParamPtr, Params,
+};
+use nih_plug::wrapper::state::{ParamValue, PluginState};
+use nih_plug_egui::egui::{ComboBox, Key, TextEdit, Ui};
+use serde::{Deserialize, Serialize};
+
+use crate::param_menu::ParamMenuState;
+
+/// The version of the preset file format written by this module. Files with a newer version are
+/// rejected instead of being partially loaded.
+pub const PRESET_FORMAT_VERSION: u32 = 1;
+/// The extension used for preset files.
+const PRESET_EXTENSION: &str = "json";
+
+/// A parameter's plain value in a preset. Unlike NIH-plug's `ParamValue` this is tagged with the
+/// parameter's type, so integer values can't be mistaken for floating point values when the preset
+/// is loaded again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresetValue {
+    Float(f32),
+    Int(i32),
+    Bool(bool),
+    /// The stable ID of an `EnumParam`'s variant.
+    Enum(String),
+}
+
+impl From<ParamValue> for PresetValue {
+    fn from(value: ParamValue) -> Self {
+        match value {
+            ParamValue::F32(value) => PresetValue::Float(value),
+            ParamValue::I32(value) => PresetValue::Int(value),
+            ParamValue::Bool(value) => PresetValue::Bool(value),
+            ParamValue::String(id) => PresetValue::Enum(id),
+        }
+    }
+}
+
+impl From<PresetValue> for ParamValue {
+    fn from(value: PresetValue) -> Self {
+        match value {
+            PresetValue::Float(value) => ParamValue::F32(value),
+            PresetValue::Int(value) => ParamValue::I32(value),
+            PresetValue::Bool(value) => ParamValue::Bool(value),
+            PresetValue::Enum(id) => ParamValue::String(id),
+        }
+    }
+}
+
+/// A named snapshot of a plugin's parameters and persisted fields.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    /// The preset file format's version, see [`PRESET_FORMAT_VERSION`].
+    pub format_version: u32,
+    pub name: String,
+    /// The version of the plugin that saved the preset.
+    #[serde(default)]
+    pub plugin_version: String,
+    /// The parameters' plain values, indexed by their parameter IDs.
+    pub params: BTreeMap<String, PresetValue>,
+    /// The persisted fields' serialized values, indexed by their keys.
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+}
+
+/// An error that occurred while loading or saving a preset.
+#[derive(Debug)]
+pub enum PresetError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The preset was saved in a newer format than this version of the plugin supports.
+    UnsupportedVersion(u32),
+    /// There is no user data directory to save presets to.
+    NoPresetDirectory,
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetError::Io(err) => write!(f, "Could not access the preset file: {err}"),
+            PresetError::Json(err) => write!(f, "Invalid preset: {err}"),
+            PresetError::UnsupportedVersion(version) => write!(
+                f,
+                "The preset uses format version {version}, but only versions up to \
+                 {PRESET_FORMAT_VERSION} are supported"
+            ),
+            PresetError::NoPresetDirectory => write!(f, "Could not find a directory for presets"),
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+impl Preset {
+    /// Create a preset from the plugin's current state. The persisted fields in `ignored_fields`
+    /// are left out. Those should be things like the editor's size that should not change when
+    /// loading a preset.
+    pub fn from_state(name: &str, state: PluginState, ignored_fields: &[&str]) -> Self {
+        Self {
+            format_version: PRESET_FORMAT_VERSION,
+            name: name.to_owned(),
+            plugin_version: state.version,
+            params: state
+                .params
+                .into_iter()
+                .map(|(id, value)| (id, PresetValue::from(value)))
+                .collect(),
+            fields: state
+                .fields
+                .into_iter()
+                .filter(|(key, _)| !ignored_fields.contains(&key.as_str()))
+                .collect(),
+        }
+    }
+
+    /// Parse a preset from a JSON string.
+    pub fn from_json(json: &str) -> Result<Self, PresetError> {
+        let preset: Self = serde_json::from_str(json).map_err(PresetError::Json)?;
+        if preset.format_version > PRESET_FORMAT_VERSION {
+            return Err(PresetError::UnsupportedVersion(preset.format_version));
+        }
+
+        Ok(preset)
+    }
+
+    /// Serialize the preset to pretty printed JSON.
+    pub fn to_json(&self) -> Result<String, PresetError> {
+        serde_json::to_string_pretty(self).map_err(PresetError::Json)
+    }
+
+    /// Load a preset from a JSON file.
+    pub fn load(path: &Path) -> Result<Self, PresetError> {
+        Self::from_json(&std::fs::read_to_string(path).map_err(PresetError::Io)?)
+    }
+
+    /// Write the preset to a JSON file, creating the file's directory if needed.
+    pub fn save(&self, path: &Path) -> Result<(), PresetError> {
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(PresetError::Io)?;
+        }
+
+        std::fs::write(path, self.to_json()?).map_err(PresetError::Io)
+    }
+
+    /// Overwrite the values in `state` with the preset's values. Parameters for which `is_locked`
+    /// returns `true` keep their current value, and parameters and fields that aren't part of the
+    /// preset are left alone.
+    pub fn apply_to_state(&self, state: &mut PluginState, is_locked: impl Fn(&str) -> bool) {
+        for (id, value) in &self.params {
+            if !is_locked(id) {
+                state
+                    .params
+                    .insert(id.clone(), ParamValue::from(value.clone()));
+            }
+        }
+        for (key, value) in &self.fields {
+            state.fields.insert(key.clone(), value.clone());
+        }
+    }
+
+    /// Whether `state` still contains the preset's values, ignoring locked parameters.
+    pub fn matches_state(&self, state: &PluginState, is_locked: impl Fn(&str) -> bool) -> bool {
+        let params_match = self.params.iter().all(|(id, value)| {
+            is_locked(id)
+                || state
+                    .params
+                    .get(id)
+                    .map(|current_value| PresetValue::from(current_value.clone()) == *value)
+                    .unwrap_or(false)
+        });
+        let fields_match = self
+            .fields
+            .iter()
+            .all(|(key, value)| state.fields.get(key) == Some(value));
+
+        params_match && fields_match
+    }
+}
+
+/// The factory and user presets for a plugin, and the preset that was loaded last. Factory presets
+/// come first, followed by the user presets sorted by name. This is meant to be stored in the
+/// editor's user state and to be shown with [`preset_bar()`].
+pub struct PresetManager {
+    /// Where user presets are stored, or `None` if there is no user data directory.
+    user_directory: Option<PathBuf>,
+    /// Persisted fields that should not be stored in presets.
+    ignored_fields: &'static [&'static str],
+    factory_presets: Vec<Preset>,
+    user_presets: Vec<Preset>,
+    /// The index of the last loaded or saved preset.
+    current: Option<usize>,
+    /// The last result of [`PresetManager::is_dirty()`] and the change count it was computed at.
+    /// This is cleared whenever a preset is loaded or saved.
+    dirty: Option<(u64, bool)>,
+
+    /// The name being typed in for saving a new preset.
+    name_entry: String,
+    /// The error from the last failed load or save, if any.
+    error: Option<String>,
+}
+
+impl PresetManager {
+    /// Create a preset manager for a plugin. `factory_presets` are the JSON contents of the factory
+    /// presets, usually included with `include_str!()`. User presets are read from
+    /// [`PresetManager::user_directory_for()`]. `ignored_fields` are the keys of the persisted
+    /// fields that should not be part of presets.
+    pub fn new(
+        plugin_name: &str,
+        factory_presets: &[&str],
+        ignored_fields: &'static [&'static str],
+    ) -> Self {
+        Self::with_directory(
+            Self::user_directory_for(plugin_name),
+            factory_presets,
+            ignored_fields,
+        )
+    }
+
+    /// The same as [`PresetManager::new()`], but with an explicit user preset directory.
+    pub fn with_directory(
+        user_directory: Option<PathBuf>,
+        factory_presets: &[&str],
+        ignored_fields: &'static [&'static str],
+    ) -> Self {
+        let factory_presets = factory_presets
+            .iter()
+            .filter_map(|json| match Preset::from_json(json) {
+                Ok(preset) => Some(preset),
+                Err(err) => {
+                    nih_log!("Skipping an invalid factory preset: {err}");
+                    None
+                }
+            })
+            .collect();
+
+        let mut manager = Self {
+            user_directory,
+            ignored_fields,
+            factory_presets,
+            user_presets: Vec::new(),
+            current: None,
+            dirty: None,
+
+            name_entry: String::new(),
+            error: None,
+        };
+        manager.rescan();
+
+        manager
+    }
+
+    /// The directory user presets are stored in for a plugin, inside of the user's data directory.
+    pub fn user_directory_for(plugin_name: &str) -> Option<PathBuf> {
+        dirs::data_dir().map(|directory| directory.join(plugin_name).join("presets"))
+    }
+
+    /// Reread the user presets from the user preset directory. The current preset is kept if it
+    /// still exists.
+    pub fn rescan(&mut self) {
+        let current_name = self.current().map(|preset| preset.name.clone());
+
+        self.user_presets.clear();
+        if let Some(entries) = self
+            .user_directory
+            .as_deref()
+            .and_then(|directory| std::fs::read_dir(directory).ok())
+        {
+            for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+                if path.extension().and_then(|extension| extension.to_str())
+                    != Some(PRESET_EXTENSION)
+                {
+                    continue;
+                }
+
+                match Preset::load(&path) {
+                    Ok(preset) => self.user_presets.push(preset),
+                    Err(err) => nih_log!("Skipping preset '{}': {err}", path.display()),
+                }
+            }
+        }
+        self.user_presets
+            .sort_by_key(|preset| preset.name.to_lowercase());
+
+        self.current = current_name.and_then(|name| self.position(&name));
+        self.dirty = None;
+    }
+
+    /// The number of factory and user presets.
+    pub fn len(&self) -> usize {
+        self.factory_presets.len() + self.user_presets.len()
+    }
+
+    /// Whether there are no presets at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get a preset by its index. Factory presets come before user presets.
+    pub fn get(&self, index: usize) -> Option<&Preset> {
+        match index.checked_sub(self.factory_presets.len()) {
+            Some(user_index) => self.user_presets.get(user_index),
+            None => self.factory_presets.get(index),
+        }
+    }
+
+    /// Whether the preset at this index is a factory preset.
+    pub fn is_factory_preset(&self, index: usize) -> bool {
+        index < self.factory_presets.len()
+    }
+
+    /// The last loaded or saved preset.
+    pub fn current(&self) -> Option<&Preset> {
+        self.current.and_then(|index| self.get(index))
+    }
+
+    /// The index of the last loaded or saved preset.
+    pub fn current_index(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Whether the plugin's state has changed since the current preset was loaded or saved.
+    /// Locked parameters are ignored. This is `false` if no preset has been loaded.
+    pub fn is_dirty(&self, state: &PluginState, locks: Option<&ParamMenuState>) -> bool {
+        match self.current() {
+            Some(preset) => !preset.matches_state(state, |id| is_locked(locks, id)),
+            None => false,
+        }
+    }
+
+    /// The same as [`PresetManager::is_dirty()`], but the plugin's state is only fetched and compared
+    /// when `change_count` differs from the last call, or after a preset has been loaded or saved.
+    /// `change_count` should change whenever the parameters change, like
+    /// [`EditHistory::change_count()`][crate::history::EditHistory::change_count()].
+    pub fn is_dirty_cached(
+        &mut self,
+        change_count: u64,
+        state: impl FnOnce() -> PluginState,
+        locks: Option<&ParamMenuState>,
+    ) -> bool {
+        if self.current.is_none() {
+            return false;
+        }
+
+        match self.dirty {
+            Some((dirty_change_count, dirty)) if dirty_change_count == change_count => dirty,
+            _ => {
+                let dirty = self.is_dirty(&state(), locks);
+                self.dirty = Some((change_count, dirty));
+
+                dirty
+            }
+        }
+    }
+
+    /// Load the preset at an index through the GUI context. Locked parameters keep their value.
+    pub fn load(&mut self, index: usize, setter: &ParamSetter, locks: Option<&ParamMenuState>) {
+        if let Some(preset) = self.get(index) {
+            let mut state = setter.raw_context.get_state();
+            preset.apply_to_state(&mut state, |id| is_locked(locks, id));
+            setter.raw_context.set_state(state);
+
+            self.current = Some(index);
+            self.dirty = None;
+            self.error = None;
+        }
+    }
+
+    /// Load the next preset, wrapping around to the first preset at the end.
+    pub fn load_next(&mut self, setter: &ParamSetter, locks: Option<&ParamMenuState>) {
+        if !self.is_empty() {
+            let index = self
+                .current
+                .map(|index| (index + 1) % self.len())
+                .unwrap_or(0);
+            self.load(index, setter, locks);
+        }
+    }
+
+    /// Load the previous preset, wrapping around to the last preset at the start.
+    pub fn load_previous(&mut self, setter: &ParamSetter, locks: Option<&ParamMenuState>) {
+        if !self.is_empty() {
+            let index = self
+                .current
+                .map(|index| (index + self.len() - 1) % self.len())
+                .unwrap_or(self.len() - 1);
+            self.load(index, setter, locks);
+        }
+    }
+
+    /// Save the plugin's current state as a user preset. A user preset with the same name is
+    /// overwritten.
+    pub fn save(&mut self, name: &str, setter: &ParamSetter) -> Result<(), PresetError> {
+        self.save_state(name, setter.raw_context.get_state())
+    }
+
+    /// Save a plugin state as a user preset and make it the current preset.
+    pub fn save_state(&mut self, name: &str, state: PluginState) -> Result<(), PresetError> {
+        let directory = self
+            .user_directory
+            .as_deref()
+            .ok_or(PresetError::NoPresetDirectory)?;
+
+        let preset = Preset::from_state(name, state, self.ignored_fields);
+        preset.save(&directory.join(format!("{}.{PRESET_EXTENSION}", file_name_for(name))))?;
+
+        self.rescan();
+        self.current = self.position(name);
+
+        Ok(())
+    }
+
+    /// The index of the preset with this name. User presets take precedence over factory presets
+    /// with the same name, since that's what gets saved over.
+    fn position(&self, name: &str) -> Option<usize> {
+        self.user_presets
+            .iter()
+            .position(|preset| preset.name == name)
+            .map(|index| index + self.factory_presets.len())
+            .or_else(|| {
+                self.factory_presets
+                    .iter()
+                    .position(|preset| preset.name == name)
+            })
+    }
+}
+
+fn is_locked(locks: Option<&ParamMenuState>, param_id: &str) -> bool {
+    locks.is_some_and(|locks| locks.is_locked(param_id))
+}
+
+/// Turn a preset name into something that can safely be used as a file name.
+fn file_name_for(name: &str) -> String {
+    let file_name: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if file_name.is_empty() {
+        String::from("Untitled")
+    } else {
+        file_name
+    }
+}
+
+/// Previous and next buttons, a drop-down for browsing all presets, and a text field for saving
+/// the current state as a new user preset. The current preset's name is marked with an asterisk
+/// when the parameters have been changed since it was loaded. That's only recomputed when
+/// `change_count` changes, see [`PresetManager::is_dirty_cached()`]. `locks` are the parameters
+/// that should keep their value when loading a preset.
+pub fn preset_bar(
+    ui: &mut Ui,
+    presets: &mut PresetManager,
+    setter: &ParamSetter,
+    change_count: u64,
+    locks: Option<&ParamMenuState>,
+) {
+    let dirty = presets.is_dirty_cached(change_count, || setter.raw_context.get_state(), locks);
+    let current_name = match presets.current() {
+        Some(preset) if dirty => format!("{} *", preset.name),
+        Some(preset) => preset.name.clone(),
+        None => String::from("No preset"),
+    };
+
+    let mut load_index = None;
+    let mut load_previous = false;
+    let mut load_next = false;
+    let mut save = false;
+    ui.horizontal(|ui| {
+        ui.label("Preset");
+        load_previous = ui.button("<").on_hover_text("Previous preset").clicked();
+
+        ComboBox::from_id_source("preset_browser")
+            .selected_text(current_name)
+            .show_ui(ui, |ui| {
+                for index in 0..presets.len() {
+                    if index == 0 && presets.is_factory_preset(index) {
+                        ui.label("Factory");
+                    } else if !presets.is_factory_preset(index)
+                        && (index == 0 || presets.is_factory_preset(index - 1))
+                    {
+                        ui.separator();
+                        ui.label("User");
+                    }
+
+                    let preset = presets.get(index).unwrap();
+                    if ui
+                        .selectable_label(presets.current_index() == Some(index), &preset.name)
+                        .clicked()
+                    {
+                        load_index = Some(index);
+                    }
+                }
+            });
+
+        load_next = ui.button(">").on_hover_text("Next preset").clicked();
+
+        let name_response =
+            ui.add(TextEdit::singleline(&mut presets.name_entry).hint_text("Preset name"));
+        let enter_pressed = name_response.lost_focus() && ui.input().key_pressed(Key::Enter);
+        save =
+            (ui.button("Save").clicked() || enter_pressed) && !presets.name_entry.trim().is_empty();
+    });
+
+    if let Some(index) = load_index {
+        presets.load(index, setter, locks);
+    } else if load_previous {
+        presets.load_previous(setter, locks);
+    } else if load_next {
+        presets.load_next(setter, locks);
+    }
+
+    if save {
+        let name = presets.name_entry.trim().to_owned();
+        match presets.save(&name, setter) {
+            Ok(()) => {
+                presets.name_entry.clear();
+                presets.error = None;
+            }
+            Err(err) => presets.error = Some(err.to_string()),
+        }
+    }
+
+    if let Some(error) = &presets.error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+}
+
+/// A state with one parameter of every type NIH-plug serializes, and a persisted field. This is
+/// parsed from JSON the same way NIH-plug reads plugin state.
+#[cfg(test)]
+fn test_state() -> PluginState {
+    serde_json::from_str(
+        r#"{
+            "version": "0.1.0",
+            "params": {
+                "gain": 0.5,
+                "foobar": 3,
+                "wave_style": "sawtooth",
+                "invert": true
+            },
+            "fields": {
+                "wave-table": "[0.0,0.5,1.0]",
+                "editor-state": "{\"size\":[600,600],\"scale\":1.0}"
+            }
+        }"#,
+    )
+    .unwrap()
+}
+
+#[cfg(test)]
+fn test_directory(name: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("nih_widgets_presets_{}_{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+
+    directory
+}
+
+#[cfg(test)]
+#[derive(Enum, Debug, PartialEq, Clone, Copy)]
+enum Wave {
+    Sine,
+    Sawtooth,
+    Square,
+}
+
+/// Real parameters, so the round trip tests use the same values NIH-plug puts in the state.
+#[cfg(test)]
+#[derive(Params)]
+struct TestParams {
+    #[id = "gain"]
+    gain: FloatParam,
+    #[id = "foobar"]
+    foobar: IntParam,
+    #[id = "wave"]
+    wave: EnumParam<Wave>,
+    #[id = "invert"]
+    invert: BoolParam,
+    #[persist = "wave-table"]
+    wave_table: std::sync::RwLock<Vec<f32>>,
+}
+
+#[cfg(test)]
+impl TestParams {
+    fn new(gain: f32, foobar: i32, wave: Wave, invert: bool, wave_table: &[f32]) -> Self {
+        Self {
+            gain: FloatParam::new("Gain", gain, FloatRange::Linear { min: 0.0, max: 2.0 }),
+            foobar: IntParam::new("Foobar", foobar, IntRange::Linear { min: 0, max: 10 }),
+            wave: EnumParam::new("Wave", wave),
+            invert: BoolParam::new("Invert", invert),
+            wave_table: std::sync::RwLock::new(wave_table.to_vec()),
+        }
+    }
+
+    /// The plugin state NIH-plug would send to [`PresetManager::save()`] for these parameters.
+    fn state(&self) -> PluginState {
+        let params = self
+            .param_map()
+            .into_iter()
+            .map(|(id, param_ptr, _)| {
+                // SAFETY: The pointers point to the parameters in `self`
+                let value = unsafe {
+                    match param_ptr {
+                        ParamPtr::FloatParam(param) => {
+                            ParamValue::F32((*param).unmodulated_plain_value())
+                        }
+                        ParamPtr::IntParam(param) => {
+                            ParamValue::I32((*param).unmodulated_plain_value())
+                        }
+                        ParamPtr::BoolParam(param) => {
+                            ParamValue::Bool((*param).unmodulated_plain_value())
+                        }
+                        ParamPtr::EnumParam(param) => {
+                            ParamValue::I32((*param).unmodulated_plain_value())
+                        }
+                    }
+                };
+
+                (id, value)
+            })
+            .collect();
+
+        PluginState {
+            version: String::from("0.1.0"),
+            params,
+            fields: self.serialize_fields(),
+        }
+    }
+}
+
+#[test]
+fn test_real_params_round_trip() {
+    let params = TestParams::new(1.5, 7, Wave::Sawtooth, true, &[0.0, 0.5, 1.0]);
+    let directory = test_directory("real_params");
+    let mut presets = PresetManager::with_directory(Some(directory.clone()), &[], &[]);
+    presets.save_state("Real", params.state()).unwrap();
+
+    // The preset is read back from disk and loaded over parameters with different values
+    let presets = PresetManager::with_directory(Some(directory.clone()), &[], &[]);
+    assert_eq!(presets.len(), 1);
+    let loaded_params = TestParams::new(0.5, 2, Wave::Sine, false, &[]);
+    let mut state = loaded_params.state();
+    presets
+        .get(0)
+        .unwrap()
+        .apply_to_state(&mut state, |_| false);
+    loaded_params.deserialize_fields(&state.fields);
+
+    assert!(matches!(state.params["gain"], ParamValue::F32(value) if value == params.gain.value()));
+    assert!(
+        matches!(state.params["foobar"], ParamValue::I32(value) if value == params.foobar.value())
+    );
+    assert!(
+        matches!(state.params["wave"], ParamValue::I32(value) if value == params.wave.value() as i32)
+    );
+    assert!(
+        matches!(state.params["invert"], ParamValue::Bool(value) if value == params.invert.value())
+    );
+    assert_eq!(
+        *loaded_params.wave_table.read().unwrap(),
+        *params.wave_table.read().unwrap()
+    );
+    assert!(presets.get(0).unwrap().matches_state(&state, |_| false));
+    assert!(!presets
+        .get(0)
+        .unwrap()
+        .matches_state(&loaded_params.state(), |_| false));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_dirty_cache() {
+    let directory = test_directory("dirty_cache");
+    let mut presets = PresetManager::with_directory(Some(directory.clone()), &[], &[]);
+    let fetches = std::cell::Cell::new(0);
+    let state = || {
+        fetches.set(fetches.get() + 1);
+        test_state()
+    };
+
+    // Nothing is fetched until a preset has been loaded or saved
+    assert!(!presets.is_dirty_cached(0, state, None));
+    assert_eq!(fetches.get(), 0);
+
+    presets.save_state("Cached", test_state()).unwrap();
+    assert!(!presets.is_dirty_cached(0, state, None));
+    assert!(!presets.is_dirty_cached(0, state, None));
+    assert_eq!(fetches.get(), 1);
+
+    let mut changed_state = test_state();
+    changed_state
+        .params
+        .insert(String::from("invert"), ParamValue::Bool(false));
+    assert!(presets.is_dirty_cached(1, || changed_state.clone(), None));
+    assert!(presets.is_dirty_cached(1, state, None));
+    assert_eq!(fetches.get(), 1);
+
+    // Saving invalidates the cache even if the parameters didn't change
+    presets.save_state("Cached", changed_state.clone()).unwrap();
+    assert!(presets.is_dirty_cached(1, state, None));
+    assert_eq!(fetches.get(), 2);
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_preset_round_trip() {
+    let mut state = test_state();
+    // `ParamValue` is untagged, so integers are set explicitly instead of relying on how they're
+    // parsed from JSON. Enums without stable IDs are stored as variant indices.
+    state
+        .params
+        .insert(String::from("foobar"), ParamValue::I32(3));
+    state
+        .params
+        .insert(String::from("wave_index"), ParamValue::I32(1));
+
+    let preset = Preset::from_state("Round trip", state, &["editor-state"]);
+    assert_eq!(preset.params["gain"], PresetValue::Float(0.5));
+    assert_eq!(preset.params["foobar"], PresetValue::Int(3));
+    assert_eq!(preset.params["wave_index"], PresetValue::Int(1));
+    assert_eq!(
+        preset.params["wave_style"],
+        PresetValue::Enum(String::from("sawtooth"))
+    );
+    assert_eq!(preset.params["invert"], PresetValue::Bool(true));
+    assert_eq!(preset.fields["wave-table"], "[0.0,0.5,1.0]");
+    assert!(!preset.fields.contains_key("editor-state"));
+
+    let directory = test_directory("round_trip");
+    let path = directory.join("Round trip.json");
+    preset.save(&path).unwrap();
+    let loaded = Preset::load(&path).unwrap();
+    assert_eq!(loaded, preset);
+
+    // Loading the preset again should give the same values NIH-plug would have serialized
+    let mut new_state = test_state();
+    new_state.params.clear();
+    loaded.apply_to_state(&mut new_state, |_| false);
+    assert!(matches!(new_state.params["gain"], ParamValue::F32(value) if value == 0.5));
+    assert!(matches!(new_state.params["foobar"], ParamValue::I32(3)));
+    assert!(matches!(new_state.params["wave_index"], ParamValue::I32(1)));
+    assert!(matches!(&new_state.params["wave_style"], ParamValue::String(id) if id == "sawtooth"));
+    assert!(matches!(new_state.params["invert"], ParamValue::Bool(true)));
+    assert_eq!(new_state.fields["wave-table"], "[0.0,0.5,1.0]");
+    assert!(loaded.matches_state(&new_state, |_| false));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_locked_params() {
+    let preset = Preset::from_state("Locked", test_state(), &[]);
+
+    let mut state = test_state();
+    state
+        .params
+        .insert(String::from("gain"), ParamValue::F32(0.25));
+    state
+        .params
+        .insert(String::from("invert"), ParamValue::Bool(false));
+    assert!(!preset.matches_state(&state, |_| false));
+
+    preset.apply_to_state(&mut state, |id| id == "gain");
+    assert!(matches!(state.params["gain"], ParamValue::F32(value) if value == 0.25));
+    assert!(matches!(state.params["invert"], ParamValue::Bool(true)));
+    assert!(preset.matches_state(&state, |id| id == "gain"));
+    assert!(!preset.matches_state(&state, |_| false));
+}
+
+#[test]
+fn test_preset_versions() {
+    let preset = Preset::from_state("Versioned", test_state(), &[]);
+    assert_eq!(preset.format_version, PRESET_FORMAT_VERSION);
+
+    let mut future_preset = preset;
+    future_preset.format_version = PRESET_FORMAT_VERSION + 1;
+    assert!(matches!(
+        Preset::from_json(&future_preset.to_json().unwrap()),
+        Err(PresetError::UnsupportedVersion(_))
+    ));
+    assert!(matches!(Preset::from_json("{}"), Err(PresetError::Json(_))));
+}
+
+#[test]
+fn test_preset_manager() {
+    let factory_preset = Preset::from_state("Factory", test_state(), &[])
+        .to_json()
+        .unwrap();
+    let directory = test_directory("manager");
+    let mut presets = PresetManager::with_directory(
+        Some(directory.clone()),
+        &[&factory_preset, "not a preset"],
+        &["editor-state"],
+    );
+    assert_eq!(presets.len(), 1);
+    assert!(presets.is_factory_preset(0));
+    assert!(presets.current().is_none());
+
+    presets.save_state("b/user", test_state()).unwrap();
+    presets.save_state("A user", test_state()).unwrap();
+    assert!(directory.join("b_user.json").exists());
+    assert_eq!(presets.len(), 3);
+    assert_eq!(presets.get(1).unwrap().name, "A user");
+    assert_eq!(presets.get(2).unwrap().name, "b/user");
+    assert!(presets.get(3).is_none());
+    assert_eq!(presets.current_index(), Some(1));
+    assert!(!presets.get(1).unwrap().fields.contains_key("editor-state"));
+
+    assert!(!presets.is_dirty(&test_state(), None));
+    let mut changed_state = test_state();
+    changed_state
+        .params
+        .insert(String::from("invert"), ParamValue::Bool(false));
+    assert!(presets.is_dirty(&changed_state, None));
+
+    // Saving over an existing preset doesn't add a new one
+    presets.save_state("A user", changed_state.clone()).unwrap();
+    assert_eq!(presets.len(), 3);
+    assert!(!presets.is_dirty(&changed_state, None));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
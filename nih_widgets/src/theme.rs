@@ -0,0 +1,413 @@
+//! Color themes for egui based editors. A [`Theme`] is a Catppuccin style palette that gets mapped
+//! onto `egui::Visuals`, so regular egui widgets and the widgets from this crate all pick it up.
+//! Next to the built-in light and dark themes, themes can be loaded from JSON or TOML files at
+//! runtime.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use nih_plug::prelude::nih_log;
+use nih_plug_egui::egui::{self, Color32, Stroke, TextEdit, Ui};
+use serde::{Deserialize, Serialize};
+
+/// A color in a theme file, written as a `#rrggbb` hex string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ThemeColor(pub Color32);
+
+impl ThemeColor {
+    pub const fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Self(Color32::from_rgb(r, g, b))
+    }
+}
+
+impl TryFrom<String> for ThemeColor {
+    type Error = String;
+
+    fn try_from(string: String) -> Result<Self, Self::Error> {
+        let hex = string
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6 && hex.is_ascii())
+            .ok_or_else(|| format!("'{string}' is not a '#rrggbb' color"))?;
+        let component = |idx: usize| {
+            u8::from_str_radix(&hex[idx..idx + 2], 16)
+                .map_err(|_| format!("'{string}' is not a '#rrggbb' color"))
+        };
+
+        Ok(Self::from_rgb(component(0)?, component(2)?, component(4)?))
+    }
+}
+
+impl From<ThemeColor> for String {
+    fn from(color: ThemeColor) -> Self {
+        let [r, g, b, _] = color.0.to_array();
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+impl From<ThemeColor> for Color32 {
+    fn from(color: ThemeColor) -> Self {
+        color.0
+    }
+}
+
+/// The colors for a theme variant. The palette follows Catppuccin's naming. The accent colors are
+/// used for selections, links, warnings and errors, and the text, overlay, surface and base colors
+/// go from the foreground to the background.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    /// Whether this is a dark theme. egui uses this for things like shadows.
+    pub dark_mode: bool,
+
+    pub rosewater: ThemeColor,
+    pub flamingo: ThemeColor,
+    pub pink: ThemeColor,
+    pub mauve: ThemeColor,
+    pub red: ThemeColor,
+    pub maroon: ThemeColor,
+    pub peach: ThemeColor,
+    pub yellow: ThemeColor,
+    pub green: ThemeColor,
+    pub teal: ThemeColor,
+    pub sky: ThemeColor,
+    pub sapphire: ThemeColor,
+    pub blue: ThemeColor,
+    pub lavender: ThemeColor,
+    pub text: ThemeColor,
+    pub subtext1: ThemeColor,
+    pub subtext0: ThemeColor,
+    pub overlay2: ThemeColor,
+    pub overlay1: ThemeColor,
+    pub overlay0: ThemeColor,
+    pub surface2: ThemeColor,
+    pub surface1: ThemeColor,
+    pub surface0: ThemeColor,
+    pub base: ThemeColor,
+    pub mantle: ThemeColor,
+    pub crust: ThemeColor,
+}
+
+/// The themes that ship with the plugins, and which one is currently selected. This is stored in
+/// the plugin's state through a `#[persist]` field, so a theme loaded from a file is loaded again
+/// from the same path when the plugin is restored.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemeSelection {
+    Light,
+    #[default]
+    Dark,
+    File(PathBuf),
+}
+
+/// An error that occurred while loading a theme file.
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    /// The file doesn't have a `.json` or `.toml` extension.
+    UnknownFormat(PathBuf),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::Io(err) => write!(f, "Could not read the theme file: {err}"),
+            ThemeError::Json(err) => write!(f, "Invalid JSON theme: {err}"),
+            ThemeError::Toml(err) => write!(f, "Invalid TOML theme: {err}"),
+            ThemeError::UnknownFormat(path) => {
+                write!(f, "'{}' is not a .json or .toml theme file", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+impl Theme {
+    /// The built-in light theme, based on Catppuccin Latte.
+    pub fn light() -> Self {
+        Self {
+            name: String::from("Light"),
+            dark_mode: false,
+
+            rosewater: ThemeColor::from_rgb(220, 138, 120),
+            flamingo: ThemeColor::from_rgb(221, 120, 120),
+            pink: ThemeColor::from_rgb(234, 118, 203),
+            mauve: ThemeColor::from_rgb(136, 57, 239),
+            red: ThemeColor::from_rgb(210, 15, 57),
+            maroon: ThemeColor::from_rgb(230, 69, 83),
+            peach: ThemeColor::from_rgb(254, 100, 11),
+            yellow: ThemeColor::from_rgb(223, 142, 29),
+            green: ThemeColor::from_rgb(64, 160, 43),
+            teal: ThemeColor::from_rgb(23, 146, 153),
+            sky: ThemeColor::from_rgb(4, 165, 229),
+            sapphire: ThemeColor::from_rgb(32, 159, 181),
+            blue: ThemeColor::from_rgb(30, 102, 245),
+            lavender: ThemeColor::from_rgb(114, 135, 253),
+            text: ThemeColor::from_rgb(76, 79, 105),
+            subtext1: ThemeColor::from_rgb(92, 95, 119),
+            subtext0: ThemeColor::from_rgb(108, 111, 133),
+            overlay2: ThemeColor::from_rgb(124, 127, 147),
+            overlay1: ThemeColor::from_rgb(140, 143, 161),
+            overlay0: ThemeColor::from_rgb(156, 160, 176),
+            surface2: ThemeColor::from_rgb(172, 176, 190),
+            surface1: ThemeColor::from_rgb(188, 192, 204),
+            surface0: ThemeColor::from_rgb(204, 208, 218),
+            base: ThemeColor::from_rgb(239, 241, 245),
+            mantle: ThemeColor::from_rgb(230, 233, 239),
+            crust: ThemeColor::from_rgb(220, 224, 232),
+        }
+    }
+
+    /// The built-in dark theme, based on Catppuccin Mocha.
+    pub fn dark() -> Self {
+        Self {
+            name: String::from("Dark"),
+            dark_mode: true,
+
+            rosewater: ThemeColor::from_rgb(245, 224, 220),
+            flamingo: ThemeColor::from_rgb(242, 205, 205),
+            pink: ThemeColor::from_rgb(245, 194, 231),
+            mauve: ThemeColor::from_rgb(203, 166, 247),
+            red: ThemeColor::from_rgb(243, 139, 168),
+            maroon: ThemeColor::from_rgb(235, 160, 172),
+            peach: ThemeColor::from_rgb(250, 179, 135),
+            yellow: ThemeColor::from_rgb(249, 226, 175),
+            green: ThemeColor::from_rgb(166, 227, 161),
+            teal: ThemeColor::from_rgb(148, 226, 213),
+            sky: ThemeColor::from_rgb(137, 220, 235),
+            sapphire: ThemeColor::from_rgb(116, 199, 236),
+            blue: ThemeColor::from_rgb(137, 180, 250),
+            lavender: ThemeColor::from_rgb(180, 190, 254),
+            text: ThemeColor::from_rgb(205, 214, 244),
+            subtext1: ThemeColor::from_rgb(186, 194, 222),
+            subtext0: ThemeColor::from_rgb(166, 173, 200),
+            overlay2: ThemeColor::from_rgb(147, 153, 178),
+            overlay1: ThemeColor::from_rgb(127, 132, 156),
+            overlay0: ThemeColor::from_rgb(108, 112, 134),
+            surface2: ThemeColor::from_rgb(88, 91, 112),
+            surface1: ThemeColor::from_rgb(69, 71, 90),
+            surface0: ThemeColor::from_rgb(49, 50, 68),
+            base: ThemeColor::from_rgb(30, 30, 46),
+            mantle: ThemeColor::from_rgb(24, 24, 37),
+            crust: ThemeColor::from_rgb(17, 17, 27),
+        }
+    }
+
+    /// Parse a theme from a JSON string.
+    pub fn from_json(json: &str) -> Result<Self, ThemeError> {
+        serde_json::from_str(json).map_err(ThemeError::Json)
+    }
+
+    /// Parse a theme from a TOML string.
+    pub fn from_toml(toml: &str) -> Result<Self, ThemeError> {
+        toml::from_str(toml).map_err(ThemeError::Toml)
+    }
+
+    /// Load a theme from a `.json` or `.toml` file.
+    pub fn load(path: &Path) -> Result<Self, ThemeError> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("json") => {
+                Self::from_json(&std::fs::read_to_string(path).map_err(ThemeError::Io)?)
+            }
+            Some("toml") => {
+                Self::from_toml(&std::fs::read_to_string(path).map_err(ThemeError::Io)?)
+            }
+            _ => Err(ThemeError::UnknownFormat(path.to_owned())),
+        }
+    }
+
+    /// Map the palette onto egui's visuals. Everything not covered by the palette, like rounding
+    /// and stroke widths, is taken from egui's default light or dark visuals.
+    pub fn visuals(&self) -> egui::Visuals {
+        let old = if self.dark_mode {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
+
+        egui::Visuals {
+            dark_mode: self.dark_mode,
+            override_text_color: Some(self.text.into()),
+            widgets: egui::style::Widgets {
+                noninteractive: self.widget_visuals(old.widgets.noninteractive, self.base),
+                inactive: self.widget_visuals(old.widgets.inactive, self.surface0),
+                hovered: self.widget_visuals(old.widgets.hovered, self.surface2),
+                active: self.widget_visuals(old.widgets.active, self.surface1),
+                open: self.widget_visuals(old.widgets.open, self.surface0),
+            },
+            selection: egui::style::Selection {
+                bg_fill: Color32::from(self.blue).linear_multiply(if self.dark_mode {
+                    0.4
+                } else {
+                    0.2
+                }),
+                stroke: Stroke {
+                    color: self.blue.into(),
+                    ..old.selection.stroke
+                },
+            },
+            hyperlink_color: self.rosewater.into(),
+            faint_bg_color: self.surface0.into(),
+            extreme_bg_color: self.crust.into(),
+            code_bg_color: self.mantle.into(),
+            warn_fg_color: self.peach.into(),
+            error_fg_color: self.maroon.into(),
+            window_shadow: egui::epaint::Shadow {
+                color: self.base.into(),
+                ..old.window_shadow
+            },
+            popup_shadow: egui::epaint::Shadow {
+                color: self.base.into(),
+                ..old.popup_shadow
+            },
+            window_fill: self.base.into(),
+            window_stroke: Stroke {
+                color: self.overlay1.into(),
+                ..old.window_stroke
+            },
+            panel_fill: self.base.into(),
+            ..old
+        }
+    }
+
+    fn widget_visuals(
+        &self,
+        old: egui::style::WidgetVisuals,
+        bg_fill: ThemeColor,
+    ) -> egui::style::WidgetVisuals {
+        egui::style::WidgetVisuals {
+            bg_fill: bg_fill.into(),
+            bg_stroke: Stroke {
+                color: self.overlay1.into(),
+                ..old.bg_stroke
+            },
+            fg_stroke: Stroke {
+                color: self.text.into(),
+                ..old.fg_stroke
+            },
+            ..old
+        }
+    }
+
+    /// Apply the theme to a [`Context`](egui::Context).
+    pub fn apply(&self, ctx: &egui::Context) {
+        ctx.set_visuals(self.visuals());
+    }
+}
+
+impl ThemeSelection {
+    /// Get the selected theme. This loads the theme file for [`ThemeSelection::File`].
+    pub fn load(&self) -> Result<Theme, ThemeError> {
+        match self {
+            ThemeSelection::Light => Ok(Theme::light()),
+            ThemeSelection::Dark => Ok(Theme::dark()),
+            ThemeSelection::File(path) => Theme::load(path),
+        }
+    }
+}
+
+/// Apply the theme selected in a plugin's persisted state to a [`Context`](egui::Context). If the
+/// selected theme file can no longer be loaded, the default theme is used instead. This is meant to
+/// be called from the editor's build function.
+pub fn apply_selected_theme(ctx: &egui::Context, selection: &RwLock<ThemeSelection>) {
+    let selection = selection.read().unwrap();
+    match selection.load() {
+        Ok(theme) => theme.apply(ctx),
+        Err(err) => {
+            nih_log!("Could not load the selected theme, using the default theme instead: {err}");
+            Theme::dark().apply(ctx);
+        }
+    }
+}
+
+/// The editor state [`theme_picker()`] needs to keep around between frames.
+#[derive(Debug, Default)]
+pub struct ThemePickerState {
+    /// The theme file path being typed in.
+    path_entry: String,
+    /// The error from the last theme file that failed to load, if any.
+    error: Option<String>,
+}
+
+/// Buttons for switching between the built-in themes, and a text field for loading a theme file.
+/// The new theme is applied to the UI's context right away and stored in `selection`.
+pub fn theme_picker(ui: &mut Ui, selection: &RwLock<ThemeSelection>, state: &mut ThemePickerState) {
+    let current_selection = selection.read().unwrap().clone();
+    let mut new_selection = None;
+
+    ui.horizontal(|ui| {
+        ui.label("Theme");
+        if ui
+            .selectable_label(current_selection == ThemeSelection::Light, "Light")
+            .clicked()
+        {
+            new_selection = Some(ThemeSelection::Light);
+        }
+        if ui
+            .selectable_label(current_selection == ThemeSelection::Dark, "Dark")
+            .clicked()
+        {
+            new_selection = Some(ThemeSelection::Dark);
+        }
+
+        let path_response = ui.add(
+            TextEdit::singleline(&mut state.path_entry).hint_text("Path to a .json or .toml theme"),
+        );
+        let enter_pressed = path_response.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+        if (ui.button("Load").clicked() || enter_pressed) && !state.path_entry.is_empty() {
+            new_selection = Some(ThemeSelection::File(PathBuf::from(state.path_entry.trim())));
+        }
+    });
+
+    if let Some(new_selection) = new_selection {
+        match new_selection.load() {
+            Ok(theme) => {
+                theme.apply(ui.ctx());
+                *selection.write().unwrap() = new_selection;
+                state.error = None;
+            }
+            Err(err) => state.error = Some(err.to_string()),
+        }
+    }
+
+    if let Some(error) = &state.error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+}
+
+#[test]
+fn test_builtin_themes_round_trip() {
+    for theme in [Theme::light(), Theme::dark()] {
+        let json = serde_json::to_string(&theme).unwrap();
+        assert_eq!(Theme::from_json(&json).unwrap(), theme);
+
+        let toml = toml::to_string(&theme).unwrap();
+        assert_eq!(Theme::from_toml(&toml).unwrap(), theme);
+    }
+}
+
+#[test]
+fn test_theme_colors() {
+    let color = ThemeColor::try_from(String::from("#1e66f5")).unwrap();
+    assert_eq!(color, ThemeColor::from_rgb(30, 102, 245));
+    assert_eq!(String::from(color), "#1e66f5");
+
+    assert!(ThemeColor::try_from(String::from("1e66f5")).is_err());
+    assert!(ThemeColor::try_from(String::from("#1e66f")).is_err());
+    assert!(ThemeColor::try_from(String::from("#1e66zz")).is_err());
+}
+
+#[test]
+fn test_unknown_theme_format() {
+    assert!(matches!(
+        Theme::load(Path::new("theme.yaml")),
+        Err(ThemeError::UnknownFormat(_))
+    ));
+}
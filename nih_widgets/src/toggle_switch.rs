@@ -0,0 +1,115 @@
+//! An on/off switch for a NIH-plug `BoolParam`.
+
+use nih_plug::prelude::{BoolParam, Param, ParamSetter};
+use nih_plug_egui::egui::{
+    lerp, pos2, vec2, Response, Sense, Ui, Vec2, Widget, WidgetInfo, WidgetType,
+};
+
+/// Draws a [`ToggleSwitch`]. The switch itself only handles input and talking to the host, so
+/// plugins can implement this to give the switch their own look.
+pub trait ToggleSkin {
+    /// The switch's size when the widget doesn't override it with [`ToggleSwitch::with_size()`].
+    fn default_size(&self, ui: &Ui) -> Vec2 {
+        ui.spacing().interact_size.y * vec2(2.0, 1.0)
+    }
+
+    /// Draw the switch in `response.rect`. `how_on` is 0.0 when the switch is off and 1.0 when it's
+    /// on.
+    fn paint(&self, ui: &Ui, response: &Response, how_on: f32);
+}
+
+/// An iOS-style toggle switch that follows the active `egui::Visuals`:
+///
+/// ``` text
+///      _____________
+///     /       /.....\
+///    |       |.......|
+///     \_______\_____/
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultToggleSkin;
+
+impl ToggleSkin for DefaultToggleSkin {
+    fn paint(&self, ui: &Ui, response: &Response, how_on: f32) {
+        let visuals = ui.style().interact_selectable(response, how_on > 0.5);
+        // All coordinates are in absolute screen coordinates so we use `rect` to place the elements.
+        let rect = response.rect.expand(visuals.expansion);
+        let radius = 0.5 * rect.height();
+        ui.painter()
+            .rect(rect, radius, visuals.bg_fill, visuals.bg_stroke);
+
+        // Paint the circle, moving it from left to right with `how_on`
+        let circle_x = lerp((rect.left() + radius)..=(rect.right() - radius), how_on);
+        let center = pos2(circle_x, rect.center().y);
+        ui.painter()
+            .circle(center, 0.75 * radius, visuals.bg_fill, visuals.fg_stroke);
+    }
+}
+
+/// A toggle switch bound to a [`BoolParam`]. Clicking the switch, or pressing space or enter while
+/// it has keyboard focus, flips the parameter as a single automation gesture. The switch is drawn
+/// from the parameter's current value every frame, so automation and changes made by the host are
+/// reflected immediately, with a short animation between the two states. Drawing the switch is
+/// left to a [`ToggleSkin`].
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct ToggleSwitch<'a> {
+    param: &'a BoolParam,
+    setter: &'a ParamSetter<'a>,
+    skin: &'a dyn ToggleSkin,
+
+    size: Option<Vec2>,
+}
+
+impl<'a> ToggleSwitch<'a> {
+    /// Create a new toggle switch for a parameter. Use the other methods to modify the switch
+    /// before passing it to [`Ui::add()`].
+    pub fn for_param(param: &'a BoolParam, setter: &'a ParamSetter<'a>) -> Self {
+        Self {
+            param,
+            setter,
+            skin: &DefaultToggleSkin,
+
+            size: None,
+        }
+    }
+
+    /// Draw the switch with a custom skin instead of [`DefaultToggleSkin`].
+    pub fn with_skin(mut self, skin: &'a dyn ToggleSkin) -> Self {
+        self.skin = skin;
+        self
+    }
+
+    /// Set a custom size for the switch.
+    pub fn with_size(mut self, size: Vec2) -> Self {
+        self.size = Some(size);
+        self
+    }
+}
+
+impl Widget for ToggleSwitch<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let size = self.size.unwrap_or_else(|| self.skin.default_size(ui));
+        let (rect, mut response) = ui.allocate_exact_size(size, Sense::click());
+
+        if response.clicked() {
+            self.setter.begin_set_parameter(self.param);
+            self.setter
+                .set_parameter(self.param, !self.param.unmodulated_plain_value());
+            self.setter.end_set_parameter(self.param);
+            response.mark_changed();
+        }
+
+        // This is read after handling the click so the new value is shown right away
+        let on = self.param.modulated_plain_value();
+        response.widget_info(|| WidgetInfo::selected(WidgetType::Checkbox, on, self.param.name()));
+
+        if ui.is_rect_visible(rect) {
+            // egui keeps track of the last value for this ID and returns an animated value in the
+            // 0-1 range for how much "on" we are
+            let how_on = ui.ctx().animate_bool(response.id, on);
+            self.skin.paint(ui, &response, how_on);
+        }
+
+        response
+    }
+}
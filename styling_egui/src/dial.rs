@@ -1,144 +0,0 @@
-use nih_plug_egui::egui;
-use std::f32::consts::TAU;
-
-/// iOS-style dial switch:
-///
-/// ``` text
-///      _____________
-///     /       /.....\
-///    |       |.......|
-///     \_______\_____/
-/// ```
-///
-/// ## Example:
-/// ``` ignore
-/// dial_ui(ui, &mut my_bool);
-/// ```
-///
-
-pub fn dial_ui(ui: &mut egui::Ui, min: f32, max: f32, value: &mut f32) -> egui::Response {
-    // Widget code can be broken up in four steps:
-    //  1. Decide a size for the widget
-    //  2. Allocate space for it
-    //  3. Handle interactions with the widget (if any)
-    //  4. Paint the widget
-
-    // fixed size widget based on the height of a standard button:
-    let desired_size = ui.spacing().interact_size.y * egui::vec2(2.0, 2.0);
-
-    // 2. Allocating space:
-    // This is where we get a region of the screen assigned.
-    // We also tell the Ui to sense clicks in the allocated region.
-    let (rect, mut response) = ui.allocate_exact_size(desired_size, egui::Sense::click());
-
-    // 3. Interact: Time to check for clicks!
-    // if response.clicked() {
-    //     *on = !*on;
-    //     response.mark_changed(); // report back that the value changed
-    // }
-
-    // Attach some meta-data to the response which can be used by screen readers:
-    // response.widget_info(|| egui::WidgetInfo::selected(egui::WidgetType::Checkbox, *on, ""));
-
-    // 4. Paint!
-    // Make sure we need to paint:
-    if ui.is_rect_visible(rect) {
-        // Let's ask for a simple animation from egui.
-        // egui keeps track of changes in the boolean associated with the id and
-        // returns an animated value in the 0-1 range for how much "on" we are.
-        // let how_on = ui.ctx().animate_bool(response.id, *on);
-        // We will follow the current style by asking
-        // "how should something that is being interacted with be painted?".
-        // This will, for instance, give us different colors when the widget is hovered or clicked.
-        let visuals = ui.style().interact_selectable(&response, true);
-        // All coordinates are in absolute screen coordinates so we use `rect` to place the elements.
-        let rect = rect.expand(visuals.expansion);
-        let radius = 0.5 * rect.height();
-
-        ui.painter()
-            .rect(rect, radius, visuals.bg_fill, visuals.bg_stroke);
-
-        // Paint the circle, animating it from left to right with `how_on`:
-        let circle_x = rect.left() + radius;
-
-        // center of the circle
-        let inner_circle_center = egui::pos2(circle_x, rect.center().y);
-
-        // radius of the inner circle
-        let inner_circle_radius = 0.75 * radius;
-
-        // draw the inner circle
-        ui.painter().circle(
-            inner_circle_center,
-            inner_circle_radius,
-            visuals.bg_fill,
-            visuals.fg_stroke,
-        );
-
-
-        // Get the angle of the value based on the min/max.
-        let range = max - min;
-        let normalised_value = *value - min;
-        let value_as_percentage = normalised_value / range;
-        let value_as_angle = (value_as_percentage - 0.5) * TAU;
-
-        // TAU is 2*pi aka 360 degrees.
-        let arrow_vec = egui::Vec2::angled(value_as_angle).normalized() * inner_circle_radius;
-
-        // let arrow_edge = [arr]
-        let point2 = inner_circle_center + arrow_vec;
-
-        // draw an arrow from the center to the edge of the inner circle
-        // ui.painter()
-        //     .arrow(inner_circle_center, arrow_vec, visuals.fg_stroke);
-
-        ui.painter()
-            .line_segment([inner_circle_center, point2], visuals.fg_stroke);
-    }
-
-    // All done! Return the interaction response so the user can check what happened
-    // (hovered, clicked, ...) and maybe show a tooltip:
-    response
-}
-
-/// Here is the same code again, but a bit more compact:
-#[allow(dead_code)]
-fn dial_ui_compact(ui: &mut egui::Ui, on: &mut bool) -> egui::Response {
-    let desired_size = ui.spacing().interact_size.y * egui::vec2(2.0, 1.0);
-    let (rect, mut response) = ui.allocate_exact_size(desired_size, egui::Sense::click());
-    if response.clicked() {
-        *on = !*on;
-        response.mark_changed();
-    }
-    response.widget_info(|| egui::WidgetInfo::selected(egui::WidgetType::Checkbox, *on, ""));
-
-    if ui.is_rect_visible(rect) {
-        let how_on = ui.ctx().animate_bool(response.id, *on);
-        let visuals = ui.style().interact_selectable(&response, *on);
-        let rect = rect.expand(visuals.expansion);
-        let radius = 0.5 * rect.height();
-        ui.painter()
-            .rect(rect, radius, visuals.bg_fill, visuals.bg_stroke);
-        let circle_x = egui::lerp((rect.left() + radius)..=(rect.right() - radius), how_on);
-        let center = egui::pos2(circle_x, rect.center().y);
-        ui.painter()
-            .circle(center, 0.75 * radius, visuals.bg_fill, visuals.fg_stroke);
-    }
-
-    response
-}
-
-// A wrapper that allows the more idiomatic usage pattern: `ui.add(dial(&mut my_bool))`
-/// iOS-style dial switch.
-///
-/// ## Example:
-/// ``` ignore
-/// ui.add(dial(&mut my_bool));
-/// ```
-pub fn dial(min: f32, max: f32, value: &mut f32) -> impl egui::Widget + '_ {
-    move |ui: &mut egui::Ui| dial_ui(ui, min, max, value)
-}
-
-pub fn url_to_file_source_code() -> String {
-    format!("https://github.com/emilk/egui/blob/master/{}", file!())
-}
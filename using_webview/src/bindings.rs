@@ -0,0 +1,47 @@
+//! Keeps the protocol bindings in `gui.html` up to date. The web UI is a single HTML file without a
+//! build step, so the JavaScript generated by [`nih_webview_bridge::bindings`] is inlined between
+//! the `BEGIN GENERATED BINDINGS` and `END GENERATED BINDINGS` comments. [`test_bindings`] fails
+//! when that no longer matches the Rust types, and running the tests with `UPDATE_BINDINGS=1`
+//! regenerates it.
+
+use nih_webview_bridge::bindings::{check_bindings, javascript_bindings};
+use std::fs;
+
+const GUI_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/gui.html");
+
+const BEGIN_MARKER: &str = "// BEGIN GENERATED BINDINGS\n";
+const END_MARKER: &str = "// END GENERATED BINDINGS\n";
+/// The generated code is indented to match the rest of the `<script>` tag.
+const INDENT: &str = "      ";
+
+/// `gui.html` with the generated bindings between the markers.
+fn gui_with_bindings(gui: &str) -> String {
+    let begin = gui
+        .find(BEGIN_MARKER)
+        .expect("'gui.html' is missing the 'BEGIN GENERATED BINDINGS' comment")
+        + BEGIN_MARKER.len();
+    let end = gui[begin..]
+        .find(END_MARKER)
+        .expect("'gui.html' is missing the 'END GENERATED BINDINGS' comment")
+        + begin;
+    // The end marker is indented as well
+    let end = gui[..end].trim_end_matches(' ').len();
+
+    let mut result = gui[..begin].to_owned();
+    for line in javascript_bindings().lines() {
+        if !line.is_empty() {
+            result.push_str(INDENT);
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+    result.push_str(&gui[end..]);
+
+    result
+}
+
+#[test]
+fn test_bindings() {
+    let gui = fs::read_to_string(GUI_PATH).expect("Could not read 'gui.html'");
+    check_bindings(GUI_PATH, &gui_with_bindings(&gui));
+}
@@ -1,28 +1,29 @@
 // Forked and modified from: https://github.com/robbert-vdh/nih-plug/tree/master/plugins/examples/gain
 use nih_plug::prelude::*;
 use nih_plug_webview::*;
-use serde::Deserialize;
-use serde_json::json;
-use std::sync::atomic::{AtomicBool, Ordering};
+use nih_webview_bridge::{DirtyParams, Dispatcher, ParamBridge, WebViewEditorState};
 use std::sync::Arc;
 
+#[cfg(test)]
+mod bindings;
+
 struct Gain {
     params: Arc<GainParams>,
 }
 
-#[derive(Deserialize)]
-#[serde(tag = "type")]
-enum Action {
-    Init,
-    SetSize { width: u32, height: u32 },
-    SetGain { value: f32 },
-}
-
 #[derive(Params)]
 struct GainParams {
+    /// The editor's size and the web UI's state, saved together with the parameter state so they
+    /// can be restored.
+    #[persist = "editor-state"]
+    editor_state: Arc<WebViewEditorState>,
+
     #[id = "gain"]
     pub gain: FloatParam,
-    gain_value_changed: Arc<AtomicBool>,
+
+    /// The parameters that changed since the editor last sent them to the web UI. Every parameter
+    /// marks itself by its ID through its callback.
+    dirty_params: Arc<DirtyParams>,
 }
 
 impl Default for Gain {
@@ -35,14 +36,11 @@ impl Default for Gain {
 
 impl Default for GainParams {
     fn default() -> Self {
-        let gain_value_changed = Arc::new(AtomicBool::new(false));
-
-        let v = gain_value_changed.clone();
-        let param_callback = Arc::new(move |_: f32| {
-            v.store(true, Ordering::Relaxed);
-        });
+        let dirty_params = Arc::new(DirtyParams::default());
 
         Self {
+            editor_state: WebViewEditorState::from_size(200, 200),
+
             gain: FloatParam::new(
                 "Gain",
                 util::db_to_gain(0.0),
@@ -56,8 +54,8 @@ impl Default for GainParams {
             .with_unit(" dB")
             .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
             .with_string_to_value(formatters::s2v_f32_gain_to_db())
-            .with_callback(param_callback.clone()),
-            gain_value_changed,
+            .with_callback(dirty_params.callback("gain")),
+            dirty_params,
         }
     }
 }
@@ -113,50 +111,28 @@ impl Plugin for Gain {
     }
 
     fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
-        let params = self.params.clone();
-        let gain_value_changed = self.params.gain_value_changed.clone();
-        let editor = WebViewEditor::new(HTMLSource::String(include_str!("gui.html")), (200, 200))
+        let bridge = ParamBridge::new(self.params.clone(), self.params.dirty_params.clone());
+        let dispatcher = Dispatcher::new(bridge, self.params.editor_state.clone());
+        let size = self.params.editor_state.size();
+        let editor = WebViewEditor::new(HTMLSource::String(include_str!("gui.html")), size)
             .with_background_color((150, 150, 150, 255))
             .with_developer_mode(true)
             .with_event_loop(move |ctx, setter| {
                 while let Some(event) = ctx.next_event() {
                     match event {
-                        WebviewEvent::JSON(value) => {
-                            if let Ok(action) = serde_json::from_value(value) {
-                                match action {
-                                    Action::SetGain { value } => {
-                                        setter.begin_set_parameter(&params.gain);
-                                        setter.set_parameter_normalized(&params.gain, value);
-                                        setter.end_set_parameter(&params.gain);
-                                    }
-                                    Action::SetSize { width, height } => {
-                                        ctx.resize(width, height);
-                                    }
-                                    Action::Init => {
-                                        let _ = ctx.send_json(json!({
-                                            "type": "set_size",
-                                            "width": ctx.width.load(Ordering::Relaxed),
-                                            "height": ctx.height.load(Ordering::Relaxed)
-                                        }));
-                                    }
-                                }
-                            } else {
-                                panic!("Invalid action received from web UI.")
-                            }
+                        WebviewEvent::JSON(value) => dispatcher.handle_message(ctx, &setter, value),
+                        // This example doesn't load any files, see `using_webview_2` for that
+                        WebviewEvent::FileDropped(path) => {
+                            nih_log!(
+                                "Ignoring the file dropped on the editor: {}",
+                                path.display()
+                            )
                         }
-                        WebviewEvent::FileDropped(path) => println!("File dropped: {:?}", path),
                         _ => {}
                     }
                 }
 
-                if gain_value_changed.swap(false, Ordering::Relaxed) {
-                    let _ = ctx.send_json(json!({
-                        "type": "param_change",
-                        "param": "gain",
-                        "value": params.gain.unmodulated_normalized_value(),
-                        "text": params.gain.to_string()
-                    }));
-                }
+                dispatcher.send_changes(ctx);
             });
 
         Some(Box::new(editor))
@@ -0,0 +1,136 @@
+//! Builds the React UI in `src/ui` with Vite, and compresses the resulting files so the plugin can
+//! embed them. The plugin includes the generated `$OUT_DIR/embedded_assets.rs`, which lists every
+//! file with its path relative to the UI's root. This needs Node.js and Yarn, and the UI's
+//! dependencies need to be installed with `yarn install` first. Without those dependencies debug
+//! builds embed a placeholder page instead, so the plugin can be built and tested without Node.js.
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const UI_DIR: &str = "src/ui";
+/// The files and directories in [`UI_DIR`] the UI is built from.
+const UI_SOURCES: &[&str] = &[
+    "src",
+    "index.html",
+    "package.json",
+    "yarn.lock",
+    "tsconfig.json",
+    "tsconfig.node.json",
+    "vite.config.ts",
+];
+
+/// Embedded instead of the UI when the UI's dependencies have not been installed.
+const PLACEHOLDER_HTML: &str = "<!DOCTYPE html>
+<html>
+  <body>
+    <p>The UI has not been built. Run <code>yarn install</code> in <code>src/ui</code> and build the
+    plugin again.</p>
+  </body>
+</html>
+";
+
+fn main() {
+    for source in UI_SOURCES {
+        println!("cargo:rerun-if-changed={UI_DIR}/{source}");
+    }
+
+    let ui_dir = Path::new(UI_DIR);
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is not set"));
+    let dist_dir = out_dir.join("ui");
+    let compressed_dir = out_dir.join("ui_compressed");
+
+    if ui_dir.join("node_modules").exists() {
+        // Arguments after the script name are passed on to `vite build`
+        let dist_dir_str = dist_dir.to_str().expect("OUT_DIR is not valid UTF-8");
+        yarn(ui_dir, &["build", "--outDir", dist_dir_str, "--emptyOutDir"]);
+    } else {
+        // Release builds are the ones that get bundled, and those should never contain the
+        // placeholder
+        if env::var("PROFILE").as_deref() == Ok("release") {
+            panic!(
+                "The UI's dependencies have not been installed. Run 'yarn install' in '{UI_DIR}' \
+                 before building the plugin in release mode."
+            );
+        }
+
+        println!(
+            "cargo:warning=The UI's dependencies have not been installed, so the plugin embeds a \
+             placeholder page instead. Run 'yarn install' in '{UI_DIR}' to build the UI."
+        );
+        // This directory is cheap to scan without `node_modules`, and it changes when the
+        // dependencies get installed
+        println!("cargo:rerun-if-changed={UI_DIR}");
+
+        let _ = fs::remove_dir_all(&dist_dir);
+        fs::create_dir_all(&dist_dir).expect("Could not create the UI directory");
+        fs::write(dist_dir.join("index.html"), PLACEHOLDER_HTML)
+            .expect("Could not write the placeholder UI");
+    }
+
+    let mut files = Vec::new();
+    collect_files(&dist_dir, &mut files);
+    files.sort();
+
+    let _ = fs::remove_dir_all(&compressed_dir);
+    fs::create_dir_all(&compressed_dir).expect("Could not create the compressed assets directory");
+
+    let mut assets = String::from("pub static EMBEDDED_ASSETS: &[EmbeddedAsset] = &[\n");
+    for (idx, file) in files.iter().enumerate() {
+        let relative_path = file
+            .strip_prefix(&dist_dir)
+            .unwrap()
+            .components()
+            .map(|component| component.as_os_str().to_str().expect("Non-UTF-8 asset path"))
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let contents = fs::read(file).expect("Could not read a built asset");
+        let compressed_file = compressed_dir.join(format!("{idx}.deflate"));
+        fs::write(
+            &compressed_file,
+            miniz_oxide::deflate::compress_to_vec(&contents, 10),
+        )
+        .expect("Could not write a compressed asset");
+
+        writeln!(
+            assets,
+            "    EmbeddedAsset {{ path: {relative_path:?}, compressed: include_bytes!({compressed_file:?}) }},"
+        )
+        .unwrap();
+    }
+    assets.push_str("];\n");
+
+    fs::write(out_dir.join("embedded_assets.rs"), assets)
+        .expect("Could not write the embedded assets list");
+}
+
+fn yarn(ui_dir: &Path, args: &[&str]) {
+    // Yarn is a batch script on Windows, which `Command` doesn't resolve on its own
+    let yarn = if cfg!(windows) { "yarn.cmd" } else { "yarn" };
+    let status = Command::new(yarn)
+        .args(args)
+        .current_dir(ui_dir)
+        .status()
+        .unwrap_or_else(|err| {
+            panic!("Could not run '{yarn}', make sure Node.js and Yarn are installed: {err}")
+        });
+
+    if !status.success() {
+        panic!("'{yarn} {}' failed with {status}", args.join(" "));
+    }
+}
+
+/// Recursively add all files in `dir` to `files`.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).expect("Could not read the built UI") {
+        let path = entry.expect("Could not read the built UI").path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
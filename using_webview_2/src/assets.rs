@@ -0,0 +1,165 @@
+//! Serves the web UI's files to the webview through a custom protocol. Release builds use the files
+//! `build.rs` compressed and embedded into the plugin. While developing, the files can also be read
+//! straight from a directory, so a rebuilt UI shows up without rebuilding the plugin.
+
+use std::borrow::Cow;
+use std::fs;
+use std::path::PathBuf;
+
+include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+
+/// The name of the custom protocol the assets are served from.
+pub const PROTOCOL_NAME: &str = "assets";
+
+/// The URL of the UI's entry point. Windows' WebView2 doesn't support custom URL schemes, so custom
+/// protocols are served from a special HTTPS domain there instead.
+pub const INDEX_URL: &str = if cfg!(windows) {
+    "https://assets.localhost/index.html"
+} else {
+    "assets://localhost/index.html"
+};
+
+/// A file compressed and embedded into the plugin by `build.rs`.
+pub struct EmbeddedAsset {
+    /// The path relative to the UI's root, separated by forward slashes.
+    pub path: &'static str,
+    /// The file's contents, compressed with raw DEFLATE.
+    pub compressed: &'static [u8],
+}
+
+/// A file served to the webview.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Asset {
+    pub mime_type: &'static str,
+    pub contents: Cow<'static, [u8]>,
+}
+
+/// Where the web UI's files are loaded from.
+pub enum AssetSource {
+    /// The files embedded into the plugin.
+    Embedded(&'static [EmbeddedAsset]),
+    /// A directory on disk, like the Vite build output directory. The files are read on every
+    /// request.
+    Directory(PathBuf),
+}
+
+impl AssetSource {
+    /// Look up the file for a request path like `/assets/index.js`. Query strings and fragments are
+    /// ignored, and directories resolve to their `index.html`. Returns `None` if the file doesn't
+    /// exist, if it can't be read, or if the path tries to escape the UI's root.
+    pub fn resolve(&self, request_path: &str) -> Option<Asset> {
+        let path = normalize_path(request_path)?;
+        let contents = match self {
+            AssetSource::Embedded(assets) => {
+                let asset = assets.iter().find(|asset| asset.path == path)?;
+                miniz_oxide::inflate::decompress_to_vec(asset.compressed).ok()?
+            }
+            AssetSource::Directory(dir) => fs::read(dir.join(&path)).ok()?,
+        };
+
+        Some(Asset {
+            mime_type: mime_type(&path),
+            contents: Cow::Owned(contents),
+        })
+    }
+}
+
+/// Turn a request path into a path relative to the UI's root, or `None` if it contains `..` or
+/// other components that could escape the root.
+fn normalize_path(request_path: &str) -> Option<String> {
+    let path = request_path
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches('/');
+
+    let mut components = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => return None,
+            component if component.contains(['\\', ':']) => return None,
+            component => components.push(component),
+        }
+    }
+
+    if path.is_empty() || path.ends_with('/') {
+        components.push("index.html");
+    }
+
+    Some(components.join("/"))
+}
+
+/// The MIME type for a file, based on its extension.
+pub fn mime_type(path: &str) -> &'static str {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+#[test]
+fn test_normalize_path() {
+    assert_eq!(normalize_path("/").as_deref(), Some("index.html"));
+    assert_eq!(normalize_path("").as_deref(), Some("index.html"));
+    assert_eq!(
+        normalize_path("/assets/index-1234.js?v=1#top").as_deref(),
+        Some("assets/index-1234.js")
+    );
+    assert_eq!(
+        normalize_path("/./assets//app.css").as_deref(),
+        Some("assets/app.css")
+    );
+    assert_eq!(normalize_path("/docs/").as_deref(), Some("docs/index.html"));
+    assert_eq!(normalize_path("/../Cargo.toml"), None);
+    assert_eq!(normalize_path("/assets/../../secret"), None);
+    assert_eq!(normalize_path("/C:/Windows/win.ini"), None);
+    assert_eq!(normalize_path("/assets\\..\\secret"), None);
+}
+
+#[test]
+fn test_mime_type() {
+    assert_eq!(mime_type("index.html"), "text/html; charset=utf-8");
+    assert_eq!(
+        mime_type("assets/index-1234.JS"),
+        "text/javascript; charset=utf-8"
+    );
+    assert_eq!(mime_type("assets/font.woff2"), "font/woff2");
+    assert_eq!(mime_type("LICENSE"), "application/octet-stream");
+}
+
+#[test]
+fn test_resolve_embedded() {
+    let html = b"<!DOCTYPE html><html><body>Gain</body></html>";
+    let compressed: &'static [u8] = miniz_oxide::deflate::compress_to_vec(html, 10).leak();
+    let assets: &'static [EmbeddedAsset] = Box::leak(Box::new([EmbeddedAsset {
+        path: "index.html",
+        compressed,
+    }]));
+    let source = AssetSource::Embedded(assets);
+
+    let asset = source.resolve("/").unwrap();
+    assert_eq!(asset.mime_type, "text/html; charset=utf-8");
+    assert_eq!(asset.contents, &html[..]);
+    assert!(source.resolve("/missing.js").is_none());
+}
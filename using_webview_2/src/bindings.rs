@@ -0,0 +1,29 @@
+//! TypeScript definitions for the messages exchanged with the web UI, generated from the Rust types
+//! with ts-rs. The shared messages come from [`nih_webview_bridge::protocol`], and the plugin's own
+//! messages from [`EditorMessage`]. The web UI imports them from `src/ui/src/bindings.ts`. That file
+//! is checked in so the UI can be built without running the tests, and [`test_bindings`] fails
+//! when it no longer matches the Rust types. Running the tests with `UPDATE_BINDINGS=1`
+//! regenerates it.
+
+use nih_webview_bridge::bindings::{check_bindings, typescript_bindings};
+use ts_rs::TS;
+
+use crate::editor_message::EditorMessage;
+use crate::file_drop::{FileKind, FileStatus, LoadStatus};
+use crate::metering::{Meters, Spectrum};
+
+const BINDINGS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/ui/src/bindings.ts");
+
+#[test]
+fn test_bindings() {
+    let bindings = typescript_bindings(&[
+        EditorMessage::decl(),
+        FileStatus::decl(),
+        FileKind::decl(),
+        LoadStatus::decl(),
+        Meters::decl(),
+        Spectrum::decl(),
+    ]);
+
+    check_bindings(BINDINGS_PATH, &bindings);
+}
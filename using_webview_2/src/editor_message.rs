@@ -0,0 +1,27 @@
+//! The messages this plugin's editor sends to the web UI next to the shared
+//! [`PluginMessage`][nih_webview_bridge::PluginMessage]s.
+
+use serde::Serialize;
+use serde_json::Value;
+#[cfg(test)]
+use ts_rs::TS;
+
+use crate::file_drop::FileStatus;
+use crate::metering::Meters;
+
+/// A message sent to the web UI that only this plugin sends.
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(test, derive(TS))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EditorMessage {
+    /// The progress of loading a file dropped on the editor.
+    FileStatus { file: FileStatus },
+    /// A new measurement for the meters.
+    Meters(Meters),
+}
+
+impl EditorMessage {
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).expect("Could not serialize a message for the web UI")
+    }
+}
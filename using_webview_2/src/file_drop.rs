@@ -0,0 +1,164 @@
+//! Routes files dropped on the editor by their extension, and reports what happened to them back to
+//! the web UI.
+
+use nih_plug::prelude::*;
+use nih_plug::wrapper::state::PluginState;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+#[cfg(test)]
+use ts_rs::TS;
+
+/// What a dropped file is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(test, derive(TS))]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    /// A WAV or FLAC file, loaded as the impulse response on a background thread.
+    ImpulseResponse,
+    /// A JSON file in the format nih-plug stores the plugin's state in. Only the parameters are
+    /// applied.
+    Preset,
+}
+
+impl FileKind {
+    /// Determine what a file is used for based on its extension, or `None` if the plugin can't do
+    /// anything with it.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "wav" | "flac" => Some(FileKind::ImpulseResponse),
+            "json" => Some(FileKind::Preset),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(test, derive(TS))]
+#[serde(rename_all = "snake_case")]
+pub enum LoadStatus {
+    Loading,
+    Loaded,
+    Failed,
+}
+
+/// The progress of loading a dropped file, as sent to the web UI in a `file_status` message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(test, derive(TS))]
+pub struct FileStatus {
+    pub path: String,
+    /// `None` for files with an unsupported extension.
+    pub kind: Option<FileKind>,
+    pub status: LoadStatus,
+    /// The reason loading the file failed.
+    pub message: Option<String>,
+}
+
+impl FileStatus {
+    pub fn loading(path: &Path, kind: FileKind) -> Self {
+        Self::new(path, Some(kind), LoadStatus::Loading, None)
+    }
+
+    pub fn loaded(path: &Path, kind: FileKind) -> Self {
+        Self::new(path, Some(kind), LoadStatus::Loaded, None)
+    }
+
+    pub fn failed(path: &Path, kind: Option<FileKind>, message: String) -> Self {
+        Self::new(path, kind, LoadStatus::Failed, Some(message))
+    }
+
+    fn new(
+        path: &Path,
+        kind: Option<FileKind>,
+        status: LoadStatus,
+        message: Option<String>,
+    ) -> Self {
+        Self {
+            path: path.display().to_string(),
+            kind,
+            status,
+            message,
+        }
+    }
+}
+
+/// Read a preset file and apply its parameter values to the plugin. The plugin's other state, like
+/// the editor's, is left alone.
+pub fn apply_preset(path: &Path, context: &dyn GuiContext) -> Result<(), String> {
+    let json = fs::read_to_string(path).map_err(|err| format!("Could not read the file: {err}"))?;
+    let preset: PluginState =
+        serde_json::from_str(&json).map_err(|err| format!("Not a valid preset: {err}"))?;
+
+    let mut state = context.get_state();
+    merge_preset_params(&mut state, preset)?;
+    context.set_state(state);
+
+    Ok(())
+}
+
+/// Overwrite the parameter values in `state` with the values from `preset`. Parameters the plugin
+/// doesn't have are ignored, but at least one of the preset's parameters needs to match.
+fn merge_preset_params(state: &mut PluginState, preset: PluginState) -> Result<(), String> {
+    let mut num_applied = 0;
+    for (param_id, value) in preset.params {
+        if let Some(current_value) = state.params.get_mut(&param_id) {
+            *current_value = value;
+            num_applied += 1;
+        }
+    }
+
+    if num_applied > 0 {
+        Ok(())
+    } else {
+        Err(String::from(
+            "The preset does not contain any of this plugin's parameters",
+        ))
+    }
+}
+
+#[test]
+fn test_file_kind() {
+    assert_eq!(
+        FileKind::from_path(Path::new("/samples/Room.WAV")),
+        Some(FileKind::ImpulseResponse)
+    );
+    assert_eq!(
+        FileKind::from_path(Path::new("cab.flac")),
+        Some(FileKind::ImpulseResponse)
+    );
+    assert_eq!(
+        FileKind::from_path(Path::new("loud.json")),
+        Some(FileKind::Preset)
+    );
+    assert_eq!(FileKind::from_path(Path::new("song.mp3")), None);
+    assert_eq!(FileKind::from_path(Path::new("README")), None);
+}
+
+#[test]
+fn test_merge_preset_params() {
+    use nih_plug::wrapper::state::ParamValue;
+    use std::collections::BTreeMap;
+
+    let mut state = PluginState {
+        version: String::from("0.0.1"),
+        params: BTreeMap::from([
+            (String::from("gain"), ParamValue::F32(1.0)),
+            (String::from("ir_mix"), ParamValue::F32(1.0)),
+        ]),
+        fields: BTreeMap::from([(String::from("editor-state"), String::from("{}"))]),
+    };
+
+    let preset: PluginState = serde_json::from_str(
+        r#"{ "version": "0.0.1", "params": { "gain": 0.5, "unknown": 1.0 }, "fields": {} }"#,
+    )
+    .unwrap();
+    merge_preset_params(&mut state, preset).unwrap();
+    assert!(matches!(state.params["gain"], ParamValue::F32(value) if value == 0.5));
+    assert!(matches!(state.params["ir_mix"], ParamValue::F32(value) if value == 1.0));
+    assert_eq!(state.fields.len(), 1);
+
+    let preset: PluginState =
+        serde_json::from_str(r#"{ "params": { "unknown": 1.0 }, "fields": {} }"#).unwrap();
+    assert!(merge_preset_params(&mut state, preset).is_err());
+}
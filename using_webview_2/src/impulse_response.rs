@@ -0,0 +1,403 @@
+//! Impulse responses loaded from audio files, and a direct form convolution to apply them on the
+//! audio thread.
+
+use crossbeam::queue::ArrayQueue;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// The longest impulse response in samples at the plugin's sample rate. Longer files are truncated.
+/// The convolution is done in the time domain, so this needs to stay short.
+pub const MAX_LENGTH: usize = 2048;
+/// The number of samples at the end of a truncated impulse response that are faded out.
+const TRUNCATION_FADE_LENGTH: usize = 64;
+/// The most channels the plugin processes. Mono impulse responses are used for every channel.
+const MAX_CHANNELS: usize = 2;
+
+/// An impulse response along with the convolution state for applying it. The default impulse
+/// response is empty and leaves the signal unchanged.
+#[derive(Debug, Default)]
+pub struct ImpulseResponse {
+    /// The impulse response for each channel. There are either no channels, one channel that's
+    /// used for every input channel, or [`MAX_CHANNELS`] channels.
+    taps: Vec<Vec<f32>>,
+    /// The last `taps.len()` input samples for each of the [`MAX_CHANNELS`] channels, as ring
+    /// buffers.
+    history: Vec<Vec<f32>>,
+    /// The position in `history` where the next input sample is written.
+    pos: usize,
+}
+
+impl ImpulseResponse {
+    /// Decode a WAV or FLAC file and turn it into an impulse response at `sample_rate`. This
+    /// allocates, so it should be done on a background thread.
+    pub fn load(path: &Path, sample_rate: f32) -> Result<Self, String> {
+        // Only the part of the file that fits at this sample rate is decoded
+        if sample_rate <= 0.0 {
+            return Err(String::from("Invalid sample rate"));
+        }
+
+        let (channels, file_sample_rate) = decode(path, sample_rate)?;
+        Self::from_channels(channels, file_sample_rate, sample_rate)
+    }
+
+    /// Create an impulse response from the de-interleaved samples of an audio file. The samples
+    /// are resampled from `file_sample_rate` to `sample_rate`, truncated to [`MAX_LENGTH`], and
+    /// normalized to unit energy so loading an impulse response doesn't cause huge level jumps.
+    pub fn from_channels(
+        channels: Vec<Vec<f32>>,
+        file_sample_rate: f32,
+        sample_rate: f32,
+    ) -> Result<Self, String> {
+        if channels.is_empty() || channels[0].is_empty() {
+            return Err(String::from("The file does not contain any audio"));
+        }
+        if file_sample_rate <= 0.0 || sample_rate <= 0.0 {
+            return Err(String::from("Invalid sample rate"));
+        }
+
+        // Files with more channels than the plugin only use their first channels
+        let mut taps: Vec<Vec<f32>> = channels
+            .iter()
+            .take(if channels.len() >= MAX_CHANNELS {
+                MAX_CHANNELS
+            } else {
+                1
+            })
+            .map(|samples| resample(samples, file_sample_rate, sample_rate))
+            .collect();
+
+        for channel in &mut taps {
+            if channel.len() > MAX_LENGTH {
+                channel.truncate(MAX_LENGTH);
+                let fade_start = MAX_LENGTH - TRUNCATION_FADE_LENGTH;
+                for (idx, sample) in channel[fade_start..].iter_mut().enumerate() {
+                    *sample *= 1.0 - ((idx + 1) as f32 / TRUNCATION_FADE_LENGTH as f32);
+                }
+            }
+        }
+
+        let energy = taps
+            .iter()
+            .map(|channel| channel.iter().map(|sample| sample * sample).sum::<f32>())
+            .fold(0.0f32, f32::max);
+        if energy <= f32::EPSILON || !energy.is_finite() {
+            return Err(String::from("The impulse response is silent"));
+        }
+        let gain = energy.sqrt().recip();
+        for sample in taps.iter_mut().flatten() {
+            *sample *= gain;
+        }
+
+        let length = taps[0].len();
+        Ok(Self {
+            taps,
+            history: vec![vec![0.0; length]; MAX_CHANNELS],
+            pos: 0,
+        })
+    }
+
+    /// The impulse response's length in samples, or 0 for the empty impulse response.
+    pub fn len(&self) -> usize {
+        self.taps.first().map_or(0, Vec::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Convolve one sample for `channel` with the impulse response. [`advance()`][Self::advance()]
+    /// needs to be called after every channel's sample has been processed.
+    pub fn process(&mut self, channel: usize, input: f32) -> f32 {
+        let length = self.len();
+        if length == 0 || channel >= MAX_CHANNELS {
+            return input;
+        }
+
+        let taps = &self.taps[channel.min(self.taps.len() - 1)];
+        let history = &mut self.history[channel];
+        history[self.pos] = input;
+
+        // `history[pos]` is the newest sample, so it's multiplied with the first tap and the
+        // samples before it with the taps after that. This is split into two loops so neither
+        // needs to wrap around.
+        let (older, newer) = history.split_at(self.pos + 1);
+        let newer_taps = newer.len();
+        let recent: f32 = older
+            .iter()
+            .rev()
+            .zip(taps)
+            .map(|(sample, tap)| sample * tap)
+            .sum();
+        let wrapped: f32 = newer
+            .iter()
+            .rev()
+            .zip(&taps[length - newer_taps..])
+            .map(|(sample, tap)| sample * tap)
+            .sum();
+
+        recent + wrapped
+    }
+
+    /// Move on to the next sample.
+    pub fn advance(&mut self) {
+        let length = self.len();
+        if length > 0 {
+            self.pos = (self.pos + 1) % length;
+        }
+    }
+}
+
+/// Hands newly loaded impulse responses to the audio thread. The audio thread hands back the
+/// impulse response it replaced so that it's deallocated on a background thread instead of on the
+/// audio thread.
+pub struct ImpulseResponseExchange {
+    /// The most recently loaded impulse response that the audio thread hasn't picked up yet.
+    incoming: ArrayQueue<Box<ImpulseResponse>>,
+    /// Impulse responses the audio thread no longer uses.
+    retired: ArrayQueue<Box<ImpulseResponse>>,
+}
+
+impl Default for ImpulseResponseExchange {
+    fn default() -> Self {
+        Self {
+            incoming: ArrayQueue::new(1),
+            retired: ArrayQueue::new(4),
+        }
+    }
+}
+
+impl ImpulseResponseExchange {
+    /// Send a new impulse response to the audio thread, replacing one that hasn't been picked up
+    /// yet. Impulse responses the audio thread has retired are deallocated here, so this should be
+    /// called from a background thread.
+    pub fn send(&self, impulse_response: Box<ImpulseResponse>) {
+        while self.retired.pop().is_some() {}
+        // An impulse response that was never picked up is simply dropped here
+        drop(self.incoming.force_push(impulse_response));
+    }
+
+    /// Swap `current` with a newly loaded impulse response if there is one. This doesn't allocate
+    /// or deallocate, so it's safe to call from the audio thread.
+    pub fn receive(&self, current: &mut Box<ImpulseResponse>) {
+        // The old impulse response can only be swapped out if it can be handed back. Only the audio
+        // thread adds to the retired queue, so it can't fill up between this check and the push.
+        if self.retired.is_full() {
+            return;
+        }
+
+        if let Some(new) = self.incoming.pop() {
+            let old = std::mem::replace(current, new);
+            if self.retired.push(old).is_err() {
+                unreachable!("The retired queue was checked for room")
+            }
+        }
+    }
+}
+
+/// Decode an audio file into one `Vec` per channel, along with the file's sample rate. Only the
+/// part of the file that fits in [`MAX_LENGTH`] samples at `sample_rate` is decoded.
+fn decode(path: &Path, sample_rate: f32) -> Result<(Vec<Vec<f32>>, f32), String> {
+    let file = File::open(path).map_err(|err| format!("Could not open the file: {err}"))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|err| format!("Unsupported audio file: {err}"))?
+        .format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| String::from("The file does not contain any audio"))?;
+    let track_id = track.id;
+    let file_sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| String::from("The file does not have a sample rate"))?
+        as f32;
+    let num_channels = track
+        .codec_params
+        .channels
+        .map_or(0, |channels| channels.count());
+    if num_channels == 0 {
+        return Err(String::from("The file does not contain any audio"));
+    }
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|err| format!("Unsupported audio codec: {err}"))?;
+
+    // A couple of extra samples so the resampler has something to interpolate towards
+    let max_file_length = (MAX_LENGTH as f32 * file_sample_rate / sample_rate).ceil() as usize + 2;
+    let mut channels = vec![Vec::new(); num_channels];
+    let mut sample_buffer = None;
+    while channels[0].len() < max_file_length {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(format!("Could not read the file: {err}")),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = decoder
+            .decode(&packet)
+            .map_err(|err| format!("Could not decode the file: {err}"))?;
+        let sample_buffer = sample_buffer.get_or_insert_with(|| {
+            SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec())
+        });
+        sample_buffer.copy_interleaved_ref(decoded);
+
+        for frame in sample_buffer.samples().chunks_exact(num_channels) {
+            for (channel, sample) in channels.iter_mut().zip(frame) {
+                channel.push(*sample);
+            }
+        }
+    }
+
+    Ok((channels, file_sample_rate))
+}
+
+/// Resample `samples` from `from_rate` to `to_rate` with linear interpolation. This is good enough
+/// for impulse responses, which are mostly used at or near their original sample rate.
+fn resample(samples: &[f32], from_rate: f32, to_rate: f32) -> Vec<f32> {
+    if from_rate == to_rate {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate / to_rate;
+    let length = ((samples.len() as f32 / ratio).floor() as usize).max(1);
+    (0..length)
+        .map(|idx| {
+            let pos = idx as f32 * ratio;
+            let left = pos.floor() as usize;
+            let right = (left + 1).min(samples.len() - 1);
+            let t = pos - left as f32;
+
+            samples[left.min(samples.len() - 1)] * (1.0 - t) + samples[right] * t
+        })
+        .collect()
+}
+
+#[test]
+fn test_from_channels() {
+    assert!(ImpulseResponse::from_channels(Vec::new(), 48000.0, 48000.0).is_err());
+    assert!(ImpulseResponse::from_channels(vec![vec![0.0; 16]], 48000.0, 48000.0).is_err());
+
+    // Normalized to unit energy
+    let ir = ImpulseResponse::from_channels(vec![vec![0.0, 2.0, 0.0]], 48000.0, 48000.0).unwrap();
+    assert_eq!(ir.taps, [[0.0, 1.0, 0.0]]);
+
+    // Resampled to the plugin's sample rate and truncated
+    let ir = ImpulseResponse::from_channels(vec![vec![1.0; 8000]], 24000.0, 48000.0).unwrap();
+    assert_eq!(ir.len(), MAX_LENGTH);
+    assert_eq!(*ir.taps[0].last().unwrap(), 0.0);
+}
+
+#[test]
+fn test_convolution() {
+    let mut ir =
+        ImpulseResponse::from_channels(vec![vec![1.0, 0.0, 0.5], vec![0.0, 1.0, 0.0]], 1.0, 1.0)
+            .unwrap();
+    let gain = 1.25f32.sqrt().recip();
+
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+    for input in [1.0, 0.0, 0.0, 2.0, 0.0] {
+        left.push(ir.process(0, input));
+        right.push(ir.process(1, input));
+        ir.advance();
+    }
+
+    // Both channels are normalized by the channel with the most energy
+    let expected_left = [1.0, 0.0, 0.5, 2.0, 0.0];
+    let expected_right = [0.0, 1.0, 0.0, 0.0, 2.0];
+    for (actual, expected) in left.iter().zip(expected_left) {
+        assert!((actual - expected * gain).abs() < 1e-6, "{left:?}");
+    }
+    for (actual, expected) in right.iter().zip(expected_right) {
+        assert!((actual - expected * gain).abs() < 1e-6, "{right:?}");
+    }
+}
+
+#[test]
+fn test_empty_impulse_response() {
+    let mut ir = ImpulseResponse::default();
+    assert_eq!(ir.process(0, 0.5), 0.5);
+    ir.advance();
+    assert_eq!(ir.process(1, -0.5), -0.5);
+}
+
+#[test]
+fn test_exchange() {
+    let exchange = ImpulseResponseExchange::default();
+    let mut current = Box::default();
+
+    exchange.receive(&mut current);
+    assert!(current.is_empty());
+
+    let new = ImpulseResponse::from_channels(vec![vec![1.0; 4]], 1.0, 1.0).unwrap();
+    exchange.send(Box::new(new));
+    exchange.receive(&mut current);
+    assert_eq!(current.len(), 4);
+    assert_eq!(exchange.retired.len(), 1);
+
+    // Sending another impulse response deallocates the retired ones
+    let new = ImpulseResponse::from_channels(vec![vec![1.0; 8]], 1.0, 1.0).unwrap();
+    exchange.send(Box::new(new));
+    assert!(exchange.retired.is_empty());
+    exchange.receive(&mut current);
+    assert_eq!(current.len(), 8);
+}
+
+#[test]
+fn test_load_wav() {
+    // A mono 16-bit PCM WAV file containing a single impulse followed by silence
+    let samples: [i16; 4] = [i16::MAX, 0, 0, 0];
+    let data: Vec<u8> = samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect();
+    let mut wav = Vec::new();
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&48000u32.to_le_bytes());
+    wav.extend_from_slice(&(48000u32 * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&data);
+
+    let path = std::env::temp_dir().join(format!("impulse-{}.wav", std::process::id()));
+    std::fs::write(&path, wav).unwrap();
+    let result = ImpulseResponse::load(&path, 48000.0);
+    let invalid_result = ImpulseResponse::load(&path, 0.0);
+    std::fs::remove_file(&path).unwrap();
+
+    let ir = result.unwrap();
+    assert_eq!(ir.taps, [[1.0, 0.0, 0.0, 0.0]]);
+    assert!(invalid_result.is_err());
+    assert!(ImpulseResponse::load(Path::new("does-not-exist.wav"), 48000.0).is_err());
+}
@@ -1,48 +1,114 @@
 // Forked and modified from: https://github.com/robbert-vdh/nih-plug/tree/master/plugins/examples/gain
+use atomic_float::AtomicF32;
 use nih_plug::prelude::*;
+use nih_plug_webview::http::header::CONTENT_TYPE;
+use nih_plug_webview::http::{Response, StatusCode};
 use nih_plug_webview::*;
-use serde::Deserialize;
-use serde_json::json;
-use std::sync::atomic::{AtomicBool, Ordering};
+use nih_webview_bridge::{DirtyParams, Dispatcher, ParamBridge, WebViewEditorState};
+use parking_lot::Mutex;
+use std::borrow::Cow;
+use std::env;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use crate::assets::{AssetSource, EMBEDDED_ASSETS};
+use crate::editor_message::EditorMessage;
+use crate::file_drop::{apply_preset, FileKind, FileStatus};
+use crate::impulse_response::{ImpulseResponse, ImpulseResponseExchange};
+use crate::metering::{Meter, MeterData};
+
+mod assets;
+#[cfg(test)]
+mod bindings;
+mod editor_message;
+mod file_drop;
+mod impulse_response;
+mod metering;
+
+/// Enables the webview's developer tools, and allows loading the UI from somewhere other than the
+/// files embedded into the plugin through these environment variables:
+///
+/// - `GAIN_UI_DEV_URL`: The URL of a running Vite dev server, like `http://localhost:5173`, for hot
+///   reloading while working on the UI.
+/// - `GAIN_UI_DIR`: A directory containing a built UI, like `src/ui/dist` kept up to date with
+///   `yarn build --watch`.
+const DEVELOPER_MODE: bool = cfg!(debug_assertions);
+
 struct Gain {
     params: Arc<GainParams>,
+
+    /// The impulse response the signal is convolved with. This is empty until an audio file is
+    /// dropped on the editor.
+    impulse_response: Box<ImpulseResponse>,
+    /// Hands impulse responses loaded on a background thread to the audio thread.
+    impulse_response_exchange: Arc<ImpulseResponseExchange>,
+    /// The current sample rate, which impulse responses are resampled to when they're loaded. This
+    /// is 0 until the plugin has been initialized.
+    sample_rate: Arc<AtomicF32>,
+    /// The file the current impulse response was loaded from, so it can be loaded again when the
+    /// sample rate changes.
+    impulse_response_path: Arc<Mutex<Option<PathBuf>>>,
+    /// Status updates for dropped files that still need to be sent to the web UI.
+    file_statuses: Arc<Mutex<Vec<FileStatus>>>,
+
+    /// Measures the output for the web UI's meters and spectrum analyzer.
+    meter: Meter,
+    /// The editor's side of `meter`, which receives its updates.
+    meter_output: Arc<Mutex<triple_buffer::Output<MeterData>>>,
 }
 
-// #[derive(Deserialize)]
-// #[serde(tag = "type")]
-// enum Action {
-//     Init,
-//     SetSize { width: u32, height: u32 },
-//     SetGain { value: f32 },
-// }
+/// Work the editor hands off to a background thread.
+#[derive(Debug)]
+enum Task {
+    /// Load a WAV or FLAC file as the impulse response.
+    LoadImpulseResponse(PathBuf),
+}
 
 #[derive(Params)]
 struct GainParams {
+    /// The editor's size and the web UI's state, saved together with the parameter state so they
+    /// can be restored.
+    #[persist = "editor-state"]
+    editor_state: Arc<WebViewEditorState>,
+
     #[id = "gain"]
     pub gain: FloatParam,
-    gain_value_changed: Arc<AtomicBool>,
+    /// How much of the signal is sent through the impulse response.
+    #[id = "ir_mix"]
+    pub ir_mix: FloatParam,
+
+    /// The parameters that changed since the editor last sent them to the web UI. Every parameter
+    /// marks itself by its ID through its callback.
+    dirty_params: Arc<DirtyParams>,
 }
 
 impl Default for Gain {
     fn default() -> Self {
+        let (meter, meter_output) = Meter::new();
+
         Self {
             params: Arc::new(GainParams::default()),
+
+            impulse_response: Box::default(),
+            impulse_response_exchange: Arc::new(ImpulseResponseExchange::default()),
+            sample_rate: Arc::new(AtomicF32::new(0.0)),
+            impulse_response_path: Arc::new(Mutex::new(None)),
+            file_statuses: Arc::new(Mutex::new(Vec::new())),
+
+            meter,
+            meter_output: Arc::new(Mutex::new(meter_output)),
         }
     }
 }
 
 impl Default for GainParams {
     fn default() -> Self {
-        let gain_value_changed = Arc::new(AtomicBool::new(false));
-
-        let v = gain_value_changed.clone();
-        let param_callback = Arc::new(move |_: f32| {
-            v.store(true, Ordering::Relaxed);
-        });
+        let dirty_params = Arc::new(DirtyParams::default());
 
         Self {
+            editor_state: WebViewEditorState::from_size(600, 600),
+
             gain: FloatParam::new(
                 "Gain",
                 util::db_to_gain(0.0),
@@ -56,14 +122,20 @@ impl Default for GainParams {
             .with_unit(" dB")
             .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
             .with_string_to_value(formatters::s2v_f32_gain_to_db())
-            .with_callback(param_callback.clone()),
-            gain_value_changed,
+            .with_callback(dirty_params.callback("gain")),
+            ir_mix: FloatParam::new("IR Mix", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage())
+                .with_callback(dirty_params.callback("ir_mix")),
+            dirty_params,
         }
     }
 }
 
 impl Plugin for Gain {
-    type BackgroundTask = ();
+    type BackgroundTask = Task;
     type SysExMessage = ();
 
     const NAME: &'static str = "Gain";
@@ -95,69 +167,169 @@ impl Plugin for Gain {
         self.params.clone()
     }
 
+    fn task_executor(&mut self) -> TaskExecutor<Self> {
+        let impulse_response_exchange = self.impulse_response_exchange.clone();
+        let sample_rate = self.sample_rate.clone();
+        let impulse_response_path = self.impulse_response_path.clone();
+        let file_statuses = self.file_statuses.clone();
+
+        Box::new(move |task| match task {
+            Task::LoadImpulseResponse(path) => {
+                *impulse_response_path.lock() = Some(path.clone());
+
+                // The editor can be opened before the plugin is initialized. In that case the
+                // impulse response is loaded by `initialize()` once the sample rate is known.
+                let load_sample_rate = sample_rate.load(Ordering::Relaxed);
+                if load_sample_rate <= 0.0 {
+                    return;
+                }
+
+                let kind = FileKind::ImpulseResponse;
+                let status = match ImpulseResponse::load(&path, load_sample_rate) {
+                    // `initialize()` loads the impulse response again if the sample rate changed
+                    // in the meantime
+                    Ok(_) if sample_rate.load(Ordering::Relaxed) != load_sample_rate => return,
+                    Ok(impulse_response) => {
+                        impulse_response_exchange.send(Box::new(impulse_response));
+                        FileStatus::loaded(&path, kind)
+                    }
+                    Err(message) => {
+                        nih_log!("Could not load '{}': {message}", path.display());
+                        FileStatus::failed(&path, Some(kind), message)
+                    }
+                };
+
+                file_statuses.lock().push(status);
+            }
+        })
+    }
+
+    fn initialize(
+        &mut self,
+        _audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        context: &mut impl InitContext<Self>,
+    ) -> bool {
+        let previous_sample_rate = self
+            .sample_rate
+            .swap(buffer_config.sample_rate, Ordering::Relaxed);
+        self.meter.set_sample_rate(buffer_config.sample_rate);
+
+        // Impulse responses are resampled when they're loaded, so they need to be loaded again for
+        // the new sample rate. This also loads files that were dropped before the plugin was
+        // initialized.
+        if buffer_config.sample_rate != previous_sample_rate {
+            let path = self.impulse_response_path.lock().clone();
+            if let Some(path) = path {
+                context.execute(Task::LoadImpulseResponse(path));
+            }
+        }
+
+        true
+    }
+
+    fn reset(&mut self) {
+        self.meter.reset();
+    }
+
     fn process(
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
         _context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        for channel_samples in buffer.iter_samples() {
+        self.impulse_response_exchange
+            .receive(&mut self.impulse_response);
+
+        for mut channel_samples in buffer.iter_samples() {
             let gain = self.params.gain.smoothed.next();
+            let ir_mix = self.params.ir_mix.smoothed.next();
 
-            for sample in channel_samples {
-                *sample *= gain;
+            for (channel, sample) in channel_samples.iter_mut().enumerate() {
+                let dry = *sample * gain;
+                let wet = self.impulse_response.process(channel, dry);
+                *sample = dry + (wet - dry) * ir_mix;
             }
+            self.impulse_response.advance();
         }
 
+        self.meter.process(buffer.as_slice_immutable());
+
         ProcessStatus::Normal
     }
 
-    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
-        let params = self.params.clone();
-        let gain_value_changed = self.params.gain_value_changed.clone();
-        let editor = WebViewEditor::new(HTMLSource::String(include_str!("gui.html")), (600, 600))
+    fn editor(&mut self, async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+        let bridge = ParamBridge::new(self.params.clone(), self.params.dirty_params.clone());
+        let dispatcher = Dispatcher::new(bridge, self.params.editor_state.clone());
+        let file_statuses = self.file_statuses.clone();
+        let meter_output = self.meter_output.clone();
+        let (html_source, asset_source) = ui_source();
+        let size = self.params.editor_state.size();
+        let editor = WebViewEditor::new(html_source, size)
             .with_background_color((150, 150, 150, 255))
-            .with_developer_mode(true)
+            .with_developer_mode(DEVELOPER_MODE)
+            .with_custom_protocol(assets::PROTOCOL_NAME.to_owned(), move |request| {
+                match asset_source.resolve(request.uri().path()) {
+                    Some(asset) => Response::builder()
+                        .header(CONTENT_TYPE, asset.mime_type)
+                        .body(asset.contents),
+                    None => Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Cow::Borrowed(&[][..])),
+                }
+                .map_err(Into::into)
+            })
             .with_event_loop(move |ctx, setter| {
                 while let Some(event) = ctx.next_event() {
-                    ctx.resize(700, 700);
-                    // match event {
-                    //     WebviewEvent::JSON(value) => {
-                    //         if let Ok(action) = serde_json::from_value(value) {
-                    //             match action {
-                    //                 Action::SetGain { value } => {
-                    //                     setter.begin_set_parameter(&params.gain);
-                    //                     setter.set_parameter_normalized(&params.gain, value);
-                    //                     setter.end_set_parameter(&params.gain);
-                    //                 }
-                    //                 Action::SetSize { width, height } => {
-                    //                     ctx.resize(700, 700);
-                    //                 }
-                    //                 Action::Init => {
-                    //                     let _ = ctx.send_json(json!({
-                    //                         "type": "set_size",
-                    //                         "width": ctx.width.load(Ordering::Relaxed),
-                    //                         "height": ctx.height.load(Ordering::Relaxed)
-                    //                     }));
-                    //                 }
-                    //             }
-                    //         } else {
-                    //             panic!("Invalid action received from web UI.")
-                    //         }
-                    //     }
-                    //     WebviewEvent::FileDropped(path) => println!("File dropped: {:?}", path),
-                    //     _ => {}
-                    // }
+                    match event {
+                        WebviewEvent::JSON(value) => dispatcher.handle_message(ctx, &setter, value),
+                        WebviewEvent::FileDropped(path) => {
+                            let status = match FileKind::from_path(&path) {
+                                Some(kind @ FileKind::ImpulseResponse) => {
+                                    async_executor.execute_background(Task::LoadImpulseResponse(
+                                        path.clone(),
+                                    ));
+                                    FileStatus::loading(&path, kind)
+                                }
+                                Some(kind @ FileKind::Preset) => {
+                                    match apply_preset(&path, setter.raw_context) {
+                                        Ok(()) => FileStatus::loaded(&path, kind),
+                                        Err(message) => {
+                                            nih_log!(
+                                                "Could not load '{}': {message}",
+                                                path.display()
+                                            );
+                                            FileStatus::failed(&path, Some(kind), message)
+                                        }
+                                    }
+                                }
+                                None => FileStatus::failed(
+                                    &path,
+                                    None,
+                                    String::from("Unsupported file type"),
+                                ),
+                            };
+                            file_statuses.lock().push(status);
+                        }
+                        _ => {}
+                    }
                 }
 
-                if gain_value_changed.swap(false, Ordering::Relaxed) {
-                    let _ = ctx.send_json(json!({
-                        "type": "param_change",
-                        "param": "gain",
-                        "value": params.gain.unmodulated_normalized_value(),
-                        "text": params.gain.to_string()
-                    }));
+                // Includes the results of impulse responses loaded on the background thread
+                let statuses = std::mem::take(&mut *file_statuses.lock());
+                for file in statuses {
+                    let _ = ctx.send_json(EditorMessage::FileStatus { file }.to_json());
                 }
+
+                // The meters are updated at a fixed rate, which is usually slower than the editor's
+                // frame rate
+                let mut meter_output = meter_output.lock();
+                if meter_output.update() {
+                    let meters = meter_output.output_buffer().to_meters();
+                    let _ = ctx.send_json(EditorMessage::Meters(meters).to_json());
+                }
+
+                dispatcher.send_changes(ctx);
             });
 
         Some(Box::new(editor))
@@ -166,6 +338,35 @@ impl Plugin for Gain {
     fn deactivate(&mut self) {}
 }
 
+/// Where the editor loads the UI from. See [`DEVELOPER_MODE`].
+fn ui_source() -> (HTMLSource, AssetSource) {
+    let embedded = (
+        HTMLSource::URL(assets::INDEX_URL),
+        AssetSource::Embedded(EMBEDDED_ASSETS),
+    );
+    if !DEVELOPER_MODE {
+        return embedded;
+    }
+
+    if let Ok(url) = env::var("GAIN_UI_DEV_URL") {
+        nih_log!("Loading the UI from {url}");
+        // This leaks the URL every time the editor is opened, which is fine for development builds
+        (
+            HTMLSource::URL(Box::leak(url.into_boxed_str())),
+            AssetSource::Embedded(EMBEDDED_ASSETS),
+        )
+    } else if let Some(dir) = env::var_os("GAIN_UI_DIR") {
+        let dir = PathBuf::from(dir);
+        nih_log!("Loading the UI from {}", dir.display());
+        (
+            HTMLSource::URL(assets::INDEX_URL),
+            AssetSource::Directory(dir),
+        )
+    } else {
+        embedded
+    }
+}
+
 impl ClapPlugin for Gain {
     const CLAP_ID: &'static str = "com.moist-plugins-gmbh.gain";
     const CLAP_DESCRIPTION: Option<&'static str> = Some("A smoothed gain parameter example plugin");
@@ -0,0 +1,361 @@
+//! Measures the plugin's output for the web UI's meters and spectrum analyzer. The audio thread
+//! fills in a [`MeterData`] a fixed number of times per second and publishes it through a triple
+//! buffer, so neither the audio thread nor the editor ever has to wait for the other. The editor
+//! sends the data to the web UI whenever a new update has been published.
+
+use realfft::num_complex::Complex32;
+use realfft::{RealFftPlanner, RealToComplex};
+use serde::Serialize;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use triple_buffer::TripleBuffer;
+#[cfg(test)]
+use ts_rs::TS;
+
+/// How many times per second the meters are updated.
+pub const UPDATE_RATE: f32 = 30.0;
+/// The most channels the meters are shown for.
+pub const MAX_CHANNELS: usize = 2;
+/// The number of logarithmically spaced bands the spectrum is reduced to before it's sent to the
+/// web UI.
+pub const SPECTRUM_BANDS: usize = 96;
+pub const SPECTRUM_MIN_FREQUENCY: f32 = 20.0;
+pub const SPECTRUM_MAX_FREQUENCY: f32 = 20_000.0;
+
+/// The number of samples the spectrum is computed from.
+const SPECTRUM_WINDOW_SIZE: usize = 2048;
+/// Levels are clamped to this value, in dBFS, so silence doesn't result in negative infinity.
+const MIN_DB: f32 = -100.0;
+
+/// A single update for the meters. All levels are in dBFS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeterData {
+    /// The number of channels in `peak` and `rms` that are in use.
+    pub num_channels: usize,
+    /// The highest absolute sample value since the previous update.
+    pub peak: [f32; MAX_CHANNELS],
+    /// The RMS level since the previous update.
+    pub rms: [f32; MAX_CHANNELS],
+    /// The magnitude spectrum of the channels' average, with the highest magnitude in each of the
+    /// bands spaced logarithmically between [`SPECTRUM_MIN_FREQUENCY`] and `max_frequency`.
+    pub spectrum: [f32; SPECTRUM_BANDS],
+    /// The upper end of the spectrum. This is below [`SPECTRUM_MAX_FREQUENCY`] at low sample
+    /// rates.
+    pub max_frequency: f32,
+}
+
+impl Default for MeterData {
+    fn default() -> Self {
+        Self {
+            num_channels: 0,
+            peak: [MIN_DB; MAX_CHANNELS],
+            rms: [MIN_DB; MAX_CHANNELS],
+            spectrum: [MIN_DB; SPECTRUM_BANDS],
+            max_frequency: SPECTRUM_MAX_FREQUENCY,
+        }
+    }
+}
+
+/// The contents of the `meters` message sent to the web UI. Levels are in dBFS, rounded to a tenth
+/// of a decibel to keep the messages small.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(test, derive(TS))]
+pub struct Meters {
+    /// One value per channel.
+    pub peak: Vec<f32>,
+    /// One value per channel.
+    pub rms: Vec<f32>,
+    pub spectrum: Spectrum,
+}
+
+/// The spectrum's bands are spaced logarithmically between `min_frequency` and `max_frequency`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(test, derive(TS))]
+pub struct Spectrum {
+    pub min_frequency: f32,
+    pub max_frequency: f32,
+    pub bands: Vec<f32>,
+}
+
+impl MeterData {
+    pub fn to_meters(&self) -> Meters {
+        let round = |db: &f32| (db * 10.0).round() / 10.0;
+
+        Meters {
+            peak: self.peak[..self.num_channels].iter().map(round).collect(),
+            rms: self.rms[..self.num_channels].iter().map(round).collect(),
+            spectrum: Spectrum {
+                min_frequency: SPECTRUM_MIN_FREQUENCY,
+                max_frequency: self.max_frequency,
+                bands: self.spectrum.iter().map(round).collect(),
+            },
+        }
+    }
+}
+
+/// Measures the audio on the audio thread. Everything is allocated up front, so
+/// [`process()`][Self::process()] doesn't allocate.
+pub struct Meter {
+    output: triple_buffer::Input<MeterData>,
+
+    /// The number of samples between updates.
+    update_interval: usize,
+    samples_since_update: usize,
+    num_channels: usize,
+    peak: [f32; MAX_CHANNELS],
+    sum_of_squares: [f32; MAX_CHANNELS],
+
+    /// The last [`SPECTRUM_WINDOW_SIZE`] samples of the channels' average, as a ring buffer.
+    spectrum_history: Vec<f32>,
+    /// The position in `spectrum_history` where the next sample is written.
+    spectrum_pos: usize,
+    fft: Arc<dyn RealToComplex<f32>>,
+    /// A Hann window, applied to the samples before taking the FFT.
+    window: Vec<f32>,
+    fft_input: Vec<f32>,
+    fft_output: Vec<Complex32>,
+    fft_scratch: Vec<Complex32>,
+    /// The range of FFT bins that make up each of the spectrum's bands at the current sample rate.
+    band_bins: [(usize, usize); SPECTRUM_BANDS],
+    max_frequency: f32,
+}
+
+impl Meter {
+    /// Create a meter along with the output the editor reads its updates from. The sample rate
+    /// needs to be set with [`set_sample_rate()`][Self::set_sample_rate()] before processing.
+    pub fn new() -> (Self, triple_buffer::Output<MeterData>) {
+        let (input, output) = TripleBuffer::new(&MeterData::default()).split();
+        let fft = RealFftPlanner::new().plan_fft_forward(SPECTRUM_WINDOW_SIZE);
+        let window = (0..SPECTRUM_WINDOW_SIZE)
+            .map(|idx| 0.5 - 0.5 * (2.0 * PI * idx as f32 / SPECTRUM_WINDOW_SIZE as f32).cos())
+            .collect();
+
+        let mut meter = Self {
+            output: input,
+
+            update_interval: 1,
+            samples_since_update: 0,
+            num_channels: 0,
+            peak: [0.0; MAX_CHANNELS],
+            sum_of_squares: [0.0; MAX_CHANNELS],
+
+            spectrum_history: vec![0.0; SPECTRUM_WINDOW_SIZE],
+            spectrum_pos: 0,
+            window,
+            fft_input: fft.make_input_vec(),
+            fft_output: fft.make_output_vec(),
+            fft_scratch: fft.make_scratch_vec(),
+            fft,
+            band_bins: [(0, 1); SPECTRUM_BANDS],
+            max_frequency: SPECTRUM_MAX_FREQUENCY,
+        };
+        meter.set_sample_rate(44100.0);
+
+        (meter, output)
+    }
+
+    /// Update the update interval and the spectrum's bands for a new sample rate. This also
+    /// resets the meter.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.update_interval = ((sample_rate / UPDATE_RATE).round() as usize).max(1);
+        self.max_frequency = SPECTRUM_MAX_FREQUENCY.min(sample_rate / 2.0);
+
+        let bin_width = sample_rate / SPECTRUM_WINDOW_SIZE as f32;
+        let num_bins = self.fft_output.len();
+        let ratio = self.max_frequency / SPECTRUM_MIN_FREQUENCY;
+        for (band_idx, bins) in self.band_bins.iter_mut().enumerate() {
+            let lower =
+                SPECTRUM_MIN_FREQUENCY * ratio.powf(band_idx as f32 / SPECTRUM_BANDS as f32);
+            let upper =
+                SPECTRUM_MIN_FREQUENCY * ratio.powf((band_idx + 1) as f32 / SPECTRUM_BANDS as f32);
+
+            let start = ((lower / bin_width).ceil() as usize).min(num_bins);
+            let end = ((upper / bin_width).ceil() as usize).min(num_bins);
+            *bins = if start < end {
+                (start, end)
+            } else {
+                // The lowest bands are narrower than a single bin, so they use the bin closest to
+                // their center frequency instead
+                let center =
+                    (((lower * upper).sqrt() / bin_width).round() as usize).min(num_bins - 1);
+                (center, center + 1)
+            };
+        }
+
+        self.reset();
+    }
+
+    /// Clear the accumulated levels and the spectrum's history.
+    pub fn reset(&mut self) {
+        self.samples_since_update = 0;
+        self.peak = [0.0; MAX_CHANNELS];
+        self.sum_of_squares = [0.0; MAX_CHANNELS];
+        self.spectrum_history.fill(0.0);
+        self.spectrum_pos = 0;
+    }
+
+    /// Measure a block of audio, publishing an update every time the update interval has passed.
+    /// Channels beyond [`MAX_CHANNELS`] are ignored.
+    pub fn process(&mut self, channels: &[&mut [f32]]) {
+        let channels = &channels[..channels.len().min(MAX_CHANNELS)];
+        let num_samples = channels.first().map_or(0, |channel| channel.len());
+        self.num_channels = channels.len();
+
+        for sample_idx in 0..num_samples {
+            let mut sum = 0.0;
+            for (channel_idx, channel) in channels.iter().enumerate() {
+                let sample = channel[sample_idx];
+                self.peak[channel_idx] = self.peak[channel_idx].max(sample.abs());
+                self.sum_of_squares[channel_idx] += sample * sample;
+                sum += sample;
+            }
+
+            self.spectrum_history[self.spectrum_pos] = sum / channels.len() as f32;
+            self.spectrum_pos = (self.spectrum_pos + 1) % SPECTRUM_WINDOW_SIZE;
+
+            self.samples_since_update += 1;
+            if self.samples_since_update >= self.update_interval {
+                self.publish();
+            }
+        }
+    }
+
+    fn publish(&mut self) {
+        let data = self.output.input_buffer();
+        let num_samples = self.samples_since_update as f32;
+        data.num_channels = self.num_channels;
+        for channel_idx in 0..MAX_CHANNELS {
+            data.peak[channel_idx] = gain_to_db(self.peak[channel_idx]);
+            data.rms[channel_idx] =
+                gain_to_db((self.sum_of_squares[channel_idx] / num_samples).sqrt());
+        }
+
+        self.samples_since_update = 0;
+        self.peak = [0.0; MAX_CHANNELS];
+        self.sum_of_squares = [0.0; MAX_CHANNELS];
+
+        // The history is a ring buffer, so the oldest sample is the one that will be overwritten
+        // next
+        let (newer, older) = self.spectrum_history.split_at(self.spectrum_pos);
+        for ((input, sample), window) in self
+            .fft_input
+            .iter_mut()
+            .zip(older.iter().chain(newer))
+            .zip(&self.window)
+        {
+            *input = sample * window;
+        }
+
+        // This only fails if the buffers have the wrong sizes
+        if self
+            .fft
+            .process_with_scratch(
+                &mut self.fft_input,
+                &mut self.fft_output,
+                &mut self.fft_scratch,
+            )
+            .is_ok()
+        {
+            // Scales a sine wave's bin to its amplitude, so a full scale sine shows up at 0 dBFS
+            let scale = 2.0 / (SPECTRUM_WINDOW_SIZE as f32 / 2.0);
+            for (band, &(start, end)) in data.spectrum.iter_mut().zip(&self.band_bins) {
+                let magnitude = self.fft_output[start..end]
+                    .iter()
+                    .map(|bin| bin.norm())
+                    .fold(0.0, f32::max);
+                *band = gain_to_db(magnitude * scale);
+            }
+        }
+        data.max_frequency = self.max_frequency;
+
+        self.output.publish();
+    }
+}
+
+fn gain_to_db(gain: f32) -> f32 {
+    (20.0 * gain.log10()).max(MIN_DB)
+}
+
+#[cfg(test)]
+fn sine(frequency: f32, amplitude: f32, sample_rate: f32, num_samples: usize) -> Vec<f32> {
+    (0..num_samples)
+        .map(|idx| amplitude * (2.0 * PI * frequency * idx as f32 / sample_rate).sin())
+        .collect()
+}
+
+#[test]
+fn test_levels() {
+    let sample_rate = 48000.0;
+    let (mut meter, mut output) = Meter::new();
+    meter.set_sample_rate(sample_rate);
+
+    // Exactly one update, with a whole number of periods
+    let mut left = sine(1000.0, 1.0, sample_rate, 1600);
+    let mut right = vec![-0.5; 1600];
+    meter.process(&[&mut left, &mut right]);
+
+    assert!(output.update());
+    let data = output.output_buffer();
+    assert_eq!(data.num_channels, 2);
+    assert!(data.peak[0].abs() < 0.01, "{:?}", data.peak);
+    assert!((data.rms[0] + 3.01).abs() < 0.01, "{:?}", data.rms);
+    assert!((data.peak[1] + 6.02).abs() < 0.01, "{:?}", data.peak);
+    assert!((data.rms[1] + 6.02).abs() < 0.01, "{:?}", data.rms);
+
+    // Nothing is published until the next update interval has passed
+    meter.process(&[&mut vec![0.0; 800], &mut vec![0.0; 800]]);
+    assert!(!output.update());
+    meter.process(&[&mut vec![0.0; 800], &mut vec![0.0; 800]]);
+    assert!(output.update());
+    assert_eq!(output.output_buffer().peak, [MIN_DB; MAX_CHANNELS]);
+}
+
+#[test]
+fn test_spectrum() {
+    let sample_rate = 44100.0;
+    let (mut meter, mut output) = Meter::new();
+    meter.set_sample_rate(sample_rate);
+
+    let frequency = 1000.0;
+    let mut samples = sine(frequency, 0.5, sample_rate, SPECTRUM_WINDOW_SIZE * 2);
+    meter.process(&[&mut samples]);
+
+    assert!(output.update());
+    let data = output.output_buffer();
+    let (loudest_band, loudest_db) = data
+        .spectrum
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap();
+    let band_frequency = SPECTRUM_MIN_FREQUENCY
+        * (data.max_frequency / SPECTRUM_MIN_FREQUENCY)
+            .powf((loudest_band as f32 + 0.5) / SPECTRUM_BANDS as f32);
+
+    assert!(
+        (band_frequency / frequency).log2().abs() < 0.1,
+        "{band_frequency} Hz"
+    );
+    // A Hann window loses up to 1.42 dB when the frequency falls between two bins
+    assert!((-7.5..=-5.5).contains(loudest_db), "{loudest_db} dB");
+    assert!(data.spectrum[0] < -60.0, "{:?}", data.spectrum);
+    assert!(
+        data.spectrum[SPECTRUM_BANDS - 1] < -60.0,
+        "{:?}",
+        data.spectrum
+    );
+}
+
+#[test]
+fn test_to_meters() {
+    let mut data = MeterData {
+        num_channels: 1,
+        ..MeterData::default()
+    };
+    data.peak[0] = -3.0103;
+
+    let meters = data.to_meters();
+    assert_eq!(meters.peak, [-3.0]);
+    assert_eq!(meters.rms, [MIN_DB]);
+    assert_eq!(meters.spectrum.bands.len(), SPECTRUM_BANDS);
+}
@@ -1,35 +1,143 @@
-import { useState } from 'react'
-import reactLogo from './assets/react.svg'
-import viteLogo from '/vite.svg'
+import { useEffect, useState } from 'react'
+import LevelMeter from './components/LevelMeter'
+import ParamSlider from './components/ParamSlider'
+import ResizeCorner from './components/ResizeCorner'
+import Spectrum from './components/Spectrum'
+import {
+  addPluginListener,
+  FileStatus,
+  Meters,
+  ParamInfo,
+  PROTOCOL_VERSION,
+  sendToPlugin,
+} from './plugin'
+import { DEFAULT_UI_STATE, parseUiState, TABS, UiState, ZOOM_LEVELS } from './uiState'
 import './App.css'
 
 function App() {
-  const [count, setCount] = useState(0)
+  const [params, setParams] = useState<ParamInfo[]>([])
+  // The modulated value displays, keyed by parameter ID
+  const [modulatedTexts, setModulatedTexts] = useState<Record<string, string>>({})
+  const [size, setSize] = useState({ width: 0, height: 0 })
+  // The most recently dropped file
+  const [droppedFile, setDroppedFile] = useState<FileStatus | null>(null)
+  const [meters, setMeters] = useState<Meters | null>(null)
+  const [uiState, setUiState] = useState<UiState>(DEFAULT_UI_STATE)
+
+  useEffect(() => {
+    const removeListener = addPluginListener((message) => {
+      switch (message.type) {
+        case 'param_list':
+          setParams(message.params)
+          setModulatedTexts({})
+          break
+        case 'params_changed': {
+          const changes = new Map(message.params.map((change) => [change.param, change]))
+          setParams((params) =>
+            params.map((param) => {
+              const change = changes.get(param.id)
+              return change ? { ...param, value: change.value, text: change.text } : param
+            }),
+          )
+          setModulatedTexts((texts) => ({
+            ...texts,
+            ...Object.fromEntries(
+              message.params.map((change) => [change.param, change.modulated_text]),
+            ),
+          }))
+          break
+        }
+        case 'ui_state':
+          setUiState(parseUiState(message.state))
+          break
+        case 'set_size':
+          setSize({ width: message.width, height: message.height })
+          break
+        case 'file_status':
+          setDroppedFile(message.file)
+          break
+        case 'meters':
+          setMeters(message)
+          break
+      }
+    })
+
+    sendToPlugin({ type: 'Init', version: PROTOCOL_VERSION })
+
+    return removeListener
+  }, [])
+
+  const updateUiState = (changes: Partial<UiState>) => {
+    const newUiState = { ...uiState, ...changes }
+    setUiState(newUiState)
+    sendToPlugin({ type: 'SetUiState', state: newUiState })
+  }
 
   return (
     <>
-      <div>
-        <a href="https://vitejs.dev" target="_blank">
-          <img src={viteLogo} className="logo" alt="Vite logo" />
-        </a>
-        <a href="https://react.dev" target="_blank">
-          <img src={reactLogo} className="logo react" alt="React logo" />
-        </a>
-      </div>
-      <h1>Vite + React</h1>
-      <div className="card">
-        <button onClick={() => setCount((count) => count + 1)}>
-          count is {count}
-        </button>
-        <p>
-          Edit <code>src/App.tsx</code> and save to test HMR
-        </p>
+      <div className="app" style={{ zoom: uiState.zoom }}>
+        <header>
+          <h1>Gain</h1>
+          <nav className="tabs">
+            {TABS.map((tab) => (
+              <button
+                key={tab.id}
+                className={tab.id === uiState.tab ? 'active' : ''}
+                onClick={() => updateUiState({ tab: tab.id })}
+              >
+                {tab.label}
+              </button>
+            ))}
+          </nav>
+          <select
+            className="zoom"
+            value={uiState.zoom}
+            onChange={(e) => updateUiState({ zoom: Number(e.target.value) })}
+          >
+            {ZOOM_LEVELS.map((zoom) => (
+              <option key={zoom} value={zoom}>
+                {zoom * 100}%
+              </option>
+            ))}
+          </select>
+        </header>
+
+        {uiState.tab === 'controls' ? (
+          <>
+            {params.map((param) => (
+              <ParamSlider key={param.id} param={param} modulatedText={modulatedTexts[param.id]} />
+            ))}
+            <p className={`drop-status ${droppedFile?.status ?? ''}`}>
+              {droppedFile
+                ? describeFileStatus(droppedFile)
+                : 'Drop an impulse response or a preset here'}
+            </p>
+          </>
+        ) : (
+          meters && (
+            <>
+              <LevelMeter peak={meters.peak} rms={meters.rms} />
+              <Spectrum spectrum={meters.spectrum} />
+            </>
+          )
+        )}
       </div>
-      <p className="read-the-docs">
-        Click on the Vite and React logos to learn more
-      </p>
+      <ResizeCorner width={size.width} height={size.height} />
     </>
   )
 }
 
+function describeFileStatus({ path, kind, status, message }: FileStatus) {
+  const name = path.split(/[\\/]/).pop()
+  const what = kind === 'preset' ? 'preset' : 'impulse response'
+  switch (status) {
+    case 'loading':
+      return `Loading ${what} ${name}…`
+    case 'loaded':
+      return `Loaded ${what} ${name}`
+    case 'failed':
+      return `Could not load ${name}: ${message}`
+  }
+}
+
 export default App
@@ -0,0 +1,82 @@
+// Generated from the plugin's Rust types, don't edit this by hand. Run
+// `UPDATE_BINDINGS=1 cargo test` after changing any of the messages.
+
+export const PROTOCOL_VERSION = 1
+
+export type Action = { "type": "Init", version: number, } | { "type": "SetSize", width: number, height: number, } | { "type": "BeginSetParameter", param: string, } | { "type": "SetParameter", param: string, value: number, } | { "type": "EndSetParameter", param: string, } | { "type": "SetUiState", state: JsonValue, };
+
+export type PluginMessage = { "type": "param_list", version: number, params: Array<ParamInfo>, } | { "type": "params_changed", params: Array<ParamChange>, } | { "type": "ui_state", state: JsonValue, } | { "type": "set_size", width: number, height: number, } | { "type": "error", version: number, code: string, message: string, };
+
+export type ParamInfo = { id: string, name: string, 
+/**
+ * The parameter's group, or an empty string for top-level parameters.
+ */
+group: string, unit: string, 
+/**
+ * The plain value at normalized value 0.0.
+ */
+min: number, 
+/**
+ * The plain value at normalized value 1.0.
+ */
+max: number, 
+/**
+ * The number of steps for discrete parameters, or `None` for continuous parameters.
+ */
+step_count: number | null, default_value: number, automatable: boolean, 
+/**
+ * The current unmodulated normalized value.
+ */
+value: number, 
+/**
+ * The current value formatted with the parameter's unit.
+ */
+text: string, };
+
+export type ParamChange = { param: string, 
+/**
+ * The new unmodulated normalized value.
+ */
+value: number, 
+/**
+ * The new value formatted with the parameter's unit.
+ */
+text: string, 
+/**
+ * The normalized value with monophonic modulation applied.
+ */
+modulated_value: number, 
+/**
+ * The modulated value formatted with the parameter's unit.
+ */
+modulated_text: string, };
+
+export type JsonValue = number | string | boolean | Array<JsonValue> | { [key in string]?: JsonValue } | null;
+
+export type EditorMessage = { "type": "file_status", file: FileStatus, } | { "type": "meters" } & Meters;
+
+export type FileStatus = { path: string, 
+/**
+ * `None` for files with an unsupported extension.
+ */
+kind: FileKind | null, status: LoadStatus, 
+/**
+ * The reason loading the file failed.
+ */
+message: string | null, };
+
+export type FileKind = "impulse_response" | "preset";
+
+export type LoadStatus = "loading" | "loaded" | "failed";
+
+export type Meters = { 
+/**
+ * One value per channel.
+ */
+peak: Array<number>, 
+/**
+ * One value per channel.
+ */
+rms: Array<number>, spectrum: Spectrum, };
+
+export type Spectrum = { min_frequency: number, max_frequency: number, bands: Array<number>, };
@@ -0,0 +1,50 @@
+import { useEffect, useRef, useState } from 'react'
+
+const MIN_DB = -60
+// How quickly the peak hold falls back down, in decibels per second
+const PEAK_HOLD_DECAY = 20
+
+interface LevelMeterProps {
+  // The levels since the previous update for every channel, in dBFS
+  peak: number[]
+  rms: number[]
+}
+
+// A horizontal bar for every channel showing the RMS level, with a marker for the recent peak level
+function LevelMeter({ peak, rms }: LevelMeterProps) {
+  const [peakHold, setPeakHold] = useState<number[]>([])
+  const lastUpdate = useRef(performance.now())
+
+  useEffect(() => {
+    const now = performance.now()
+    const decay = ((now - lastUpdate.current) / 1000) * PEAK_HOLD_DECAY
+    lastUpdate.current = now
+
+    setPeakHold((held) =>
+      peak.map((level, channel) => Math.max(level, (held[channel] ?? MIN_DB) - decay)),
+    )
+  }, [peak])
+
+  const toPercent = (db: number) => `${Math.min(Math.max((db - MIN_DB) / -MIN_DB, 0), 1) * 100}%`
+
+  return (
+    <div className="level-meter">
+      {rms.map((level, channel) => (
+        <div key={channel} className="level-meter-channel">
+          <div className="level-meter-rms" style={{ width: toPercent(level) }} />
+          <div
+            className={`level-meter-peak ${(peakHold[channel] ?? MIN_DB) >= 0 ? 'clipping' : ''}`}
+            style={{ left: toPercent(peakHold[channel] ?? MIN_DB) }}
+          />
+        </div>
+      ))}
+      <span className="level-meter-value">
+        {peakHold.length > 0 && Math.max(...peakHold) > MIN_DB
+          ? `${Math.max(...peakHold).toFixed(1)} dBFS`
+          : '-inf dBFS'}
+      </span>
+    </div>
+  )
+}
+
+export default LevelMeter
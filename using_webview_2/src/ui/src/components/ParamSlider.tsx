@@ -0,0 +1,53 @@
+import { useRef } from 'react'
+import { ParamInfo, sendToPlugin, setParameter } from '../plugin'
+
+interface ParamSliderProps {
+  param: ParamInfo
+  // The value display, including where modulation currently takes the parameter
+  modulatedText?: string
+}
+
+// A horizontal slider for a parameter. Dragging the slider is a single automation gesture, keyboard
+// input sets the value in one go, and double clicking resets the parameter to its default value.
+function ParamSlider({ param, modulatedText }: ParamSliderProps) {
+  const dragging = useRef(false)
+
+  const onInput = (value: number) => {
+    if (dragging.current) {
+      sendToPlugin({ type: 'SetParameter', param: param.id, value })
+    } else {
+      setParameter(param.id, value)
+    }
+  }
+
+  return (
+    <label className="param-slider">
+      <span className="param-slider-name">{param.name}</span>
+      <input
+        type="range"
+        min={0}
+        max={1}
+        // Stepped parameters snap to their steps, continuous parameters get a fine resolution
+        step={param.step_count ? 1 / param.step_count : 0.001}
+        value={param.value}
+        onPointerDown={() => {
+          dragging.current = true
+          sendToPlugin({ type: 'BeginSetParameter', param: param.id })
+        }}
+        onPointerUp={() => {
+          dragging.current = false
+          sendToPlugin({ type: 'EndSetParameter', param: param.id })
+        }}
+        onChange={(e) => onInput(Number(e.target.value))}
+        onDoubleClick={() => setParameter(param.id, param.default_value)}
+      />
+      <span className="param-slider-value">
+        {modulatedText && modulatedText !== param.text
+          ? `${param.text} → ${modulatedText}`
+          : param.text}
+      </span>
+    </label>
+  )
+}
+
+export default ParamSlider
@@ -0,0 +1,53 @@
+import { useEffect, useRef } from 'react'
+import { sendToPlugin } from '../plugin'
+
+const MIN_SIZE = 100
+
+interface ResizeCornerProps {
+  width: number
+  height: number
+}
+
+// A handle in the bottom right corner for resizing the editor
+function ResizeCorner({ width, height }: ResizeCornerProps) {
+  // The pointer position and editor size when the drag started
+  const dragStart = useRef<{ x: number; y: number; width: number; height: number } | null>(null)
+
+  useEffect(() => {
+    const onPointerMove = (e: PointerEvent) => {
+      const start = dragStart.current
+      if (start) {
+        sendToPlugin({
+          type: 'SetSize',
+          width: Math.max(MIN_SIZE, Math.round(start.width + e.clientX - start.x)),
+          height: Math.max(MIN_SIZE, Math.round(start.height + e.clientY - start.y)),
+        })
+      }
+    }
+    const onPointerUp = () => {
+      dragStart.current = null
+    }
+
+    window.addEventListener('pointermove', onPointerMove)
+    window.addEventListener('pointerup', onPointerUp)
+    return () => {
+      window.removeEventListener('pointermove', onPointerMove)
+      window.removeEventListener('pointerup', onPointerUp)
+    }
+  }, [])
+
+  return (
+    <div
+      className="resize-corner"
+      onPointerDown={(e) => {
+        dragStart.current = { x: e.clientX, y: e.clientY, width, height }
+      }}
+    >
+      <svg viewBox="0 0 10 10" width="10" height="10">
+        <path d="M 10 0 L 10 10 L 0 10 Z" />
+      </svg>
+    </div>
+  )
+}
+
+export default ResizeCorner
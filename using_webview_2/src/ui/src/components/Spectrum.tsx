@@ -0,0 +1,65 @@
+import { useEffect, useRef } from 'react'
+import { Meters } from '../plugin'
+
+const MIN_DB = -90
+// How quickly the displayed spectrum falls back down, as a fraction of the difference per update
+const RELEASE = 0.3
+
+interface SpectrumProps {
+  spectrum: Meters['spectrum']
+}
+
+// A spectrum analyser drawn on a canvas. Rising levels are shown right away, falling levels are
+// smoothed so the display doesn't flicker.
+function Spectrum({ spectrum }: SpectrumProps) {
+  const canvas = useRef<HTMLCanvasElement>(null)
+  const smoothed = useRef<number[]>([])
+
+  useEffect(() => {
+    const context = canvas.current?.getContext('2d')
+    if (!canvas.current || !context) {
+      return
+    }
+
+    smoothed.current = spectrum.bands.map((db, band) => {
+      const previous = smoothed.current[band] ?? MIN_DB
+      return db >= previous ? db : previous + (db - previous) * RELEASE
+    })
+
+    const { width, height } = canvas.current
+    const toY = (db: number) => (Math.min(Math.max(db, MIN_DB), 0) / MIN_DB) * height
+    context.clearRect(0, 0, width, height)
+
+    // Grid lines every 20 dB
+    context.strokeStyle = '#ccc'
+    for (let db = -20; db > MIN_DB; db -= 20) {
+      context.beginPath()
+      context.moveTo(0, toY(db))
+      context.lineTo(width, toY(db))
+      context.stroke()
+    }
+
+    const bandWidth = width / smoothed.current.length
+    context.beginPath()
+    context.moveTo(0, height)
+    smoothed.current.forEach((db, band) => {
+      context.lineTo((band + 0.5) * bandWidth, toY(db))
+    })
+    context.lineTo(width, height)
+    context.closePath()
+    context.fillStyle = 'rgba(60, 100, 200, 0.5)'
+    context.fill()
+  }, [spectrum])
+
+  return (
+    <figure className="spectrum">
+      <canvas ref={canvas} width={512} height={160} />
+      <figcaption>
+        <span>{Math.round(spectrum.min_frequency)} Hz</span>
+        <span>{Math.round(spectrum.max_frequency / 1000)} kHz</span>
+      </figcaption>
+    </figure>
+  )
+}
+
+export default Spectrum
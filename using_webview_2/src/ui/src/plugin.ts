@@ -0,0 +1,56 @@
+// Helpers for talking to the plugin. The message types are generated from the plugin's Rust types
+// in `bindings.ts`.
+import type { Action, EditorMessage, PluginMessage } from './bindings'
+
+export { PROTOCOL_VERSION } from './bindings'
+export type {
+  Action,
+  EditorMessage,
+  FileStatus,
+  Meters,
+  ParamChange,
+  ParamInfo,
+  PluginMessage,
+} from './bindings'
+
+// Everything the plugin sends. The shared messages are the same for every webview plugin, and this
+// plugin adds its own messages for dropped files and the meters.
+export type Message = PluginMessage | EditorMessage
+
+// Provided by the webview editor
+declare global {
+  interface Window {
+    sendToPlugin: (action: Action) => void
+    onPluginMessage: (message: Message) => void
+  }
+}
+
+export function sendToPlugin(action: Action) {
+  window.sendToPlugin(action)
+}
+
+const listeners = new Set<(message: Message) => void>()
+
+window.onPluginMessage = (message) => {
+  if (message.type === 'error') {
+    console.error(`Plugin rejected a message (${message.code}): ${message.message}`)
+  }
+
+  listeners.forEach((listener) => listener(message))
+}
+
+// Call `listener` for every message from the plugin. Returns a function that removes the listener
+// again, so this can be returned from a `useEffect()` hook.
+export function addPluginListener(listener: (message: Message) => void) {
+  listeners.add(listener)
+  return () => {
+    listeners.delete(listener)
+  }
+}
+
+// Set a parameter as a single automation gesture
+export function setParameter(param: string, value: number) {
+  sendToPlugin({ type: 'BeginSetParameter', param })
+  sendToPlugin({ type: 'SetParameter', param, value })
+  sendToPlugin({ type: 'EndSetParameter', param })
+}
@@ -0,0 +1,33 @@
+import type { JsonValue } from './bindings'
+
+export type Tab = 'controls' | 'analyzer'
+
+export const TABS: { id: Tab; label: string }[] = [
+  { id: 'controls', label: 'Controls' },
+  { id: 'analyzer', label: 'Analyzer' },
+]
+export const ZOOM_LEVELS = [0.75, 1, 1.25, 1.5]
+
+// The UI's own state, which the plugin saves together with its own state
+export type UiState = {
+  tab: Tab
+  zoom: number
+}
+
+export const DEFAULT_UI_STATE: UiState = { tab: 'controls', zoom: 1 }
+
+// Turn the state the plugin sent back into a `UiState`. The plugin doesn't check what the UI
+// stores, so anything missing or unknown, like a state saved by an older version of the UI, falls
+// back to the defaults.
+export function parseUiState(state: JsonValue): UiState {
+  const saved: { [key in string]?: JsonValue } =
+    state !== null && typeof state === 'object' && !Array.isArray(state) ? state : {}
+
+  return {
+    tab: TABS.find((tab) => tab.id === saved.tab)?.id ?? DEFAULT_UI_STATE.tab,
+    zoom:
+      typeof saved.zoom === 'number' && ZOOM_LEVELS.includes(saved.zoom)
+        ? saved.zoom
+        : DEFAULT_UI_STATE.zoom,
+  }
+}